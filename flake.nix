@@ -62,6 +62,11 @@
             program = "${self.packages.${system}.default}/bin/url-gc";
             meta.description = "The stoopid short expired URL garbage collection";
           };
+          migrate = {
+            type = "app";
+            program = "${self.packages.${system}.default}/bin/migrate";
+            meta.description = "The stoopid short database migration runner";
+          };
         };
 
         devShells.default = pkgs.mkShell {
@@ -44,6 +44,34 @@ check_get() {
   fi
 }
 
+check_head() {
+  local url="http://$ADDR/$1"
+  local expected_status="$2"
+
+  echo "HEAD $url should return $expected_status"
+
+  local status="$(curl -sS -o /dev/null -I -w "%{http_code}" "$url")"
+
+  if [[ "$status" != "$expected_status" ]]; then
+    echo "expected HTTP $expected_status, got $status"
+    return 1
+  fi
+}
+
+check_preview() {
+  local url="http://$ADDR/$1"
+  local expected_long_url="$2"
+
+  echo "GET $url should preview $expected_long_url"
+
+  local long_url="$(curl -sS "$url" | jq -r '.long_url')"
+
+  if [[ "$long_url" != "$expected_long_url" ]]; then
+    echo "expected a preview of $expected_long_url, got $long_url"
+    return 1
+  fi
+}
+
 check_put() {
   local url="http://$ADDR/$1"
   local expected_status="$2"
@@ -65,8 +93,8 @@ check_put() {
 }
 
 check_post() {
-  local body="$1"
-  local expected_status=200
+  local expected_status="$1"
+  local body="$2"
   local url="http://$ADDR/"
   local response_file="$(mktemp)"
 
@@ -111,16 +139,10 @@ retry --until=success --delay=1 --times=5 -- pg_isready -h localhost -p "$DB_POR
 
 echo "Creating database $DB_NAME"
 createdb -h localhost -p "$DB_PORT" -U "$DB_USER" "$DB_NAME"
-psql -h localhost -p "$DB_PORT" -U "$DB_USER" "$DB_NAME" <<SQL
-CREATE TABLE IF NOT EXISTS urls (
-  id TEXT PRIMARY KEY NOT NULL,
-  long_url TEXT NOT NULL,
-  expiration_time_seconds BIGINT NOT NULL
-);
 
-CREATE INDEX IF NOT EXISTS idx_urls_expiration_time_seconds
-  ON urls (expiration_time_seconds);
-SQL
+echo "Migrating database (twice, since migrating should be idempotent)"
+migrate up
+migrate up
 
 echo "Starting server"
 stoopid-short-server &
@@ -131,27 +153,35 @@ retry --until=success --delay=1 --times=5 -- curl -s "$ADDR/health"
 
 echo "Running test cases"
 
+check_get "" 405 ""
+check_get "/" 400 ""
+
 TEST_ID="validid"
 check_get $TEST_ID 404 ""
+check_head $TEST_ID 404
 check_put $TEST_ID 201 '{"url":"https://example.com/", "expiration_timestamp":"2000-01-01T00:00:10Z"}'
 check_put $TEST_ID 200 '{"url":"https://example.com/", "expiration_timestamp":"2000-01-01T00:00:10Z"}'
 check_put $TEST_ID 409 '{"url":"https://example.com/", "expiration_timestamp":"2000-01-01T00:00:11Z"}'
 check_get $TEST_ID 307 "https://example.com/"
+check_head $TEST_ID 307
 set_faketime "2000-01-01 00:00:11"
-check_get $TEST_ID 404 ""
+check_get $TEST_ID 410 ""
+check_head $TEST_ID 410
 check_put $TEST_ID 201 '{"url":"https://example.com/new-url", "expiration_timestamp":"2001-01-01T00:00:00Z"}'
 check_get $TEST_ID 307 "https://example.com/new-url"
+check_preview "$TEST_ID+" "https://example.com/new-url"
+check_preview "$TEST_ID%2B" "https://example.com/new-url"
 set_faketime "2002-01-01 00:00:00"
-check_get $TEST_ID 404 ""
+check_get $TEST_ID 410 ""
 
-POSTED_ID="$(check_post '{"url":"https://example.com/", "expiration_timestamp":"2010-01-01T00:00:00Z"}')"
-POSTED_ID_2="$(check_post '{"url":"https://example.com/", "expiration_timestamp":"2010-01-01T00:00:00Z"}')"
+POSTED_ID="$(check_post 201 '{"url":"https://example.com/", "expiration_timestamp":"2010-01-01T00:00:00Z"}')"
+POSTED_ID_2="$(check_post 200 '{"url":"https://example.com/", "expiration_timestamp":"2010-01-01T00:00:00Z"}')"
 if [[ "$POSTED_ID" != "$POSTED_ID_2" ]]; then
   echo "POST requests did not dedupe: $POSTED_ID $POSTED_ID_2"
   exit 1
 fi
 check_get $POSTED_ID 307 "https://example.com/"
 set_faketime "2020-01-01 00:00:00"
-check_get $POSTED_ID 404 ""
+check_get $POSTED_ID 410 ""
 
 echo "E2E test successful"
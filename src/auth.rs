@@ -0,0 +1,123 @@
+use axum::http::{HeaderValue, header::AUTHORIZATION};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ApiKeyError {
+    #[error("missing bearer token in the {AUTHORIZATION} header")]
+    Missing,
+    #[error("invalid API key")]
+    Mismatched,
+}
+
+/// The API keys that write operations accept, any one of which may be presented
+/// (e.g. one per tenant).
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKeys(Vec<String>);
+impl ApiKeys {
+    /// The keys of a comma-separated list, if it has any.
+    #[must_use]
+    pub fn parse(keys: &str) -> Option<Self> {
+        let keys = keys
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        (!keys.is_empty()).then_some(Self(keys))
+    }
+}
+/// NOTE: the keys themselves are never logged
+impl std::fmt::Debug for ApiKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ApiKeys").field(&self.0.len()).finish()
+    }
+}
+
+/// Checks that an `Authorization` header carries `Bearer <api_key>` for one of `api_keys`,
+/// returning that key's [`api_key_id`].
+///
+/// # Errors
+/// Returns [`ApiKeyError::Missing`] when there is no bearer token,
+/// or [`ApiKeyError::Mismatched`] when the token is none of `api_keys`.
+pub fn check_api_key(
+    api_keys: &ApiKeys,
+    authorization: Option<&HeaderValue>,
+) -> Result<String, ApiKeyError> {
+    let token = authorization
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .ok_or(ApiKeyError::Missing)?;
+
+    // NOTE: blake3::Hash equality is constant-time, and hashing first
+    // also keeps the comparison from leaking the key's length
+    let token_hash = blake3::hash(token.as_bytes());
+    api_keys
+        .0
+        .iter()
+        .find(|api_key| token_hash == blake3::hash(api_key.as_bytes()))
+        .map(|api_key| api_key_id(api_key))
+        .ok_or(ApiKeyError::Mismatched)
+}
+
+/// A stable identifier for `api_key` that doesn't reveal it,
+/// for telling the requests (and short URLs) of different keys apart.
+#[must_use]
+pub fn api_key_id(api_key: &str) -> String {
+    blake3::hash(api_key.as_bytes()).to_hex()[..16].to_owned()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const API_KEYS: &str = "s3cr3t-key, other-key";
+
+    fn check(authorization: Option<&'static str>) -> Result<String, ApiKeyError> {
+        check_api_key(
+            &ApiKeys::parse(API_KEYS).unwrap(),
+            authorization.map(HeaderValue::from_static).as_ref(),
+        )
+    }
+
+    #[test]
+    fn test_valid_key() {
+        assert_eq!(
+            check(Some("Bearer s3cr3t-key")),
+            Ok(api_key_id("s3cr3t-key"))
+        );
+        assert_eq!(check(Some("Bearer other-key")), Ok(api_key_id("other-key")));
+        assert_ne!(api_key_id("s3cr3t-key"), api_key_id("other-key"));
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(ApiKeys::parse(" , "), None);
+        assert_eq!(
+            ApiKeys::parse("a,b ,"),
+            Some(ApiKeys(vec!["a".to_owned(), "b".to_owned()]))
+        );
+    }
+
+    #[test]
+    fn test_missing_key() {
+        assert_eq!(check(None), Err(ApiKeyError::Missing));
+        assert_eq!(
+            check(Some("Basic czNjcjN0LWtleQ==")),
+            Err(ApiKeyError::Missing)
+        );
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        assert_eq!(
+            check(Some("Bearer wrong-key")),
+            Err(ApiKeyError::Mismatched)
+        );
+        assert_eq!(
+            check(Some("Bearer s3cr3t-key2")),
+            Err(ApiKeyError::Mismatched)
+        );
+        assert_eq!(check(Some("Bearer ")), Err(ApiKeyError::Mismatched));
+    }
+}
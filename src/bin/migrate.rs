@@ -0,0 +1,24 @@
+use anyhow::{Context, bail};
+use rearch::Container;
+use sea_orm::Database;
+use stoopid_short::{config, migration};
+use tracing::info;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    config::init_tracing();
+
+    // NOTE: connects directly rather than through config::init_container,
+    // since that doesn't need (or, with REPO=memory, even open) the database
+    let db_connection_options = Container::new().read(config::db_connection_options_capsule);
+    info!(?db_connection_options, "Connecting to database");
+    let db = Database::connect(db_connection_options)
+        .await
+        .context("Failed to connect to database")?;
+
+    match std::env::args().nth(1).as_deref() {
+        None | Some("up") => migration::up(&db).await,
+        Some("down") => migration::down(&db).await,
+        Some(direction) => bail!("unknown migration direction {direction}; expected up or down"),
+    }
+}
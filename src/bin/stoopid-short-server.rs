@@ -1,81 +1,669 @@
+use std::{collections::HashMap, net::SocketAddr, num::NonZeroU32, time::Instant};
+
 use axum::{
-    Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
+    Form, Json, Router,
+    extract::{
+        ConnectInfo, DefaultBodyLimit, FromRequest, FromRequestParts, MatchedPath, Path, Query,
+        Request, State, rejection::PathRejection,
+    },
+    http::{HeaderMap, Method, StatusCode, header, request::Parts},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
     routing,
 };
 use rearch::Container;
 use serde::Serialize;
 use stoopid_short::{
+    auth::{ApiKeyError, ApiKeys, check_api_key},
     config,
-    url_service::{self, GetUrlError, PostUrlError, PutUrlError, url_rest_service_capsule},
+    metrics::metrics_capsule,
+    openapi,
+    rate_limit::{RateLimitKey, rate_limiter_capsule},
+    url_repo::{NAMESPACE_SEPARATOR, ShortIdValidationError, url_repository_capsule},
+    url_service::{
+        self, DeleteUrlError, ExpireUrlError, GetUrlError, GetUrlStatsError, HealthStatus,
+        ListUrlsError, PostUrlError, PostUrlsError, PostUrlsItem, PutPrecondition, PutUrlError,
+        QrCodeError, ShortenedUrl, SitemapError, UrlCreationStatus, UrlStats,
+        url_rest_service_capsule,
+    },
 };
-use tokio::net::TcpListener;
-use tracing::{error, info, instrument};
+use tokio::{net::TcpListener, signal, sync::oneshot};
+use tracing::{error, info, instrument, warn};
+use url::Url;
 use uuid::Uuid;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+    config::init_tracing();
 
     let container = config::init_container().await?;
 
-    let app = Router::new()
-        .route("/", routing::post(post_url))
-        .route("/health", routing::get(health))
-        .route("/{id}", routing::get(get_url).put(put_url))
-        .with_state(container.clone());
+    // NOTE: read configuration eagerly so that invalid values fail fast at startup
+    container.read((
+        url_rest_service_capsule,
+        config::interstitial_capsule,
+        config::debug_headers_capsule,
+        config::redirect_status_capsule,
+        config::shutdown_grace_period_capsule,
+        config::namespaces_capsule,
+        config::api_key_capsule,
+        rate_limiter_capsule,
+    ));
+    container.read((
+        config::root_redirect_url_capsule,
+        config::max_body_bytes_capsule,
+    ));
+
+    if let Some(metrics) = container.read(metrics_capsule)
+        && let Some(period) = container.read(config::expired_urls_sample_interval_capsule)
+    {
+        let url_repo = container.read(url_repository_capsule);
+        tokio::spawn(async move { metrics.sample_expired_urls(url_repo.as_ref(), period).await });
+    }
+
+    let app = router(&container);
 
     let listener = TcpListener::bind(container.read(config::addr_capsule)).await?;
     info!(addr = %listener.local_addr()?, "Started listening on TCP");
-    axum::serve(listener, app).await?;
+
+    let grace_period = container.read(config::shutdown_grace_period_capsule);
+    let (shutdown_started_tx, shutdown_started_rx) = oneshot::channel();
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        info!(
+            ?grace_period,
+            "Shutting down; waiting for in-flight requests"
+        );
+        let _ = shutdown_started_tx.send(());
+    });
+    let mut server = std::pin::pin!(server.into_future());
+    tokio::select! {
+        result = &mut server => result?,
+        // NOTE: the grace period only starts counting once shutdown has begun,
+        // and the server keeps draining in-flight requests until it runs out
+        Ok(()) = shutdown_started_rx => {
+            if let Ok(result) = tokio::time::timeout(grace_period, &mut server).await {
+                result?;
+            } else {
+                warn!("Grace period elapsed; dropping remaining in-flight requests");
+            }
+        }
+    }
+    info!("Shutdown complete");
     Ok(())
 }
 
-#[instrument]
-async fn health() -> impl IntoResponse {
+/// Routes every endpoint, reading which optional ones are enabled from `container`.
+fn router(container: &Container) -> Router {
+    // NOTE: a MethodRouter's route_layer only wraps the methods added before it,
+    // so reads like GET and HEAD stay public (and unaffected by the body limit)
+    let api_key_layer = middleware::from_fn_with_state(container.clone(), require_api_key);
+    let body_limit = DefaultBodyLimit::max(container.read(config::max_body_bytes_capsule));
+    let bulk_body_limit =
+        DefaultBodyLimit::max(container.read(config::max_bulk_body_bytes_capsule));
+    let mut app = Router::new()
+        .route(
+            "/",
+            routing::post(post_url)
+                .route_layer(api_key_layer.clone())
+                .route_layer(body_limit)
+                .get(get_root),
+        )
+        // NOTE: an empty short ID would otherwise fall through to an empty 404
+        .route("//", routing::get(missing_short_id))
+        .route(
+            "/bulk",
+            routing::post(post_urls)
+                .route_layer(api_key_layer.clone())
+                .route_layer(bulk_body_limit),
+        )
+        .route("/health", routing::get(health))
+        .route("/policy", routing::get(policy))
+        .route("/openapi.json", routing::get(openapi_spec))
+        .route("/sitemap.xml", routing::get(sitemap))
+        .route(
+            "/{id}",
+            routing::put(put_url)
+                .delete(delete_url)
+                .route_layer(api_key_layer.clone())
+                .route_layer(body_limit)
+                .get(get_url)
+                .head(head_url),
+        )
+        .route("/{id}/stats", routing::get(get_url_stats))
+        .route("/{id}/qr", routing::get(qr_code));
+    if container.read(config::namespaces_capsule) {
+        app = app
+            .route(
+                "/{namespace}/{id}",
+                routing::put(put_url)
+                    .delete(delete_url)
+                    .route_layer(api_key_layer.clone())
+                    .route_layer(body_limit)
+                    .get(get_url)
+                    .head(head_url),
+            )
+            .route("/{namespace}/{id}/stats", routing::get(get_url_stats))
+            .route("/{namespace}/{id}/qr", routing::get(qr_code));
+    }
+    if container.read(metrics_capsule).is_some() {
+        app = app.route("/metrics", routing::get(metrics));
+    }
+    // NOTE: listing every item is only ever allowed with an API key
+    if container.read(config::api_key_capsule).is_some() {
+        app = app
+            .route(
+                "/admin/urls",
+                routing::get(list_urls).route_layer(api_key_layer.clone()),
+            )
+            .route(
+                "/admin/urls/{id}/expire",
+                routing::post(expire_url).route_layer(api_key_layer.clone()),
+            )
+            .route(
+                "/admin/{id}/debug",
+                routing::get(debug_url).route_layer(api_key_layer),
+            );
+    }
+    app.layer(middleware::from_fn_with_state(
+        container.clone(),
+        rate_limit_creates,
+    ))
+    .with_state(container.clone())
+}
+
+/// Resolves once the process is asked to stop, via either Ctrl+C (`SIGINT`) or `SIGTERM`.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {},
+        () = terminate => {},
+    }
+}
+
+/// The short ID in the request path, prefixed by its namespace on namespaced routes.
+struct ShortIdPath(String);
+impl<S: Send + Sync> FromRequestParts<S> for ShortIdPath {
+    type Rejection = PathRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(mut params) =
+            Path::<HashMap<String, String>>::from_request_parts(parts, state).await?;
+        let id = params.remove("id").unwrap_or_default();
+        Ok(Self(match params.remove("namespace") {
+            Some(namespace) => format!("{namespace}{NAMESPACE_SEPARATOR}{id}"),
+            None => id,
+        }))
+    }
+}
+
+/// The body of a `POST` to shorten a URL: usually JSON, but an HTML form submission or
+/// a raw `text/plain` URL (e.g. `curl -H 'Content-Type: text/plain' -d 'https://example.com' ...`)
+/// works too.
+#[derive(Debug)]
+enum PostUrlBody {
+    /// Sent as either JSON or `application/x-www-form-urlencoded`.
+    Payload(url_service::PostUrlPayload),
+    /// A raw URL, which expires after the default TTL and is responded to in plain text.
+    PlainText(String),
+}
+impl<S: Send + Sync> FromRequest<S> for PostUrlBody {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if has_content_type(request.headers(), "text/plain") {
+            let url = String::from_request(request, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            // NOTE: tools like echo (and heredocs) add a trailing newline
+            return Ok(Self::PlainText(url.trim().to_owned()));
+        }
+        if has_content_type(request.headers(), "application/x-www-form-urlencoded") {
+            let Form(payload) = Form::from_request(request, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(Self::Payload(payload));
+        }
+        let Json(payload) = Json::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(Self::Payload(payload))
+    }
+}
+
+/// Whether the `Content-Type` header has the given media type, ignoring any parameters.
+fn has_content_type(headers: &HeaderMap, media_type: &str) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|actual| actual.trim().eq_ignore_ascii_case(media_type))
+}
+
+/// Requires the configured API key (if any) as a bearer token.
+#[instrument(skip_all)]
+async fn require_api_key(
+    State(container): State<Container>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(api_keys) = container.read(config::api_key_capsule)
+        && let Err(error) = check_api_key(&api_keys, request.headers().get(header::AUTHORIZATION))
+    {
+        let err_uuid = Uuid::new_v4();
+        info!(
+            ?err_uuid,
+            ?error,
+            "Rejected request without a valid API key"
+        );
+        let body = Json(Error {
+            error: error.to_string(),
+            error_id: err_uuid.to_string(),
+            details: None,
+        });
+        return match error {
+            ApiKeyError::Missing => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                body,
+            )
+                .into_response(),
+            ApiKeyError::Mismatched => (StatusCode::FORBIDDEN, body).into_response(),
+        };
+    }
+    next.run(request).await
+}
+
+/// Limits how often each client (see [`rate_limit_key`]) may create (`POST` or `PUT`)
+/// short URLs; reads such as redirects are never limited.
+///
+/// NOTE: the client IP is the connecting peer, so behind a reverse proxy
+/// every unauthenticated request shares the proxy's limit.
+#[instrument(skip_all)]
+async fn rate_limit_creates(
+    State(container): State<Container>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // NOTE: bulk requests are charged per item instead, once their items are parsed
+    let is_bulk = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| path.as_str() == "/bulk");
+    if matches!(*request.method(), Method::POST | Method::PUT)
+        && !is_bulk
+        && let Some(response) = rate_limited_response(
+            &container,
+            &rate_limit_key(&container, addr, request.headers()),
+            NonZeroU32::MIN,
+        )
+    {
+        return response;
+    }
+    next.run(request).await
+}
+
+/// Who a request is rate limited as: its API key when it presents a valid one
+/// (and `RATE_LIMIT_BY_API_KEY` is set), or else its IP.
+fn rate_limit_key(container: &Container, addr: SocketAddr, headers: &HeaderMap) -> RateLimitKey {
+    if container.read(config::rate_limit_by_api_key_capsule)
+        && let Some(api_key_id) = api_key_owner(container, headers)
+    {
+        return RateLimitKey::ApiKey(api_key_id);
+    }
+    addr.ip().into()
+}
+
+/// The ID (see [`stoopid_short::auth::api_key_id`]) of the valid API key that `headers`
+/// present, if any, which owns whatever the request creates.
+fn api_key_owner(container: &Container, headers: &HeaderMap) -> Option<String> {
+    let api_keys = container.read(config::api_key_capsule)?;
+    check_api_key(&api_keys, headers.get(header::AUTHORIZATION)).ok()
+}
+
+/// Charges `key` `cost` tokens if rate limiting is enabled,
+/// returning a `429 Too Many Requests` response when it doesn't have enough left.
+fn rate_limited_response(
+    container: &Container,
+    key: &RateLimitKey,
+    cost: NonZeroU32,
+) -> Option<Response> {
+    let rate_limiter = container.read(rate_limiter_capsule)?;
+    let retry_after = rate_limiter
+        .check(key.clone(), cost, Instant::now())
+        .err()?;
+    let err_uuid = Uuid::new_v4();
+    let retry_after_seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    info!(?err_uuid, client = ?key, retry_after_seconds, "Client was rate limited");
+    Some(
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after_seconds.to_string())],
+            Json(Error {
+                error: "Too many requests; try again later".to_owned(),
+                error_id: err_uuid.to_string(),
+                details: None,
+            }),
+        )
+            .into_response(),
+    )
+}
+
+#[instrument(skip(container))]
+async fn health(State(container): State<Container>) -> impl IntoResponse {
     info!("Health check requested");
-    (StatusCode::OK, "OK")
+    let health = container.read(url_rest_service_capsule).health().await;
+    let status_code = match health.status {
+        HealthStatus::Ok => StatusCode::OK,
+        HealthStatus::DbUnreachable => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status_code, Json(health))
+}
+
+#[instrument(skip(container))]
+async fn policy(State(container): State<Container>) -> impl IntoResponse {
+    Json(container.read(url_rest_service_capsule).url_policy())
 }
 
+/// Renders the sampled [`stoopid_short::metrics::Metrics`] for Prometheus to scrape.
 #[instrument(skip(container))]
-async fn get_url(State(container): State<Container>, Path(id): Path<String>) -> impl IntoResponse {
+async fn metrics(State(container): State<Container>) -> Response {
+    container.read(metrics_capsule).map_or_else(
+        || StatusCode::NOT_FOUND.into_response(),
+        |metrics| {
+            (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                metrics.render(),
+            )
+                .into_response()
+        },
+    )
+}
+
+#[instrument]
+async fn openapi_spec() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        openapi::OPENAPI_SPEC,
+    )
+}
+
+#[instrument(skip(container))]
+async fn sitemap(State(container): State<Container>) -> impl IntoResponse {
     container
         .read(url_rest_service_capsule)
+        .sitemap()
+        .await
+        .map(|sitemap| ([(header::CONTENT_TYPE, "application/xml")], sitemap))
+        .map_err(|error: SitemapError| {
+            let err_uuid = Uuid::new_v4();
+            match error {
+                SitemapError::Disabled => (
+                    StatusCode::NOT_FOUND,
+                    Json(Error {
+                        error: "Not found".to_owned(),
+                        error_id: err_uuid.to_string(),
+                        details: None,
+                    }),
+                ),
+                SitemapError::Db(db_err) => {
+                    error!(?err_uuid, ?db_err, "Encountered database error");
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(Error {
+                            error: "Internal server error".to_owned(),
+                            error_id: err_uuid.to_string(),
+                            details: None,
+                        }),
+                    )
+                }
+            }
+        })
+}
+
+#[instrument(skip(container))]
+async fn get_root(State(container): State<Container>) -> Response {
+    let (root_redirect_url, redirect_status) = container.read((
+        config::root_redirect_url_capsule,
+        config::redirect_status_capsule,
+    ));
+    root_response(root_redirect_url.as_ref(), redirect_status)
+}
+
+/// Redirects a GET of the root when configured to; otherwise, points the client to `POST /`
+/// (unlike the bare 405 of other methods, or a short ID's 404).
+fn root_response(root_redirect_url: Option<&Url>, redirect_status: StatusCode) -> Response {
+    if let Some(root_redirect_url) = root_redirect_url {
+        return (
+            redirect_status,
+            [(header::LOCATION, root_redirect_url.as_str())],
+        )
+            .into_response();
+    }
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        [(header::ALLOW, "POST")],
+        Json(Error {
+            error: "Method not allowed; POST a URL here to shorten it".to_owned(),
+            error_id: Uuid::new_v4().to_string(),
+            details: None,
+        }),
+    )
+        .into_response()
+}
+
+#[instrument]
+async fn missing_short_id() -> impl IntoResponse {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(Error {
+            error: "Missing short ID".to_owned(),
+            error_id: Uuid::new_v4().to_string(),
+            details: None,
+        }),
+    )
+}
+
+#[instrument(skip(container, headers))]
+async fn get_url(
+    State(container): State<Container>,
+    ShortIdPath(id): ShortIdPath,
+    headers: HeaderMap,
+) -> Response {
+    // NOTE: short IDs are alphanumeric, so a trailing `+` (sent as is or percent-encoded,
+    // which the path extractor decodes) can only ask for a preview
+    if let Some(id) = id.strip_suffix('+') {
+        return preview_url(&container, id).await;
+    }
+
+    let (url_rest_service, interstitial, redirect_status, debug_headers) = container.read((
+        url_rest_service_capsule,
+        config::interstitial_capsule,
+        config::redirect_status_capsule,
+        config::debug_headers_capsule,
+    ));
+    url_rest_service
         .get_url(&id)
         .await
-        .map(
-            |url_service::Redirect {
-                 url,
-                 max_age_seconds,
-             }| {
-                (
-                    [(
-                        "Cache-Control",
-                        format!("public, max-age={max_age_seconds}"),
-                    )],
-                    Redirect::temporary(&url),
+        .map(|redirect| {
+            let cache_control = [(header::CACHE_CONTROL, redirect.cache_control())];
+            let x_cache = redirect
+                .cache_status
+                .filter(|_| debug_headers)
+                .map(|cache_status| [("X-Cache", cache_status.as_str())]);
+            let url = redirect.url;
+            let interstitial_html = interstitial
+                .filter(|interstitial| interstitial.applies_to(&url))
+                .and_then(|interstitial| {
+                    let html = interstitial.render(&url);
+                    if html.is_none() {
+                        warn!(url, "Redirecting to non-HTTP URL without the interstitial");
+                    }
+                    html
+                });
+            // NOTE: the response depends on Accept whenever there's an interstitial to show,
+            // so caches must not serve a browser's interstitial to an API client (or vice versa)
+            let vary = interstitial_html
+                .is_some()
+                .then_some([(header::VARY, "Accept")]);
+            match interstitial_html {
+                // NOTE: only browsers get the interstitial; API clients are redirected directly
+                Some(html) if accepts_html(&headers) => {
+                    (cache_control, x_cache, vary, Html(html)).into_response()
+                }
+                _ => (
+                    redirect_status,
+                    cache_control,
+                    x_cache,
+                    vary,
+                    [(header::LOCATION, url)],
                 )
-            },
+                    .into_response(),
+            }
+        })
+        .map_err(get_url_error)
+        .into_response()
+}
+
+/// Responds with where `id` points (like bit.ly's `+` suffix), instead of redirecting there.
+async fn preview_url(container: &Container, id: &str) -> Response {
+    container
+        .read(url_rest_service_capsule)
+        .preview_url(id)
+        .await
+        .map(Json)
+        .map_err(get_url_error)
+        .into_response()
+}
+
+/// The error response for a short ID that can't be redirected to (or previewed).
+fn get_url_error(error: GetUrlError) -> (StatusCode, Json<Error>) {
+    let err_uuid = Uuid::new_v4();
+    match error {
+        GetUrlError::NotFound => (
+            StatusCode::NOT_FOUND,
+            Json(Error {
+                error: "Not found".to_owned(),
+                error_id: err_uuid.to_string(),
+                details: None,
+            }),
+        ),
+        GetUrlError::Gone => (
+            StatusCode::GONE,
+            Json(Error {
+                error: "Gone".to_owned(),
+                error_id: err_uuid.to_string(),
+                details: None,
+            }),
+        ),
+        GetUrlError::DisallowedScheme(scheme) => {
+            info!(?err_uuid, scheme, "Blocked redirect to disallowed scheme");
+            (
+                StatusCode::FORBIDDEN,
+                Json(Error {
+                    error: format!("Redirects to {scheme} URLs are not allowed"),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+        }
+        GetUrlError::Db(db_err) => {
+            error!(?db_err, "Encountered database error");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(Error {
+                    error: "Internal server error".to_owned(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+        }
+    }
+}
+
+/// Like [`get_url`], but only reports the status and redirect headers (for existence checks),
+/// so it never consumes a single-use URL or counts a hit.
+#[instrument(skip(container))]
+async fn head_url(
+    State(container): State<Container>,
+    ShortIdPath(id): ShortIdPath,
+) -> impl IntoResponse {
+    let (url_rest_service, redirect_status) =
+        container.read((url_rest_service_capsule, config::redirect_status_capsule));
+    match url_rest_service.head_url(&id).await {
+        Ok(redirect) => (
+            redirect_status,
+            [
+                (header::CACHE_CONTROL, redirect.cache_control()),
+                (header::LOCATION, redirect.url),
+            ],
         )
-        .map_err(|error: GetUrlError| {
+            .into_response(),
+        Err(GetUrlError::NotFound) => StatusCode::NOT_FOUND.into_response(),
+        Err(GetUrlError::Gone) => StatusCode::GONE.into_response(),
+        Err(GetUrlError::DisallowedScheme(_)) => StatusCode::FORBIDDEN.into_response(),
+        Err(GetUrlError::Db(db_err)) => {
+            error!(?db_err, "Encountered database error");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[instrument(skip(container, headers))]
+async fn get_url_stats(
+    State(container): State<Container>,
+    ShortIdPath(id): ShortIdPath,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    container
+        .read(url_rest_service_capsule)
+        .get_url_stats(&id)
+        .await
+        .map(|stats| {
+            let etag = stats_etag(&stats);
+            if if_none_match_matches(&headers, &etag) {
+                return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+            }
+            ([(header::ETAG, etag)], Negotiated::new(stats, &headers)).into_response()
+        })
+        .map_err(|error: GetUrlStatsError| {
             let err_uuid = Uuid::new_v4();
             match error {
-                GetUrlError::NotFound => (
+                GetUrlStatsError::NotFound => (
                     StatusCode::NOT_FOUND,
                     Json(Error {
                         error: "Not found".to_owned(),
                         error_id: err_uuid.to_string(),
+                        details: None,
                     }),
                 ),
-                GetUrlError::Db(db_err) => {
-                    error!(?db_err, "Encountered database error");
+                GetUrlStatsError::Db(db_err) => {
+                    error!(?err_uuid, ?db_err, "Encountered database error");
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Json(Error {
                             error: "Internal server error".to_owned(),
                             error_id: err_uuid.to_string(),
+                            details: None,
                         }),
                     )
                 }
@@ -83,61 +671,401 @@ async fn get_url(State(container): State<Container>, Path(id): Path<String>) ->
         })
 }
 
-#[instrument(skip(container))]
+#[instrument(skip(container, headers))]
 async fn put_url(
     State(container): State<Container>,
-    Path(id): Path<String>,
+    ShortIdPath(id): ShortIdPath,
+    headers: HeaderMap,
     Json(url_service::PutUrlPayload {
         url,
         expiration_timestamp,
+        utm_template,
+        discoverable,
+        cache_max_age_seconds,
+        single_use,
+        max_hits,
+        not_before_timestamp,
     }): Json<url_service::PutUrlPayload>,
-) -> impl IntoResponse {
+) -> Response {
+    let reveal_existing_url = container.read(config::conflict_details_capsule)
+        && is_authorized(container.read(config::api_key_capsule).as_ref(), &headers);
+    let precondition = match put_precondition(&headers) {
+        Ok(precondition) => precondition,
+        Err(error) => return precondition_failed(&error),
+    };
+    let owner = api_key_owner(&container, &headers);
     container
         .read(url_rest_service_capsule)
-        .put_url(id, &url, &expiration_timestamp)
+        .put_url(
+            id,
+            &url,
+            url_service::PutUrlOptions {
+                expiration_timestamp: expiration_timestamp.as_deref(),
+                utm_template: utm_template.as_deref(),
+                discoverable,
+                cache_max_age_seconds,
+                single_use,
+                max_hits,
+                not_before_timestamp: not_before_timestamp.as_deref(),
+                precondition,
+                owner: owner.as_deref(),
+            },
+        )
         .await
         .map(|(short_url, creation_status)| {
             (
-                match creation_status {
-                    url_service::UrlCreationStatus::NewlyCreated => StatusCode::CREATED,
-                    url_service::UrlCreationStatus::AlreadyExists => StatusCode::OK,
-                },
-                Json(short_url),
+                put_url_status(&creation_status),
+                created_location(&creation_status, &short_url),
+                Negotiated::new(short_url, &headers),
             )
         })
-        .map_err(|error: PutUrlError| {
+        .map_err(|error| put_url_error(&error, reveal_existing_url))
+        .into_response()
+}
+
+/// Responds to a failed `PUT`, only revealing the URL that an already-taken short ID
+/// points to when `reveal_existing_url`.
+fn put_url_error(error: &PutUrlError, reveal_existing_url: bool) -> Response {
+    let err_uuid = Uuid::new_v4();
+    match *error {
+        PutUrlError::ShortIdAlreadyTaken { .. } | PutUrlError::ReservedId => {
+            info!(?err_uuid, ?error, "Short ID is unavailable");
+            let body = conflict_error(error, err_uuid, reveal_existing_url);
+            (StatusCode::CONFLICT, Json(body)).into_response()
+        }
+        PutUrlError::ShortIdExists
+        | PutUrlError::ShortIdMissing
+        | PutUrlError::EntityTagPrecondition => precondition_failed(error),
+        PutUrlError::TargetCooldown { cooldown_seconds } => {
+            info!(?err_uuid, ?error, "URL was shortened too recently");
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, cooldown_seconds.to_string())],
+                Json(Error {
+                    error: error.to_string(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+        PutUrlError::TooManyHosts { .. } => {
+            info!(?err_uuid, ?error, "API key targeted too many hosts");
+            (
+                StatusCode::FORBIDDEN,
+                Json(Error {
+                    error: error.to_string(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+        PutUrlError::InvalidShortId(ref details) | PutUrlError::InvalidNamespace(ref details) => {
+            info!(?err_uuid, ?error, "User submitted an invalid short ID");
+            (
+                StatusCode::BAD_REQUEST,
+                Json(Error {
+                    error: error.to_string(),
+                    error_id: err_uuid.to_string(),
+                    details: Some(ErrorDetails::InvalidShortId(details.clone())),
+                }),
+            )
+                .into_response()
+        }
+        PutUrlError::TimestampParse(_)
+        | PutUrlError::InvalidExpirationTime(_)
+        | PutUrlError::DisallowedExpirationTime(_)
+        | PutUrlError::NotBeforeAfterExpiration
+        | PutUrlError::ZeroMaxHits
+        | PutUrlError::SingleUseWithMaxHits
+        | PutUrlError::LowEntropyShortId { .. }
+        | PutUrlError::InvalidUrl(_)
+        | PutUrlError::DisallowedScheme(_)
+        | PutUrlError::UrlTooLong { .. }
+        | PutUrlError::KnownShortener { .. }
+        | PutUrlError::DisallowedUrl(_) => {
+            info!(?err_uuid, ?error, "User submitted a bad request");
+            (
+                StatusCode::BAD_REQUEST,
+                Json(Error {
+                    error: error.to_string(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+        PutUrlError::Internal(_) => {
+            error!(?err_uuid, ?error, "Encountered an error during a request");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(Error {
+                    error: "Internal server error".to_owned(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[instrument(skip(container, headers))]
+async fn post_url(
+    State(container): State<Container>,
+    Query(url_service::PostUrlQuery { redirect }): Query<url_service::PostUrlQuery>,
+    headers: HeaderMap,
+    body: PostUrlBody,
+) -> impl IntoResponse {
+    let (url, expiration_timestamp, plain_text) = match body {
+        PostUrlBody::Payload(url_service::PostUrlPayload {
+            url,
+            expiration_timestamp,
+        }) => (url, expiration_timestamp, false),
+        PostUrlBody::PlainText(url) => (url, None, true),
+    };
+    let (url_rest_service, debug_headers, preview_redirect) = container.read((
+        url_rest_service_capsule,
+        config::debug_headers_capsule,
+        config::preview_redirect_capsule,
+    ));
+    let redirect_to_preview = preview_redirect
+        && (redirect == Some(url_service::PostUrlRedirect::Preview) || accepts_html(&headers));
+    let owner = api_key_owner(&container, &headers);
+    url_rest_service
+        .post_url(&url, expiration_timestamp.as_deref(), owner.as_deref())
+        .await
+        .map(|(short_url, creation_status, attempts)| {
+            let attempts_header = debug_headers.then(|| [("X-Attempts", attempts.to_string())]);
+            // NOTE: a browser (e.g. one submitting an HTML form) is sent on to a page
+            // rather than shown the short URL's JSON
+            if redirect_to_preview {
+                let preview_location = format!("{}+", short_url.short_url);
+                return (
+                    StatusCode::SEE_OTHER,
+                    attempts_header,
+                    [(header::LOCATION, preview_location)],
+                )
+                    .into_response();
+            }
+            let location = created_location(&creation_status, &short_url);
+            let body = if plain_text {
+                format!("{}\n", short_url.short_url).into_response()
+            } else {
+                Negotiated::new(short_url, &headers).into_response()
+            };
+            (
+                put_url_status(&creation_status),
+                location,
+                attempts_header,
+                body,
+            )
+                .into_response()
+        })
+        .map_err(|error| post_url_error(&error))
+}
+
+/// Responds to a failed `POST`.
+fn post_url_error(error: &PostUrlError) -> Response {
+    let err_uuid = Uuid::new_v4();
+    match *error {
+        PostUrlError::TargetCooldown { cooldown_seconds } => {
+            info!(?err_uuid, ?error, "URL was shortened too recently");
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, cooldown_seconds.to_string())],
+                Json(Error {
+                    error: error.to_string(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+        PostUrlError::TooManyHosts { .. } => {
+            info!(?err_uuid, ?error, "API key targeted too many hosts");
+            (
+                StatusCode::FORBIDDEN,
+                Json(Error {
+                    error: error.to_string(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+        PostUrlError::TimestampParse(_)
+        | PostUrlError::InvalidExpirationTime(_)
+        | PostUrlError::DisallowedExpirationTime(_)
+        | PostUrlError::InvalidUrl(_)
+        | PostUrlError::DisallowedScheme(_)
+        | PostUrlError::UrlTooLong { .. }
+        | PostUrlError::KnownShortener { .. }
+        | PostUrlError::DisallowedUrl(_) => {
+            info!(?err_uuid, ?error, "User submitted a bad request");
+            (
+                StatusCode::BAD_REQUEST,
+                Json(Error {
+                    error: error.to_string(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+        PostUrlError::Internal(_) => {
+            error!(?err_uuid, ?error, "Encountered an error during a request");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(Error {
+                    error: "Internal server error".to_owned(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// The outcome of a single item in a bulk request.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BulkItemResult {
+    Ok(ShortenedUrl),
+    Error(String),
+    /// The item repeats the item at this index, whose result is its result too.
+    DuplicateOf(usize),
+}
+
+#[instrument(skip(container, items))]
+async fn post_urls(
+    State(container): State<Container>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(items): Json<Vec<url_service::PostUrlPayload>>,
+) -> Response {
+    // NOTE: an empty bulk request still costs as much as any other request
+    let cost =
+        NonZeroU32::new(u32::try_from(items.len()).unwrap_or(u32::MAX)).unwrap_or(NonZeroU32::MIN);
+    let key = rate_limit_key(&container, addr, &headers);
+    if let Some(response) = rate_limited_response(&container, &key, cost) {
+        return response;
+    }
+    let owner = api_key_owner(&container, &headers);
+    container
+        .read(url_rest_service_capsule)
+        .post_urls(items, owner.as_deref())
+        .await
+        .map(|results| {
+            Json(
+                results
+                    .into_iter()
+                    .map(|result| match result {
+                        PostUrlsItem::Posted(Ok(short_url)) => BulkItemResult::Ok(short_url),
+                        PostUrlsItem::Posted(Err(error @ PostUrlError::Internal(_))) => {
+                            let err_uuid = Uuid::new_v4();
+                            error!(?err_uuid, ?error, "Encountered an error during a bulk item");
+                            BulkItemResult::Error(format!("Internal server error ({err_uuid})"))
+                        }
+                        PostUrlsItem::Posted(Err(error)) => {
+                            BulkItemResult::Error(error.to_string())
+                        }
+                        PostUrlsItem::DuplicateOf(index) => BulkItemResult::DuplicateOf(index),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .map_err(|error: PostUrlsError| {
             let err_uuid = Uuid::new_v4();
             match error {
-                PutUrlError::ShortIdAlreadyTaken => {
-                    info!(?err_uuid, ?error, "Short ID exists under a different entry");
-                    (
-                        StatusCode::CONFLICT,
-                        Json(Error {
-                            error: error.to_string(),
-                            error_id: err_uuid.to_string(),
-                        }),
-                    )
-                }
-                PutUrlError::TimestampParse(_)
-                | PutUrlError::InvalidExpirationTime(_)
-                | PutUrlError::InvalidShortId(_)
-                | PutUrlError::InvalidUrl(_) => {
-                    info!(?err_uuid, ?error, "User submitted a bad request");
+                PostUrlsError::TooManyItems { .. } => {
+                    info!(?err_uuid, ?error, "User submitted too many bulk items");
                     (
-                        StatusCode::BAD_REQUEST,
+                        StatusCode::PAYLOAD_TOO_LARGE,
                         Json(Error {
                             error: error.to_string(),
                             error_id: err_uuid.to_string(),
+                            details: None,
                         }),
                     )
                 }
-                PutUrlError::Internal(_) => {
-                    error!(?err_uuid, ?error, "Encountered an error during a request");
+            }
+        })
+        .into_response()
+}
+
+#[instrument(skip(container))]
+async fn delete_url(
+    State(container): State<Container>,
+    ShortIdPath(id): ShortIdPath,
+    Query(url_service::DeleteUrlQuery { idempotent }): Query<url_service::DeleteUrlQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let result = container
+        .read(url_rest_service_capsule)
+        .delete_url(&id)
+        .await;
+    delete_url_status(
+        result,
+        idempotent || headers.contains_key(IDEMPOTENCY_KEY_HEADER),
+    )
+    .map_err(|error: DeleteUrlError| {
+        let err_uuid = Uuid::new_v4();
+        match error {
+            DeleteUrlError::NotFound => (
+                StatusCode::NOT_FOUND,
+                Json(Error {
+                    error: "Not found".to_owned(),
+                    error_id: err_uuid.to_string(),
+                    details: None,
+                }),
+            ),
+            DeleteUrlError::Db(db_err) => {
+                error!(?err_uuid, ?db_err, "Encountered database error");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(Error {
+                        error: "Internal server error".to_owned(),
+                        error_id: err_uuid.to_string(),
+                        details: None,
+                    }),
+                )
+            }
+        }
+    })
+}
+
+#[instrument(skip(container))]
+async fn expire_url(
+    State(container): State<Container>,
+    ShortIdPath(id): ShortIdPath,
+) -> impl IntoResponse {
+    container
+        .read(url_rest_service_capsule)
+        .expire_url(&id)
+        .await
+        .map(|()| StatusCode::NO_CONTENT)
+        .map_err(|error: ExpireUrlError| {
+            let err_uuid = Uuid::new_v4();
+            match error {
+                ExpireUrlError::NotFound => (
+                    StatusCode::NOT_FOUND,
+                    Json(Error {
+                        error: "Not found".to_owned(),
+                        error_id: err_uuid.to_string(),
+                        details: None,
+                    }),
+                ),
+                ExpireUrlError::Db(db_err) => {
+                    error!(?err_uuid, ?db_err, "Encountered database error");
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Json(Error {
                             error: "Internal server error".to_owned(),
                             error_id: err_uuid.to_string(),
+                            details: None,
                         }),
                     )
                 }
@@ -145,41 +1073,105 @@ async fn put_url(
         })
 }
 
+/// Responds with everything stored about `id`, including whether its ID was generated.
 #[instrument(skip(container))]
-async fn post_url(
+async fn debug_url(
     State(container): State<Container>,
-    Json(url_service::PostUrlPayload {
-        url,
-        expiration_timestamp,
-    }): Json<url_service::PostUrlPayload>,
+    ShortIdPath(id): ShortIdPath,
 ) -> impl IntoResponse {
     container
         .read(url_rest_service_capsule)
-        .post_url(&url, &expiration_timestamp)
+        .debug_url(&id)
         .await
-        .map(|short_url| (StatusCode::OK, Json(short_url)))
-        .map_err(|error: PostUrlError| {
+        .map(Json)
+        .map_err(get_url_error)
+}
+
+/// The header that retry-safe clients send, which makes a DELETE idempotent.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Determines the status of a DELETE, where an idempotent DELETE of a short ID
+/// that's already gone (e.g. a retry) succeeds just like the original.
+fn delete_url_status(
+    result: Result<(), DeleteUrlError>,
+    idempotent: bool,
+) -> Result<StatusCode, DeleteUrlError> {
+    match result {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(DeleteUrlError::NotFound) if idempotent => Ok(StatusCode::NO_CONTENT),
+        Err(error) => Err(error),
+    }
+}
+
+#[instrument(skip(container))]
+async fn list_urls(
+    State(container): State<Container>,
+    Query(url_service::ListUrlsQuery { limit, cursor }): Query<url_service::ListUrlsQuery>,
+) -> impl IntoResponse {
+    container
+        .read(url_rest_service_capsule)
+        .list_urls(limit, cursor.as_deref())
+        .await
+        .map(Json)
+        .map_err(|error: ListUrlsError| {
             let err_uuid = Uuid::new_v4();
             match error {
-                PostUrlError::TimestampParse(_)
-                | PostUrlError::InvalidExpirationTime(_)
-                | PostUrlError::InvalidUrl(_) => {
+                ListUrlsError::InvalidCursor => {
                     info!(?err_uuid, ?error, "User submitted a bad request");
                     (
                         StatusCode::BAD_REQUEST,
                         Json(Error {
                             error: error.to_string(),
                             error_id: err_uuid.to_string(),
+                            details: None,
                         }),
                     )
                 }
-                PostUrlError::Internal(_) => {
-                    error!(?err_uuid, ?error, "Encountered an error during a request");
+                ListUrlsError::Db(db_err) => {
+                    error!(?err_uuid, ?db_err, "Encountered database error");
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(Error {
+                            error: "Internal server error".to_owned(),
+                            error_id: err_uuid.to_string(),
+                            details: None,
+                        }),
+                    )
+                }
+            }
+        })
+}
+
+#[instrument(skip(container))]
+async fn qr_code(
+    State(container): State<Container>,
+    ShortIdPath(id): ShortIdPath,
+    Query(url_service::QrCodeQuery { size }): Query<url_service::QrCodeQuery>,
+) -> impl IntoResponse {
+    container
+        .read(url_rest_service_capsule)
+        .qr_code(&id, size)
+        .await
+        .map(|svg| ([(header::CONTENT_TYPE, "image/svg+xml")], svg))
+        .map_err(|error: QrCodeError| {
+            let err_uuid = Uuid::new_v4();
+            match error {
+                QrCodeError::Disabled | QrCodeError::NotFound => (
+                    StatusCode::NOT_FOUND,
+                    Json(Error {
+                        error: "Not found".to_owned(),
+                        error_id: err_uuid.to_string(),
+                        details: None,
+                    }),
+                ),
+                QrCodeError::Db(db_err) => {
+                    error!(?err_uuid, ?db_err, "Encountered database error");
                     (
                         StatusCode::INTERNAL_SERVER_ERROR,
                         Json(Error {
                             error: "Internal server error".to_owned(),
                             error_id: err_uuid.to_string(),
+                            details: None,
                         }),
                     )
                 }
@@ -187,8 +1179,797 @@ async fn post_url(
         })
 }
 
+fn accepts_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"))
+}
+
+// NOTE: field names are part of the JSON API
+#[allow(clippy::struct_field_names)]
 #[derive(Serialize)]
 pub struct Error {
     error: String,
     error_id: String,
+    /// Machine-readable fields describing the error, flattened into the body when present.
+    #[serde(flatten)]
+    details: Option<ErrorDetails>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ErrorDetails {
+    InvalidShortId(ShortIdValidationError),
+    /// The URL that an already-taken short ID points to.
+    ShortIdTaken {
+        existing_url: String,
+    },
+}
+
+/// A weak `ETag` for a short ID's stats, which changes along with its long URL,
+/// expiration, or hit count (but not, for example, its ticking `max_age_seconds`).
+fn stats_etag(stats: &UrlStats) -> String {
+    let mut hasher = blake3::Hasher::new();
+    // NOTE: neither URLs nor timestamps can contain NUL, so it safely separates the fields
+    hasher.update(stats.long_url.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(stats.expiration_timestamp.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(&stats.hit_count.to_le_bytes());
+    format!("W/\"{}\"", &hasher.finalize().to_hex()[..16])
+}
+
+/// Whether the `If-None-Match` header lists `etag` (or is `*`),
+/// using the weak comparison that RFC 9110 requires for it.
+fn if_none_match_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|if_none_match| if_none_match.to_str().ok())
+    else {
+        return false;
+    };
+    let opaque_tag = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque_tag(tag) == opaque_tag(etag))
+}
+
+/// The precondition of a `PUT`: `If-None-Match: *` may only create the short ID,
+/// while `If-Match: *` may only replace an existing one.
+/// Fails for an `If-Match` that lists entity tags, which can never match
+/// since short URLs have no strong `ETag`s.
+fn put_precondition(headers: &HeaderMap) -> Result<PutPrecondition, PutUrlError> {
+    if let Some(if_match) = headers.get(header::IF_MATCH) {
+        return if if_match == "*" {
+            Ok(PutPrecondition::MustExist)
+        } else {
+            Err(PutUrlError::EntityTagPrecondition)
+        };
+    }
+    let create_only = headers
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|if_none_match| if_none_match == "*");
+    Ok(if create_only {
+        PutPrecondition::CreateOnly
+    } else {
+        PutPrecondition::None
+    })
+}
+
+fn precondition_failed(error: &PutUrlError) -> Response {
+    let err_uuid = Uuid::new_v4();
+    info!(?err_uuid, ?error, "PUT precondition failed");
+    (
+        StatusCode::PRECONDITION_FAILED,
+        Json(Error {
+            error: error.to_string(),
+            error_id: err_uuid.to_string(),
+            details: None,
+        }),
+    )
+        .into_response()
+}
+
+const fn put_url_status(creation_status: &UrlCreationStatus) -> StatusCode {
+    match creation_status {
+        UrlCreationStatus::NewlyCreated => StatusCode::CREATED,
+        UrlCreationStatus::AlreadyExists | UrlCreationStatus::Replaced => StatusCode::OK,
+    }
+}
+
+/// The `Location` of a newly created short URL (its full short URL when `BASE_URL` is set).
+fn created_location(
+    creation_status: &UrlCreationStatus,
+    short_url: &ShortenedUrl,
+) -> Option<[(header::HeaderName, String); 1]> {
+    matches!(creation_status, UrlCreationStatus::NewlyCreated)
+        .then(|| [(header::LOCATION, short_url.short_url.clone())])
+}
+
+/// Whether the request presents the configured API key (and so is never when there is none).
+fn is_authorized(api_keys: Option<&ApiKeys>, headers: &HeaderMap) -> bool {
+    api_keys
+        .is_some_and(|api_keys| check_api_key(api_keys, headers.get(header::AUTHORIZATION)).is_ok())
+}
+
+/// Describes why a short ID is unavailable, only revealing the URL that an already-taken
+/// short ID points to when `reveal_existing_url`, since the URL may be private.
+fn conflict_error(error: &PutUrlError, err_uuid: Uuid, reveal_existing_url: bool) -> Error {
+    let existing_url = match error {
+        PutUrlError::ShortIdAlreadyTaken { existing_url } if reveal_existing_url => {
+            Some(existing_url.clone())
+        }
+        _ => None,
+    };
+    Error {
+        error: error.to_string(),
+        error_id: err_uuid.to_string(),
+        details: existing_url.map(|existing_url| ErrorDetails::ShortIdTaken { existing_url }),
+    }
+}
+
+const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// A response body sent as CBOR when the client prefers it (see [`prefers_cbor`]),
+/// and as JSON otherwise.
+struct Negotiated<T> {
+    body: T,
+    cbor: bool,
+}
+impl<T> Negotiated<T> {
+    fn new(body: T, headers: &HeaderMap) -> Self {
+        Self {
+            body,
+            cbor: prefers_cbor(headers),
+        }
+    }
+}
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        if !self.cbor {
+            return Json(self.body).into_response();
+        }
+
+        let mut bytes = Vec::new();
+        match ciborium::into_writer(&self.body, &mut bytes) {
+            Ok(()) => ([(header::CONTENT_TYPE, CBOR_CONTENT_TYPE)], bytes).into_response(),
+            Err(err) => {
+                let err_uuid = Uuid::new_v4();
+                error!(?err_uuid, ?err, "Failed to serialize CBOR response");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(Error {
+                        error: "Internal server error".to_owned(),
+                        error_id: err_uuid.to_string(),
+                        details: None,
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// Whether the `Accept` header ranks CBOR above JSON, which is the default otherwise.
+fn prefers_cbor(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+    else {
+        return false;
+    };
+
+    // NOTE: the best quality (`q`) among the media ranges matching each type
+    let (mut cbor_quality, mut json_quality) = (0.0_f32, 0.0_f32);
+    for media_range in accept.split(',') {
+        let mut params = media_range.split(';').map(str::trim);
+        let media_type = params.next().unwrap_or_default().to_ascii_lowercase();
+        let quality = params
+            .find_map(|param| param.strip_prefix("q="))
+            .map_or(Some(1.0), |quality| quality.parse().ok())
+            .unwrap_or(0.0);
+        match media_type.as_str() {
+            CBOR_CONTENT_TYPE => cbor_quality = cbor_quality.max(quality),
+            "application/json" | "application/*" | "*/*" => {
+                json_quality = json_quality.max(quality);
+            }
+            _ => {}
+        }
+    }
+    cbor_quality > json_quality
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use axum::{body::Body, http::HeaderValue};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const API_KEY: &str = "s3cr3t-key";
+
+    fn headers_with(authorization: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static(authorization),
+        );
+        headers
+    }
+
+    fn headers_with_if_none_match(if_none_match: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_static(if_none_match),
+        );
+        headers
+    }
+
+    fn conflict_body(api_key: Option<&str>, headers: &HeaderMap) -> serde_json::Value {
+        let error = PutUrlError::ShortIdAlreadyTaken {
+            existing_url: "https://example.com/private".to_owned(),
+        };
+        let api_keys = api_key.and_then(ApiKeys::parse);
+        let reveal_existing_url = is_authorized(api_keys.as_ref(), headers);
+        serde_json::to_value(conflict_error(&error, Uuid::nil(), reveal_existing_url)).unwrap()
+    }
+
+    #[test]
+    fn test_conflict_body_detailed_when_authorized() {
+        let body = conflict_body(Some(API_KEY), &headers_with("Bearer s3cr3t-key"));
+        assert_eq!(body["existing_url"], "https://example.com/private");
+        assert_eq!(body["error"], "short ID is already taken");
+    }
+
+    #[test]
+    fn test_conflict_body_opaque_when_unauthorized() {
+        for (api_key, headers) in [
+            (Some(API_KEY), HeaderMap::new()),
+            (Some(API_KEY), headers_with("Bearer wrong-key")),
+            (None, headers_with("Bearer s3cr3t-key")),
+        ] {
+            let body = conflict_body(api_key, &headers);
+            assert_eq!(body.get("existing_url"), None);
+            assert_eq!(body["error"], "short ID is already taken");
+        }
+    }
+
+    fn new_stats(hit_count: u64) -> UrlStats {
+        UrlStats {
+            shortened_url_id: "abc123".to_owned(),
+            long_url: "https://example.com/".to_owned(),
+            expiration_timestamp: "2030-01-01T00:00:00Z".to_owned(),
+            hit_count,
+            max_age_seconds: 60,
+            cache_max_age_seconds: None,
+        }
+    }
+
+    #[test]
+    fn test_stats_etag_unchanged_resource_is_not_modified() {
+        let etag = stats_etag(&new_stats(3));
+        assert!(etag.starts_with("W/\""));
+
+        let unchanged = UrlStats {
+            max_age_seconds: 59,
+            ..new_stats(3)
+        };
+        assert_eq!(stats_etag(&unchanged), etag);
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.parse().unwrap());
+        assert!(if_none_match_matches(&headers, &stats_etag(&unchanged)));
+        // NOTE: If-None-Match compares weakly, so a strong form of the ETag matches too
+        let strong_etag = etag.trim_start_matches("W/").to_owned();
+        headers.insert(header::IF_NONE_MATCH, strong_etag.parse().unwrap());
+        assert!(if_none_match_matches(&headers, &etag));
+        assert!(if_none_match_matches(
+            &headers_with_if_none_match("*"),
+            &etag
+        ));
+    }
+
+    #[test]
+    fn test_stats_etag_changed_resource_is_modified() {
+        let etag = stats_etag(&new_stats(3));
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.parse().unwrap());
+
+        assert!(!if_none_match_matches(&headers, &stats_etag(&new_stats(4))));
+        let moved = UrlStats {
+            long_url: "https://example.com/moved".to_owned(),
+            ..new_stats(3)
+        };
+        assert!(!if_none_match_matches(&headers, &stats_etag(&moved)));
+        assert!(!if_none_match_matches(&HeaderMap::new(), &etag));
+    }
+
+    #[test]
+    fn test_put_precondition() {
+        assert!(matches!(
+            put_precondition(&headers_with_if_none_match("*")),
+            Ok(PutPrecondition::CreateOnly)
+        ));
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("*"));
+        assert!(matches!(
+            put_precondition(&headers),
+            Ok(PutPrecondition::MustExist)
+        ));
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("\"abc\""));
+        let error = put_precondition(&headers).unwrap_err();
+        assert!(matches!(error, PutUrlError::EntityTagPrecondition));
+        assert_eq!(
+            precondition_failed(&error).status(),
+            StatusCode::PRECONDITION_FAILED
+        );
+    }
+
+    #[test]
+    fn test_put_without_precondition() {
+        assert!(matches!(
+            put_precondition(&HeaderMap::new()),
+            Ok(PutPrecondition::None)
+        ));
+        assert!(matches!(
+            put_precondition(&headers_with_if_none_match("\"abc\"")),
+            Ok(PutPrecondition::None)
+        ));
+        assert_eq!(
+            put_url_status(&UrlCreationStatus::AlreadyExists),
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn test_conflict_body_for_reserved_id_is_opaque() {
+        let body =
+            serde_json::to_value(conflict_error(&PutUrlError::ReservedId, Uuid::nil(), true))
+                .unwrap();
+        assert_eq!(body.get("existing_url"), None);
+    }
+
+    fn headers_with_accept(accept: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+        headers
+    }
+
+    fn shortened_url() -> ShortenedUrl {
+        ShortenedUrl {
+            shortened_url_id: "abc123".to_owned(),
+            short_url: "https://sho.rt/abc123".to_owned(),
+            long_url: "https://example.com/".to_owned(),
+            expiration_timestamp: "2100-01-01T00:00:00Z".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_location_only_on_creation() {
+        let created = (
+            StatusCode::CREATED,
+            created_location(&UrlCreationStatus::NewlyCreated, &shortened_url()),
+            (),
+        )
+            .into_response();
+        assert_eq!(created.headers()[header::LOCATION], "https://sho.rt/abc123");
+
+        let existing = (
+            StatusCode::OK,
+            created_location(&UrlCreationStatus::AlreadyExists, &shortened_url()),
+            (),
+        )
+            .into_response();
+        assert!(!existing.headers().contains_key(header::LOCATION));
+    }
+
+    #[tokio::test]
+    async fn test_cbor_response_round_trips() {
+        let headers = headers_with_accept("application/cbor");
+        let response = Negotiated::new(shortened_url(), &headers).into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CBOR_CONTENT_TYPE);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let decoded: ShortenedUrl = ciborium::from_reader(bytes.as_ref()).unwrap();
+        assert_eq!(decoded, shortened_url());
+    }
+
+    #[tokio::test]
+    async fn test_json_response_by_default() {
+        let response = Negotiated::new(shortened_url(), &HeaderMap::new()).into_response();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let decoded: ShortenedUrl = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, shortened_url());
+    }
+
+    #[test]
+    fn test_repeated_idempotent_delete_succeeds() {
+        assert_eq!(
+            delete_url_status(Ok(()), true).unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                delete_url_status(Err(DeleteUrlError::NotFound), true).unwrap(),
+                StatusCode::NO_CONTENT
+            );
+        }
+    }
+
+    #[test]
+    fn test_repeated_delete_not_found_by_default() {
+        assert_eq!(
+            delete_url_status(Ok(()), false).unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        assert!(matches!(
+            delete_url_status(Err(DeleteUrlError::NotFound), false),
+            Err(DeleteUrlError::NotFound)
+        ));
+        assert!(matches!(
+            delete_url_status(Err(DeleteUrlError::Db(anyhow::anyhow!("test error"))), true),
+            Err(DeleteUrlError::Db(_))
+        ));
+    }
+
+    #[test]
+    fn test_root_response_points_to_post() {
+        let response = root_response(None, StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[header::ALLOW], "POST");
+    }
+
+    #[test]
+    fn test_root_response_redirects_when_configured() {
+        let root_redirect_url = Url::parse("https://example.com/home").unwrap();
+        let response = root_response(Some(&root_redirect_url), StatusCode::FOUND);
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://example.com/home"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_short_id_is_bad_request() {
+        let response = missing_short_id().await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_prefers_cbor_by_quality() {
+        assert!(prefers_cbor(&headers_with_accept(
+            "application/json;q=0.5, application/cbor"
+        )));
+        assert!(!prefers_cbor(&headers_with_accept(
+            "application/json, application/cbor;q=0.9"
+        )));
+        assert!(!prefers_cbor(&headers_with_accept("*/*")));
+        assert!(!prefers_cbor(&headers_with_accept("application/cbor;q=0")));
+    }
+
+    fn post_request(content_type: &str, body: &'static str) -> Request {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plain_text_post_body_is_the_raw_url() {
+        for content_type in ["text/plain", "text/plain; charset=utf-8"] {
+            let request = post_request(content_type, "https://example.com/\n");
+            let body = PostUrlBody::from_request(request, &()).await.unwrap();
+            assert!(matches!(body, PostUrlBody::PlainText(url) if url == "https://example.com/"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_post_body_is_unchanged() {
+        let request = post_request(
+            "application/json",
+            r#"{"url":"https://example.com/","expiration_timestamp":"1d"}"#,
+        );
+        let body = PostUrlBody::from_request(request, &()).await.unwrap();
+        assert!(matches!(
+            body,
+            PostUrlBody::Payload(url_service::PostUrlPayload {
+                url,
+                expiration_timestamp: Some(expiration_timestamp),
+            }) if url == "https://example.com/" && expiration_timestamp == "1d"
+        ));
+
+        let request = post_request("application/xml", "<url>https://example.com/</url>");
+        let response = PostUrlBody::from_request(request, &()).await.err().unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_form_post_body() {
+        let request = post_request(
+            "application/x-www-form-urlencoded",
+            "url=https%3A%2F%2Fexample.com%2F%3Fq%3D1&expiration_timestamp=1d",
+        );
+        let body = PostUrlBody::from_request(request, &()).await.unwrap();
+        assert!(matches!(
+            body,
+            PostUrlBody::Payload(url_service::PostUrlPayload {
+                url,
+                expiration_timestamp: Some(expiration_timestamp),
+            }) if url == "https://example.com/?q=1" && expiration_timestamp == "1d"
+        ));
+
+        let request = post_request(
+            "application/x-www-form-urlencoded",
+            "url=https%3A%2F%2Fexample.com%2F",
+        );
+        let body = PostUrlBody::from_request(request, &()).await.unwrap();
+        assert!(matches!(
+            body,
+            PostUrlBody::Payload(url_service::PostUrlPayload {
+                expiration_timestamp: None,
+                ..
+            })
+        ));
+    }
+
+    /// A [`Container`] configured by just `env_vars` (and the in-memory repo),
+    /// so that tests don't depend on the environment they happen to run in.
+    fn test_container(env_vars: &[(&str, &str)]) -> Container {
+        let container = Container::new();
+        container.read(config::env_init_action)(
+            std::iter::once(&("REPO", "memory"))
+                .chain(env_vars)
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+        );
+        container
+    }
+
+    #[tokio::test]
+    async fn test_oversized_create_bodies_are_rejected() {
+        let app = router(&test_container(&[("MAX_BODY_BYTES", "1024")]));
+        let oversized = format!(r#"{{"url":"https://example.com/{}"}}"#, "a".repeat(1024));
+
+        for (method, uri) in [(Method::POST, "/"), (Method::PUT, "/abc12345")] {
+            let response = app
+                .clone()
+                .oneshot(json_request(&method, uri, oversized.clone()))
+                .await
+                .unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "{method} {uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulk_body_limit_scales_with_bulk_size() {
+        let container = test_container(&[("MAX_BODY_BYTES", "1024"), ("MAX_BULK_SIZE", "10")]);
+        let app = router(&container);
+        let max_bulk_body_bytes = container.read(config::max_bulk_body_bytes_capsule);
+        assert_eq!(max_bulk_body_bytes, 10 * 1024);
+
+        // NOTE: not a JSON array, so that the body is rejected right after it's read
+        let over_item_limit = format!(r#"{{"url":"{}"}}"#, "a".repeat(4 * 1024));
+        let response = app
+            .clone()
+            .oneshot(json_request(&Method::POST, "/bulk", over_item_limit))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let oversized = "a".repeat(max_bulk_body_bytes + 1);
+        let response = app
+            .oneshot(json_request(&Method::POST, "/bulk", oversized))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    fn json_request(method: &Method, uri: &str, body: String) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1234))));
+        request
+    }
+
+    #[tokio::test]
+    async fn test_post_url_redirects_browsers_to_preview() {
+        let app = router(&test_container(&[("PREVIEW_REDIRECT", "true")]));
+        let body = r#"{"url":"https://example.com/"}"#;
+
+        let mut html_request = json_request(&Method::POST, "/", body.to_owned());
+        html_request
+            .headers_mut()
+            .insert(header::ACCEPT, HeaderValue::from_static("text/html"));
+        let response = app.clone().oneshot(html_request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let preview_location = response.headers()[header::LOCATION].to_str().unwrap();
+        let short_id = preview_location.strip_suffix('+').unwrap().to_owned();
+
+        let preview_request = json_request(&Method::POST, "/?redirect=preview", body.to_owned());
+        let response = app.clone().oneshot(preview_request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()[header::LOCATION], format!("{short_id}+"));
+
+        let json_request = json_request(&Method::POST, "/", body.to_owned());
+        let response = app.oneshot(json_request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let short_url: ShortenedUrl = serde_json::from_slice(&body).unwrap();
+        assert_eq!(short_url.shortened_url_id, short_id);
+    }
+
+    #[tokio::test]
+    async fn test_post_url_reports_attempts_with_debug_headers() {
+        let body = r#"{"url":"https://example.com/"}"#;
+        for (debug_headers, expected_attempts) in [("true", Some("1")), ("false", None)] {
+            let app = router(&test_container(&[("DEBUG_HEADERS", debug_headers)]));
+            let response = app
+                .oneshot(json_request(&Method::POST, "/", body.to_owned()))
+                .await
+                .unwrap();
+            assert!(response.status().is_success());
+            assert_eq!(
+                response
+                    .headers()
+                    .get("X-Attempts")
+                    .map(|attempts| attempts.to_str().unwrap()),
+                expected_attempts,
+                "DEBUG_HEADERS={debug_headers}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_url_reports_cache_status() {
+        let app = router(&test_container(&[
+            ("ENABLE_CACHE", "true"),
+            ("DEBUG_HEADERS", "true"),
+        ]));
+        let put = json_request(
+            &Method::PUT,
+            "/cached12",
+            r#"{"url":"https://example.com/"}"#.to_owned(),
+        );
+        assert!(
+            app.clone()
+                .oneshot(put)
+                .await
+                .unwrap()
+                .status()
+                .is_success()
+        );
+
+        for expected_cache_status in ["MISS", "HIT"] {
+            let get = json_request(&Method::GET, "/cached12", String::new());
+            let response = app.clone().oneshot(get).await.unwrap();
+            assert!(response.status().is_redirection());
+            assert_eq!(response.headers()["X-Cache"], expected_cache_status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_url_interstitial_varies_on_accept() {
+        let app = router(&test_container(&[
+            ("INTERSTITIAL", "true"),
+            ("URL_SCHEME_ALLOWLIST", "http,https,ftp"),
+        ]));
+        for (id, url) in [
+            ("https12", "https://example.com/"),
+            ("ftp12345", "ftp://example.com/"),
+        ] {
+            let put = json_request(
+                &Method::PUT,
+                &format!("/{id}"),
+                format!(r#"{{"url":"{url}"}}"#),
+            );
+            assert!(
+                app.clone()
+                    .oneshot(put)
+                    .await
+                    .unwrap()
+                    .status()
+                    .is_success()
+            );
+        }
+        let get = |id: &str, accept: &'static str| {
+            let mut request = json_request(&Method::GET, &format!("/{id}"), String::new());
+            request
+                .headers_mut()
+                .insert(header::ACCEPT, HeaderValue::from_static(accept));
+            app.clone().oneshot(request)
+        };
+
+        let response = get("https12", "text/html").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::VARY], "Accept");
+        let response = get("https12", "application/json").await.unwrap();
+        assert!(response.status().is_redirection());
+        assert_eq!(response.headers()[header::VARY], "Accept");
+
+        // NOTE: non-HTTP URLs get no interstitial, so browsers are redirected like anyone else
+        let response = get("ftp12345", "text/html").await.unwrap();
+        assert!(response.status().is_redirection());
+        assert_eq!(response.headers()[header::LOCATION], "ftp://example.com/");
+        assert!(!response.headers().contains_key(header::VARY));
+    }
+
+    async fn debug_info(app: &Router, id: &str) -> serde_json::Value {
+        let mut request = json_request(&Method::GET, &format!("/admin/{id}/debug"), String::new());
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {API_KEY}")).unwrap(),
+        );
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_debug_url_tells_generated_ids_from_vanity_ones() {
+        let app = router(&test_container(&[("API_KEY", API_KEY)]));
+        let mut post = json_request(
+            &Method::POST,
+            "/",
+            r#"{"url":"https://example.com/generated"}"#.to_owned(),
+        );
+        post.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {API_KEY}")).unwrap(),
+        );
+        let response = app.clone().oneshot(post).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let generated: ShortenedUrl = serde_json::from_slice(&body).unwrap();
+        let mut put = json_request(
+            &Method::PUT,
+            "/vanity12",
+            r#"{"url":"https://example.com/vanity"}"#.to_owned(),
+        );
+        put.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {API_KEY}")).unwrap(),
+        );
+        assert!(
+            app.clone()
+                .oneshot(put)
+                .await
+                .unwrap()
+                .status()
+                .is_success()
+        );
+
+        let generated_info = debug_info(&app, &generated.shortened_url_id).await;
+        assert_eq!(generated_info["generated_id"], true);
+        assert_eq!(generated_info["long_url"], "https://example.com/generated");
+        let vanity_info = debug_info(&app, "vanity12").await;
+        assert_eq!(vanity_info["generated_id"], false);
+        assert_eq!(vanity_info["original_url"], "https://example.com/vanity");
+    }
 }
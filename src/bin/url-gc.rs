@@ -1,15 +1,29 @@
 use anyhow::Context;
 use stoopid_short::{config, url_repo::url_repository_capsule};
+use tracing::info;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+    config::init_tracing();
 
     let container = config::init_container().await?;
+    let url_repo = container.read(url_repository_capsule);
 
-    container
-        .read(url_repository_capsule)
-        .delete_expired_urls()
+    let dry_run = std::env::args().skip(1).any(|arg| arg == "--dry-run")
+        || container.read(config::gc_dry_run_capsule);
+    if dry_run {
+        let num_expired = url_repo
+            .count_expired_urls()
+            .await
+            .context("Failed to count expired URLs")?;
+        info!(num_expired, "Dry run; would have deleted expired URLs");
+        return Ok(());
+    }
+
+    let num_deleted = url_repo
+        .delete_expired_urls(container.read(config::gc_batch_size_capsule))
         .await
-        .context("Failed to delete expired URLs")
+        .context("Failed to delete expired URLs")?;
+    info!(num_deleted, "Deleted expired URLs");
+    Ok(())
 }
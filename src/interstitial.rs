@@ -0,0 +1,137 @@
+use url::Url;
+
+use crate::url_service::host_matches_domain;
+
+/// A branded page shown to browsers for a brief delay before redirecting them to external hosts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interstitial {
+    pub delay_seconds: u64,
+    pub message: String,
+    /// Hosts (and their subdomains) that are redirected to directly
+    pub exempt_hosts: Vec<String>,
+}
+
+impl Interstitial {
+    pub const DEFAULT_DELAY_SECONDS: u64 = 3;
+    pub const DEFAULT_MESSAGE: &str = "You are leaving stoopid-short.";
+
+    /// Whether the interstitial should be shown before redirecting to `url`.
+    #[must_use]
+    pub fn applies_to(&self, url: &str) -> bool {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned));
+        host.is_none_or(|host| {
+            !self
+                .exempt_hosts
+                .iter()
+                .any(|exempt_host| host_matches_domain(&host, exempt_host))
+        })
+    }
+
+    /// Renders the interstitial page redirecting to `url`, unless `url` isn't http(s),
+    /// since the page would run a `javascript:` (or similar) URL when followed.
+    #[must_use]
+    pub fn render(&self, url: &str) -> Option<String> {
+        if !Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+            return None;
+        }
+        let Self {
+            delay_seconds,
+            message,
+            ..
+        } = self;
+        let (message, url) = (escape_html(message), escape_html(url));
+        Some(format!(
+            r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <meta http-equiv="refresh" content="{delay_seconds};url={url}">
+    <title>Redirecting...</title>
+  </head>
+  <body>
+    <p>{message}</p>
+    <p>You will be redirected to <code>{url}</code> in {delay_seconds} seconds.</p>
+    <p><a href="{url}">Continue</a></p>
+  </body>
+</html>
+"#
+        ))
+    }
+}
+
+fn escape_html(unescaped: &str) -> String {
+    unescaped
+        .chars()
+        .fold(String::with_capacity(unescaped.len()), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&#39;"),
+                c => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn new_interstitial() -> Interstitial {
+        Interstitial {
+            delay_seconds: Interstitial::DEFAULT_DELAY_SECONDS,
+            message: Interstitial::DEFAULT_MESSAGE.to_owned(),
+            exempt_hosts: vec!["gsconrad.com".to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_applies_to_external_host() {
+        assert!(new_interstitial().applies_to("https://example.com/"));
+    }
+
+    #[test]
+    fn test_does_not_apply_to_exempt_host() {
+        let interstitial = new_interstitial();
+        assert!(!interstitial.applies_to("https://gsconrad.com/"));
+        assert!(!interstitial.applies_to("https://blog.gsconrad.com/post"));
+    }
+
+    #[test]
+    fn test_render_includes_redirect_and_continue_link() {
+        let html = new_interstitial().render("https://example.com/").unwrap();
+        assert!(html.contains(r#"content="3;url=https://example.com/""#));
+        assert!(html.contains(r#"<a href="https://example.com/">Continue</a>"#));
+        assert!(html.contains(Interstitial::DEFAULT_MESSAGE));
+    }
+
+    #[test]
+    fn test_render_escapes_html() {
+        let interstitial = Interstitial {
+            message: "<script>alert('hi')</script>".to_owned(),
+            ..new_interstitial()
+        };
+        let html = interstitial
+            .render(r#"https://example.com/?a="b"&c=d"#)
+            .unwrap();
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt;"));
+        assert!(html.contains("https://example.com/?a=&quot;b&quot;&amp;c=d"));
+    }
+
+    #[test]
+    fn test_render_refuses_non_http_urls() {
+        let interstitial = new_interstitial();
+        assert_eq!(
+            interstitial.render("javascript:alert(document.cookie)"),
+            None
+        );
+        assert_eq!(interstitial.render("data:text/html,<p>hi</p>"), None);
+        assert_eq!(interstitial.render("not a url"), None);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod auth;
 pub mod config;
+pub mod interstitial;
+pub mod metrics;
+pub mod migration;
+pub mod openapi;
 mod orm;
+pub mod rate_limit;
 pub mod url_repo;
 pub mod url_service;
+pub mod url_validation;
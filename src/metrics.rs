@@ -0,0 +1,105 @@
+use std::{
+    fmt::Write,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rearch::CapsuleHandle;
+use tracing::{instrument, warn};
+
+use crate::{config::expired_urls_sample_interval_capsule, url_repo::UrlRepository};
+
+/// The [`Metrics`] served at `/metrics`, if sampling them is enabled.
+#[must_use]
+pub fn metrics_capsule(CapsuleHandle { mut get, .. }: CapsuleHandle) -> Option<Arc<Metrics>> {
+    get.as_ref(expired_urls_sample_interval_capsule)
+        .map(|_| Arc::new(Metrics::default()))
+}
+
+/// Gauges that are sampled in the background, rendered in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// How many expired items were still waiting to be deleted (by `url-gc`) when last sampled
+    expired_urls: AtomicU64,
+}
+
+impl Metrics {
+    /// How many expired items were still waiting to be deleted when last sampled.
+    #[must_use]
+    pub fn expired_urls(&self) -> u64 {
+        self.expired_urls.load(Ordering::Relaxed)
+    }
+
+    /// Renders every gauge in the Prometheus text exposition format.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut rendered = String::new();
+        let _ = writeln!(
+            rendered,
+            "# HELP stoopid_short_expired_urls Expired short URLs not yet deleted\n\
+            # TYPE stoopid_short_expired_urls gauge\n\
+            stoopid_short_expired_urls {}",
+            self.expired_urls()
+        );
+        rendered
+    }
+
+    /// Samples how many expired items `url_repo` holds every `period`, forever,
+    /// so that a growing backlog can alert that `url-gc` is falling behind.
+    pub async fn sample_expired_urls(&self, url_repo: &dyn UrlRepository, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            self.sample_expired_urls_once(url_repo).await;
+        }
+    }
+
+    /// Samples how many expired items `url_repo` holds, keeping the previous sample on failure.
+    #[instrument(skip_all)]
+    async fn sample_expired_urls_once(&self, url_repo: &dyn UrlRepository) {
+        match url_repo.count_expired_urls().await {
+            Ok(expired_urls) => self.expired_urls.store(expired_urls, Ordering::Relaxed),
+            Err(err) => warn!(?err, "Failed to count expired URLs"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::url_repo::MockUrlRepository;
+
+    #[tokio::test]
+    async fn test_gauge_reflects_expired_count() {
+        let mut mock_repo = MockUrlRepository::new();
+        let mut seq = mockall::Sequence::new();
+        mock_repo
+            .expect_count_expired_urls()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|| Ok(42));
+        mock_repo
+            .expect_count_expired_urls()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|| Err(anyhow!("database is down")));
+
+        let metrics = Metrics::default();
+        metrics.sample_expired_urls_once(&mock_repo).await;
+        assert_eq!(metrics.expired_urls(), 42);
+        assert!(
+            metrics
+                .render()
+                .contains("\nstoopid_short_expired_urls 42\n")
+        );
+
+        // NOTE: a failed sample keeps reporting the last known count
+        metrics.sample_expired_urls_once(&mock_repo).await;
+        assert_eq!(metrics.expired_urls(), 42);
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Context;
+use sea_orm::{
+    ConnectionTrait, DbConn, Schema,
+    sea_query::{ColumnDef, Index, OnConflict, Query, Table},
+};
+use time::OffsetDateTime;
+use tracing::{info, instrument};
+
+use crate::orm::short_url;
+
+/// Records which [`ADDED_COLUMNS`] have been applied (shaped like sea-orm-migration's own table).
+const MIGRATIONS_TABLE: &str = "seaql_migrations";
+/// Columns added to the `urls` table since it was first created, in the order they were added,
+/// each with the version that records it as applied (and so must never be renamed).
+const ADDED_COLUMNS: [(&str, short_url::Column); 10] = [
+    ("m0001_add_original_url", short_url::Column::OriginalUrl),
+    (
+        "m0002_add_created_at_seconds",
+        short_url::Column::CreatedAtSeconds,
+    ),
+    ("m0003_add_hit_count", short_url::Column::HitCount),
+    ("m0004_add_utm_template", short_url::Column::UtmTemplate),
+    ("m0005_add_discoverable", short_url::Column::Discoverable),
+    (
+        "m0006_add_cache_max_age_seconds",
+        short_url::Column::CacheMaxAgeSeconds,
+    ),
+    ("m0007_add_single_use", short_url::Column::SingleUse),
+    ("m0008_add_max_hits", short_url::Column::MaxHits),
+    (
+        "m0009_add_not_before_seconds",
+        short_url::Column::NotBeforeSeconds,
+    ),
+    ("m0010_add_owner", short_url::Column::Owner),
+];
+
+/// Backs the expired URLs cleanup, which filters (and deletes) by expiration time.
+const EXPIRATION_INDEX: &str = "idx_urls_expiration_time_seconds";
+/// Backs POST deduplication, which looks for a recent short URL to the same long URL.
+const LONG_URL_INDEX: &str = "idx_urls_long_url_created_at_seconds";
+
+/// Creates the `urls` table and its indexes, and adds any [`ADDED_COLUMNS`] that
+/// an existing table is missing, skipping whatever was already applied
+/// (so that it is safe to run repeatedly).
+///
+/// # Errors
+/// Will return [`Err`] if the table, one of its columns, or one of its indexes
+/// could not be created.
+#[instrument(skip(db))]
+pub async fn up(db: &DbConn) -> anyhow::Result<()> {
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(
+        schema
+            .create_table_from_entity(short_url::Entity)
+            .if_not_exists(),
+    )
+    .await
+    .context("Failed to create urls table")?;
+    add_columns(db, &schema).await?;
+
+    db.execute(
+        Index::create()
+            .if_not_exists()
+            .name(EXPIRATION_INDEX)
+            .table(short_url::Entity)
+            .col(short_url::Column::ExpirationTimeSeconds),
+    )
+    .await
+    .context("Failed to create expiration time index")?;
+    db.execute(
+        Index::create()
+            .if_not_exists()
+            .name(LONG_URL_INDEX)
+            .table(short_url::Entity)
+            .col(short_url::Column::LongUrl)
+            .col(short_url::Column::CreatedAtSeconds),
+    )
+    .await
+    .context("Failed to create long URL index")?;
+
+    info!("Migrated database up");
+    Ok(())
+}
+
+/// Drops the `urls` table (along with its indexes and all of its data), if it exists.
+///
+/// # Errors
+/// Will return [`Err`] if the table could not be dropped.
+#[instrument(skip(db))]
+pub async fn down(db: &DbConn) -> anyhow::Result<()> {
+    db.execute(Table::drop().if_exists().table(short_url::Entity))
+        .await
+        .context("Failed to drop urls table")?;
+    db.execute(Table::drop().if_exists().table(MIGRATIONS_TABLE))
+        .await
+        .context("Failed to drop migrations table")?;
+
+    info!("Migrated database down");
+    Ok(())
+}
+
+/// Adds each of the [`ADDED_COLUMNS`] that hasn't been applied yet, recording it as applied.
+async fn add_columns(db: &DbConn, schema: &Schema) -> anyhow::Result<()> {
+    db.execute(
+        Table::create()
+            .table(MIGRATIONS_TABLE)
+            .if_not_exists()
+            .col(ColumnDef::new("version").string().not_null().primary_key())
+            .col(ColumnDef::new("applied_at").big_integer().not_null()),
+    )
+    .await
+    .context("Failed to create migrations table")?;
+    let applied_versions = db
+        .query_all(Query::select().column("version").from(MIGRATIONS_TABLE))
+        .await
+        .context("Failed to read applied migrations")?
+        .into_iter()
+        .map(|row| row.try_get::<String>("", "version"))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Failed to read applied migrations")?;
+
+    for (version, column) in ADDED_COLUMNS {
+        if applied_versions.iter().any(|applied| applied == version) {
+            continue;
+        }
+        // NOTE: a table created from the entity (or migrated by hand) already has the column
+        if !has_column(db, column).await {
+            db.execute(
+                Table::alter()
+                    .table(short_url::Entity)
+                    .add_column(schema.get_column_def::<short_url::Entity>(column)),
+            )
+            .await
+            .with_context(|| format!("Failed to apply migration {version}"))?;
+            info!(version, "Added column to urls table");
+        }
+        db.execute(
+            Query::insert()
+                .into_table(MIGRATIONS_TABLE)
+                .columns(["version", "applied_at"])
+                .values_panic([
+                    version.into(),
+                    OffsetDateTime::now_utc().unix_timestamp().into(),
+                ])
+                .on_conflict(OnConflict::column("version").do_nothing().to_owned()),
+        )
+        .await
+        .with_context(|| format!("Failed to record migration {version}"))?;
+    }
+    Ok(())
+}
+
+/// Whether the `urls` table has `column`, going by whether it can be selected.
+async fn has_column(db: &DbConn, column: short_url::Column) -> bool {
+    // NOTE: qualified, since SQLite treats an unknown (quoted) column name as a string literal
+    db.query_all(
+        Query::select()
+            .column((short_url::Entity, column))
+            .from(short_url::Entity)
+            .limit(0),
+    )
+    .await
+    .is_ok()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use sea_orm::{Database, EntityTrait, PaginatorTrait};
+    use time::macros::datetime;
+
+    use super::*;
+
+    async fn index_names(db: &DbConn) -> Vec<String> {
+        db.query_all_raw(sea_orm::Statement::from_string(
+            db.get_database_backend(),
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'urls' \
+             AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        ))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.try_get::<String>("", "name").unwrap())
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn test_up_is_idempotent() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+
+        up(&db).await.unwrap();
+        up(&db).await.unwrap();
+
+        assert_eq!(short_url::Entity::find().count(&db).await.unwrap(), 0);
+        assert_eq!(index_names(&db).await, [EXPIRATION_INDEX, LONG_URL_INDEX]);
+    }
+
+    #[tokio::test]
+    async fn test_up_upgrades_baseline_table() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared(
+            "CREATE TABLE urls (id TEXT NOT NULL PRIMARY KEY, long_url TEXT NOT NULL, \
+             expiration_time_seconds BIGINT NOT NULL)",
+        )
+        .await
+        .unwrap();
+        db.execute_unprepared(
+            "INSERT INTO urls VALUES ('abc12345', 'https://example.com/', 4102444800)",
+        )
+        .await
+        .unwrap();
+
+        up(&db).await.unwrap();
+        up(&db).await.unwrap();
+
+        let model = short_url::Entity::find_by_id("abc12345")
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(model.long_url, "https://example.com/");
+        assert_eq!(
+            model.expiration_time_seconds,
+            datetime!(2100-01-01 00:00 UTC)
+        );
+        assert_eq!(model.original_url, None);
+        assert_eq!(model.created_at_seconds, None);
+        assert_eq!(model.hit_count, 0);
+        assert!(!model.discoverable);
+        assert_eq!(model.single_use, None);
+        assert_eq!(model.max_hits, None);
+        assert_eq!(index_names(&db).await, [EXPIRATION_INDEX, LONG_URL_INDEX]);
+    }
+
+    #[tokio::test]
+    async fn test_up_skips_columns_added_by_hand() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared(
+            "CREATE TABLE urls (id TEXT NOT NULL PRIMARY KEY, long_url TEXT NOT NULL, \
+             expiration_time_seconds BIGINT NOT NULL, hit_count BIGINT NOT NULL DEFAULT 0)",
+        )
+        .await
+        .unwrap();
+
+        up(&db).await.unwrap();
+
+        assert_eq!(short_url::Entity::find().count(&db).await.unwrap(), 0);
+        let applied_migrations = db
+            .query_all(Query::select().column("version").from(MIGRATIONS_TABLE))
+            .await
+            .unwrap();
+        assert_eq!(applied_migrations.len(), ADDED_COLUMNS.len());
+    }
+
+    #[tokio::test]
+    async fn test_down_drops_table() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        up(&db).await.unwrap();
+
+        down(&db).await.unwrap();
+        assert!(short_url::Entity::find().count(&db).await.is_err());
+        down(&db).await.unwrap();
+
+        up(&db).await.unwrap();
+        assert_eq!(short_url::Entity::find().count(&db).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,32 @@
+/// A hand-maintained description of the HTTP API (in the `OpenAPI` 3 format), for client codegen.
+///
+/// NOTE: keep in sync with the server's routes and their payloads.
+pub const OPENAPI_SPEC: &str = include_str!("openapi.json");
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_is_valid_json_with_documented_paths_and_schemas() {
+        let spec = serde_json::from_str::<serde_json::Value>(OPENAPI_SPEC).unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+
+        for path in ["/", "/bulk", "/health", "/policy", "/{id}", "/{id}/stats"] {
+            assert!(spec["paths"][path].is_object(), "missing path {path}");
+        }
+        for schema in [
+            "PostUrlPayload",
+            "PutUrlPayload",
+            "ShortenedUrl",
+            "UrlStats",
+            "Error",
+        ] {
+            assert!(
+                spec["components"]["schemas"][schema].is_object(),
+                "missing schema {schema}"
+            );
+        }
+    }
+}
@@ -11,6 +11,31 @@ pub(crate) mod short_url {
         pub id: String,
         pub long_url: String,
         pub expiration_time_seconds: TimeUnixTimestamp,
+        // NOTE: the columns below were added after the table was first created,
+        // so each (and any new column) needs migrating in `crate::migration`
+        /// The URL exactly as submitted, before [`url::Url`] normalization (for auditing)
+        pub original_url: Option<String>,
+        /// NOTE: nullable since rows created before this column was added have no creation time
+        pub created_at_seconds: Option<TimeUnixTimestamp>,
+        /// How many times this URL has been redirected to.
+        #[sea_orm(default_value = 0)]
+        pub hit_count: i64,
+        /// Query parameters (e.g. `utm_source=x&utm_medium=y`) merged onto `long_url` on redirect.
+        pub utm_template: Option<String>,
+        /// Whether this URL may be listed publicly (e.g. in the sitemap).
+        #[sea_orm(default_value = false)]
+        pub discoverable: bool,
+        /// How long redirects to `long_url` may be cached for, overriding the default
+        /// (which caches until expiration).
+        pub cache_max_age_seconds: Option<i64>,
+        /// Whether this URL is deleted after its first redirect (treating NULL as false).
+        pub single_use: Option<bool>,
+        /// How many redirects this URL allows before it's gone, if limited.
+        pub max_hits: Option<i64>,
+        /// When this URL starts redirecting, if not immediately.
+        pub not_before_seconds: Option<TimeUnixTimestamp>,
+        /// Which API key (by its [`crate::auth::api_key_id`]) created this URL, if any.
+        pub owner: Option<String>,
     }
 
     impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,250 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr},
+    num::NonZeroU32,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use rearch::CapsuleHandle;
+
+use crate::config::rate_limit_per_minute_capsule;
+
+/// The [`RateLimiter`] for creates, if rate limiting is enabled.
+#[must_use]
+pub fn rate_limiter_capsule(
+    CapsuleHandle { mut get, .. }: CapsuleHandle,
+) -> Option<Arc<RateLimiter>> {
+    get.as_ref(rate_limit_per_minute_capsule)
+        .map(|per_minute| Arc::new(RateLimiter::new(per_minute)))
+}
+
+/// A token-bucket rate limiter keyed by [`RateLimitKey`], where each client may burst
+/// up to a minute's worth of requests.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_minute: NonZeroU32,
+    buckets: Mutex<HashMap<RateLimitKey, Bucket>>,
+}
+
+/// Who a request is limited as.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// An authenticated API key (by its [`crate::auth::api_key_id`]),
+    /// wherever its requests come from.
+    ApiKey(String),
+    /// A client IP, or IPv6 /64 since that's usually what a single client is assigned.
+    Ip(IpAddr),
+}
+impl From<IpAddr> for RateLimitKey {
+    fn from(ip: IpAddr) -> Self {
+        Self::Ip(client_key(ip))
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Past this many tracked clients, clients whose buckets have refilled are forgotten,
+    /// and then the clients closest to a full bucket, so that memory stays bounded.
+    const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+    #[must_use]
+    pub fn new(per_minute: NonZeroU32) -> Self {
+        Self {
+            per_minute,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Takes `cost` tokens for `key` at `now`, or returns how long until they are available.
+    ///
+    /// NOTE: a `cost` beyond a minute's worth of tokens is allowed once the bucket is full,
+    /// leaving it in debt, so that large requests are delayed rather than never allowed.
+    ///
+    /// # Errors
+    /// Returns the time to wait when `key` doesn't have enough tokens left.
+    pub fn check(
+        &self,
+        key: impl Into<RateLimitKey>,
+        cost: NonZeroU32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let key = key.into();
+        let capacity = f64::from(self.per_minute.get());
+        let cost = f64::from(cost.get());
+        let required_tokens = cost.min(capacity);
+        let tokens_per_second = capacity / 60.0;
+        let refilled_tokens = |bucket: &Bucket| {
+            let elapsed = now.saturating_duration_since(bucket.last_refill);
+            elapsed
+                .as_secs_f64()
+                .mul_add(tokens_per_second, bucket.tokens)
+                .min(capacity)
+        };
+
+        // NOTE: a poisoned lock only means another request panicked mid-update,
+        // which at worst leaves one bucket slightly off
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() >= Self::MAX_TRACKED_CLIENTS && !buckets.contains_key(&key) {
+            buckets.retain(|_, bucket| refilled_tokens(bucket) < capacity);
+            if buckets.len() >= Self::MAX_TRACKED_CLIENTS
+                && let Some(fullest) = buckets
+                    .iter()
+                    .max_by(|(_, a), (_, b)| refilled_tokens(a).total_cmp(&refilled_tokens(b)))
+                    .map(|(fullest, _)| fullest.clone())
+            {
+                buckets.remove(&fullest);
+            }
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+        bucket.tokens = refilled_tokens(bucket);
+        bucket.last_refill = now;
+
+        let result = if bucket.tokens >= required_tokens {
+            bucket.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (required_tokens - bucket.tokens) / tokens_per_second,
+            ))
+        };
+        drop(buckets);
+        result
+    }
+}
+
+/// The key that `ip` is limited under: IPv4 addresses as they are,
+/// and IPv6 addresses by their /64 prefix.
+const fn client_key(ip: IpAddr) -> IpAddr {
+    match ip.to_canonical() {
+        IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from_bits(ip.to_bits() & (u128::MAX << 64))),
+        ip @ IpAddr::V4(_) => ip,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const CLIENT_A: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 1));
+    const CLIENT_B: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(203, 0, 113, 2));
+    const ONE: NonZeroU32 = NonZeroU32::MIN;
+
+    fn limiter(per_minute: u32) -> RateLimiter {
+        RateLimiter::new(NonZeroU32::new(per_minute).unwrap())
+    }
+
+    #[test]
+    fn test_allows_burst_up_to_limit() {
+        let limiter = limiter(3);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.check(CLIENT_A, ONE, now), Ok(()));
+        }
+        assert!(limiter.check(CLIENT_A, ONE, now).is_err());
+    }
+
+    #[test]
+    fn test_reports_retry_after() {
+        let limiter = limiter(60);
+        let now = Instant::now();
+        for _ in 0..60 {
+            limiter.check(CLIENT_A, ONE, now).unwrap();
+        }
+        let retry_after = limiter.check(CLIENT_A, ONE, now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_refills_over_time() {
+        let limiter = limiter(60);
+        let now = Instant::now();
+        for _ in 0..60 {
+            limiter.check(CLIENT_A, ONE, now).unwrap();
+        }
+        assert!(limiter.check(CLIENT_A, ONE, now).is_err());
+        assert_eq!(
+            limiter.check(CLIENT_A, ONE, now + Duration::from_secs(1)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_clients_are_limited_independently() {
+        let limiter = limiter(1);
+        let now = Instant::now();
+        assert_eq!(limiter.check(CLIENT_A, ONE, now), Ok(()));
+        assert!(limiter.check(CLIENT_A, ONE, now).is_err());
+        assert_eq!(limiter.check(CLIENT_B, ONE, now), Ok(()));
+    }
+
+    #[test]
+    fn test_api_keys_are_limited_independently_of_ip() {
+        let limiter = limiter(1);
+        let now = Instant::now();
+        let key_a = RateLimitKey::ApiKey("key-a".to_owned());
+        let key_b = RateLimitKey::ApiKey("key-b".to_owned());
+        assert_eq!(limiter.check(key_a.clone(), ONE, now), Ok(()));
+        assert!(limiter.check(key_a, ONE, now).is_err());
+        assert_eq!(limiter.check(key_b, ONE, now), Ok(()));
+        assert_eq!(limiter.check(CLIENT_A, ONE, now), Ok(()));
+    }
+
+    #[test]
+    fn test_charges_cost() {
+        let limiter = limiter(3);
+        let now = Instant::now();
+        assert_eq!(
+            limiter.check(CLIENT_A, NonZeroU32::new(2).unwrap(), now),
+            Ok(())
+        );
+        assert_eq!(limiter.check(CLIENT_A, ONE, now), Ok(()));
+        assert!(limiter.check(CLIENT_A, ONE, now).is_err());
+    }
+
+    #[test]
+    fn test_cost_beyond_capacity_leaves_bucket_in_debt() {
+        let limiter = limiter(60);
+        let now = Instant::now();
+        let cost = NonZeroU32::new(120).unwrap();
+        assert_eq!(limiter.check(CLIENT_A, cost, now), Ok(()));
+        let retry_after = limiter.check(CLIENT_A, ONE, now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(61));
+    }
+
+    #[test]
+    fn test_ipv6_clients_are_limited_by_64_prefix() {
+        let limiter = limiter(1);
+        let now = Instant::now();
+        let client: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+        let same_prefix: IpAddr = "2001:db8:1:2:ffff::1".parse().unwrap();
+        let other_prefix: IpAddr = "2001:db8:1:3::1".parse().unwrap();
+        assert_eq!(limiter.check(client, ONE, now), Ok(()));
+        assert!(limiter.check(same_prefix, ONE, now).is_err());
+        assert_eq!(limiter.check(other_prefix, ONE, now), Ok(()));
+    }
+
+    #[test]
+    fn test_tracked_clients_are_capped() {
+        let limiter = limiter(1);
+        let now = Instant::now();
+        for client in 0..=RateLimiter::MAX_TRACKED_CLIENTS {
+            let client = IpAddr::V4(u32::try_from(client).unwrap().into());
+            limiter.check(client, ONE, now).unwrap();
+        }
+        assert_eq!(
+            limiter.buckets.lock().unwrap().len(),
+            RateLimiter::MAX_TRACKED_CLIENTS
+        );
+    }
+}
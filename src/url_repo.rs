@@ -1,33 +1,175 @@
-use std::sync::Arc;
+use std::{collections::HashSet, num::NonZeroU64, str::FromStr, sync::Arc};
 
 use anyhow::Context;
 use async_trait::async_trait;
 use rearch::CapsuleHandle;
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, EntityTrait, QueryFilter,
-    TransactionError, TransactionTrait, value::TimeUnixTimestamp,
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, DbErr, EntityTrait, ExprTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, Select, SqlErr,
+    TransactionError, TransactionTrait, sea_query::Expr, value::TimeUnixTimestamp,
 };
+use serde::Serialize;
 use thiserror::Error;
 use time::{Duration, OffsetDateTime};
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 use url::Url;
 
-use crate::{config::db_conn_capsule, orm::short_url};
+use crate::{
+    config::{
+        cache_enabled_capsule, db_conn_capsule, negative_cache_seconds_capsule,
+        repo_backend_capsule, request_timings_capsule, secondary_db_conn_capsule,
+    },
+    orm::short_url,
+};
+
+pub mod caching;
+pub mod in_memory;
+pub mod read_through;
+pub mod timed;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ShortUrl {
     pub(crate) short_id: ShortId,
     pub(crate) url: Url,
     pub(crate) expiration_time: ExpirationTime,
+    pub(crate) original_url: Option<String>,
+    /// Query parameters merged onto `url` on redirect, if any
+    pub(crate) utm_template: Option<String>,
+    /// Whether this URL may be listed publicly (e.g. in the sitemap)
+    pub(crate) discoverable: bool,
+    /// How long redirects may be cached for, if shorter than until expiration
+    pub(crate) cache_max_age_seconds: Option<u64>,
+    /// Whether this URL is deleted after its first redirect
+    pub(crate) single_use: bool,
+    /// How many redirects this URL allows before it's gone, if limited
+    pub(crate) max_hits: Option<u64>,
+    /// When this URL starts redirecting, if not immediately
+    pub(crate) not_before: Option<OffsetDateTime>,
+    /// Which API key (by its [`crate::auth::api_key_id`]) created this URL, if any
+    pub(crate) owner: Option<String>,
+}
+impl ShortUrl {
+    /// Whether both [`ShortUrl`]s describe the same link, ignoring audit-only fields.
+    pub(crate) fn is_equivalent_to(&self, other: &Self) -> bool {
+        self.expiration_time == other.expiration_time
+            && self.is_equivalent_apart_from_expiration(other)
+    }
+
+    /// Like [`ShortUrl::is_equivalent_to`], but for when either expiration time would do
+    /// (e.g., because this one was only defaulted).
+    pub(crate) fn is_equivalent_apart_from_expiration(&self, other: &Self) -> bool {
+        self.short_id == other.short_id
+            && self.url == other.url
+            && self.utm_template == other.utm_template
+            && self.discoverable == other.discoverable
+            && self.cache_max_age_seconds == other.cache_max_age_seconds
+            && self.single_use == other.single_use
+            && self.max_hits == other.max_hits
+            && self.not_before == other.not_before
+    }
+
+    /// Whether this URL's not-before time (if any) has passed.
+    pub(crate) fn is_active(&self) -> bool {
+        self.not_before
+            .is_none_or(|not_before| not_before <= OffsetDateTime::now_utc())
+    }
+
+    /// A plain (unlimited and immediately active) [`ShortUrl`] for tests,
+    /// which expires `expires_in` from now (and so may already be expired).
+    #[cfg(test)]
+    #[allow(clippy::unwrap_used)]
+    pub(crate) fn new_for_test(id: &str, url: &str, expires_in: Duration) -> Self {
+        Self {
+            short_id: ShortId::new(id.to_owned(), ShortIdLengthBounds::default()).unwrap(),
+            url: Url::parse(url).unwrap(),
+            expiration_time: ExpirationTime::from_db(OffsetDateTime::now_utc() + expires_in),
+            original_url: None,
+            utm_template: None,
+            discoverable: false,
+            cache_max_age_seconds: None,
+            single_use: false,
+            max_hits: None,
+            not_before: None,
+            owner: None,
+        }
+    }
+}
+
+/// The outcome of looking up a [`ShortUrl`] by its id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RetrievedUrl {
+    Found(Box<ShortUrl>),
+    /// The item exists, but has expired (and has not been cleaned up yet).
+    Expired,
+    NotFound,
+}
+
+/// Whether an in-memory cache in front of the repository served a lookup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+}
+impl CacheStatus {
+    /// The value of the `X-Cache` header reporting this status.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Hit => "HIT",
+            Self::Miss => "MISS",
+        }
+    }
+}
+
+/// Usage information about a stored item, which (unlike [`ShortUrl`]) may already be expired.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShortUrlStats {
+    pub(crate) url: Url,
+    pub(crate) expiration_time: OffsetDateTime,
+    pub(crate) hit_count: u64,
+    pub(crate) cache_max_age_seconds: Option<u64>,
+    pub(crate) max_hits: Option<u64>,
+}
+impl ShortUrlStats {
+    /// Whether this item has used up all of its redirects (if limited).
+    pub(crate) fn is_exhausted(&self) -> bool {
+        hits_exhausted(self.hit_count, self.max_hits)
+    }
+}
+
+/// Whether an item with `hit_count` hits has used up its `max_hits` redirects (if limited),
+/// after which every reader treats it as expired.
+fn hits_exhausted<T: PartialOrd + Copy>(hit_count: T, max_hits: Option<T>) -> bool {
+    max_hits.is_some_and(|max_hits| hit_count >= max_hits)
+}
+
+/// The inclusive range of lengths that new [`ShortId`]s must fall within.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShortIdLengthBounds {
+    pub min_len: usize,
+    pub max_len: usize,
+}
+impl Default for ShortIdLengthBounds {
+    fn default() -> Self {
+        Self {
+            min_len: 6,
+            max_len: 16,
+        }
+    }
 }
 
+/// Separates a namespace from the [`ShortId`] within it.
+pub const NAMESPACE_SEPARATOR: char = '/';
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ShortId {
     inner: String,
 }
 impl ShortId {
-    pub(crate) fn new(short_id: String) -> Result<Self, ShortIdValidationError> {
-        let (min_len, max_len) = (6, 16);
+    pub(crate) fn new(
+        short_id: String,
+        ShortIdLengthBounds { min_len, max_len }: ShortIdLengthBounds,
+    ) -> Result<Self, ShortIdValidationError> {
         if !(min_len..=max_len).contains(&short_id.len()) {
             return Err(ShortIdValidationError::InvalidLength { min_len, max_len });
         }
@@ -43,11 +185,30 @@ impl ShortId {
         Ok(Self { inner: short_id })
     }
 
+    /// Wraps an ID read back from storage, which was validated when it was created
+    /// (possibly under different length bounds), so it is not validated again.
+    const fn from_db(short_id: String) -> Self {
+        Self { inner: short_id }
+    }
+
+    /// Prefixes this ID with `namespace`, so that it only needs to be unique within it.
+    pub(crate) fn in_namespace(self, namespace: &Self) -> Self {
+        Self {
+            inner: format!("{}{NAMESPACE_SEPARATOR}{}", namespace.inner, self.inner),
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.inner
+    }
+
     pub(crate) fn into_inner(self) -> String {
         self.inner
     }
 }
-#[derive(Debug, Error)]
+/// Serializes to the machine-readable fields of the variant (e.g. `min_len` and `max_len`).
+#[derive(Clone, Debug, Error, Serialize)]
+#[serde(untagged)]
 pub enum ShortIdValidationError {
     #[error("short ID length must be between {min_len} and {max_len}")]
     InvalidLength { min_len: usize, max_len: usize },
@@ -60,19 +221,21 @@ pub struct ExpirationTime {
     inner: OffsetDateTime,
 }
 impl ExpirationTime {
+    pub const DEFAULT_MAX_TTL: Duration = Duration::days(10 * 365);
+
+    /// Validates that `proposed_time` is in the future, but no more than `max_ttl` from now.
     pub(crate) fn new(
         proposed_time: OffsetDateTime,
+        max_ttl: Duration,
     ) -> Result<Self, ExpirationTimeValidationError> {
-        const MAX_TTL: Duration = Duration::days(10 * 365);
-
         let now = OffsetDateTime::now_utc();
         if proposed_time < now {
             return Err(ExpirationTimeValidationError::InPast);
         }
 
-        let max_time = now + MAX_TTL;
+        let max_time = now.saturating_add(max_ttl);
         if proposed_time > max_time {
-            return Err(ExpirationTimeValidationError::TooFarInFuture { max_time });
+            return Err(ExpirationTimeValidationError::TooFarInFuture { max_ttl, max_time });
         }
 
         Ok(Self {
@@ -80,33 +243,155 @@ impl ExpirationTime {
         })
     }
 
+    /// Wraps an expiration time read back from storage, which may have since passed
+    /// or exceed a since-lowered maximum TTL, so it is not validated again.
+    const fn from_db(expiration_time: OffsetDateTime) -> Self {
+        Self {
+            inner: expiration_time,
+        }
+    }
+
     pub(crate) const fn into_inner(self) -> OffsetDateTime {
         self.inner
     }
 }
 #[derive(Debug, Error)]
 pub enum ExpirationTimeValidationError {
-    #[error("expiration time is too far in the future; the current maximum is {max_time}")]
-    TooFarInFuture { max_time: OffsetDateTime },
+    #[error(
+        "expiration time is too far in the future; the maximum TTL is {max_ttl}, \
+        which currently ends at {max_time}"
+    )]
+    TooFarInFuture {
+        max_ttl: Duration,
+        max_time: OffsetDateTime,
+    },
     #[error("expiration time cannot be in the past")]
     InPast,
 }
 
+/// Where [`ShortUrl`]s are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RepoBackend {
+    /// The database given by `DB_URL`.
+    #[default]
+    Db,
+    /// Process memory, which is lost on restart (for local demos and tests).
+    Memory,
+}
+impl FromStr for RepoBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "db" => Ok(Self::Db),
+            "memory" => Ok(Self::Memory),
+            _ => Err(format!("unknown repo backend {s}; expected db or memory")),
+        }
+    }
+}
+
 pub fn url_repository_capsule(
     CapsuleHandle { mut get, .. }: CapsuleHandle,
 ) -> Arc<dyn UrlRepository> {
-    let db = get.as_ref(db_conn_capsule).clone();
-    Arc::new(UrlRepositoryImpl { db })
+    let url_repo: Arc<dyn UrlRepository> = match get.as_ref(repo_backend_capsule) {
+        RepoBackend::Db => {
+            let db = get.as_ref(db_conn_capsule).clone();
+            let primary = Arc::new(UrlRepositoryImpl { db });
+            match get.as_ref(secondary_db_conn_capsule).clone() {
+                Some(secondary_db) => Arc::new(read_through::ReadThroughUrlRepository {
+                    primary,
+                    secondary: Arc::new(UrlRepositoryImpl { db: secondary_db }),
+                }),
+                None => primary,
+            }
+        }
+        RepoBackend::Memory => Arc::clone(get.as_ref(in_memory::in_memory_url_repository_capsule)),
+    };
+    let url_repo: Arc<dyn UrlRepository> = if *get.as_ref(request_timings_capsule) {
+        Arc::new(timed::TimedUrlRepository { inner: url_repo })
+    } else {
+        url_repo
+    };
+    // NOTE: cache hits are left out of request timings, since they never reach the database
+    if *get.as_ref(cache_enabled_capsule) {
+        let negative_ttl =
+            std::time::Duration::from_secs(*get.as_ref(negative_cache_seconds_capsule));
+        Arc::new(caching::CachingUrlRepository::new(url_repo, negative_ttl))
+    } else {
+        url_repo
+    }
 }
 
 #[async_trait]
 pub trait UrlRepository: Send + Sync {
-    async fn retrieve_url(&self, id: &str) -> anyhow::Result<Option<ShortUrl>>;
+    async fn retrieve_url(&self, id: &str) -> anyhow::Result<RetrievedUrl>;
+
+    /// Like [`UrlRepository::retrieve_url`], but also reports whether a cache served the item,
+    /// or [`None`] when there is no cache in front of the repository.
+    async fn retrieve_url_cached(
+        &self,
+        id: &str,
+    ) -> anyhow::Result<(RetrievedUrl, Option<CacheStatus>)> {
+        Ok((self.retrieve_url(id).await?, None))
+    }
+
+    /// Like [`UrlRepository::retrieve_url`], but also deletes the item when found (not expired),
+    /// atomically so that only one of any concurrent callers finds it.
+    async fn retrieve_and_consume(&self, id: &str) -> anyhow::Result<RetrievedUrl>;
+
+    /// Retrieves usage information for the item with the given id, expired or not.
+    async fn retrieve_url_stats(&self, id: &str) -> anyhow::Result<Option<ShortUrlStats>>;
 
     /// Idempotently saves the [`ShortUrl`] to the database.
     async fn save_url(&self, url: ShortUrl) -> Result<ShortUrl, SaveUrlError>;
 
-    async fn delete_expired_urls(&self) -> anyhow::Result<()>;
+    /// Atomically replaces the item with the same id (expired or not) with the [`ShortUrl`],
+    /// resetting its hit count, returning whether such an item existed.
+    async fn replace_url(&self, url: ShortUrl) -> anyhow::Result<bool>;
+
+    /// Finds the most recently created, non-expired item for the given URL,
+    /// if it was created at or after `since`.
+    async fn find_recently_created(
+        &self,
+        long_url: &str,
+        since: OffsetDateTime,
+    ) -> anyhow::Result<Option<ShortUrl>>;
+
+    /// Finds the distinct hosts that the non-expired items created by `owner` point to.
+    async fn find_owner_hosts(&self, owner: &str) -> anyhow::Result<HashSet<String>>;
+
+    /// Finds up to `limit` non-expired items that were marked discoverable, ordered by id.
+    async fn find_discoverable(&self, limit: u64) -> anyhow::Result<Vec<ShortUrl>>;
+
+    /// Lists up to `limit` items (expired or not) ordered by id,
+    /// starting after the item with id `after` (when given).
+    async fn list_urls(&self, limit: u64, after: Option<String>) -> anyhow::Result<Vec<ShortUrl>>;
+
+    /// Deletes the item with the given id, expired or not, returning whether it existed.
+    async fn delete_url(&self, id: &str) -> anyhow::Result<bool>;
+
+    /// Expires the item with the given id right away, keeping its record (e.g. for auditing),
+    /// returning whether it existed.
+    async fn expire_url(&self, id: &str) -> anyhow::Result<bool>;
+
+    /// Atomically increments the hit count of the item with the given id.
+    async fn increment_hits(&self, id: &str) -> anyhow::Result<()>;
+
+    /// Atomically increments the hit count of the item with the given id
+    /// unless it already reached its `max_hits`, returning whether it was incremented.
+    async fn increment_hits_within_limit(&self, id: &str) -> anyhow::Result<bool>;
+
+    /// Deletes the expired items, returning how many were deleted.
+    ///
+    /// When given a `batch_size`, deletes at most that many items at a time
+    /// (pausing in between) to avoid holding locks on large tables for long.
+    async fn delete_expired_urls(&self, batch_size: Option<NonZeroU64>) -> anyhow::Result<u64>;
+
+    /// Counts the items that [`UrlRepository::delete_expired_urls`] would delete.
+    async fn count_expired_urls(&self) -> anyhow::Result<u64>;
+
+    /// Checks that the underlying storage is reachable.
+    async fn ping(&self) -> anyhow::Result<()>;
 }
 
 #[derive(Debug, Error)]
@@ -125,22 +410,347 @@ struct UrlRepositoryImpl {
 #[async_trait]
 impl UrlRepository for UrlRepositoryImpl {
     #[instrument(skip(self))]
-    async fn retrieve_url(&self, id: &str) -> anyhow::Result<Option<ShortUrl>> {
+    async fn retrieve_url(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
         let opt_url = short_url::Entity::find_by_id(id)
             .one(&self.db)
             .await
             .context("Failed to query for existing item")?;
-        opt_url
-            .filter(|model| *model.expiration_time_seconds >= OffsetDateTime::now_utc())
+        Ok(match opt_url {
+            None => RetrievedUrl::NotFound,
+            Some(model)
+                if *model.expiration_time_seconds < OffsetDateTime::now_utc()
+                    || hits_exhausted(model.hit_count, model.max_hits) =>
+            {
+                RetrievedUrl::Expired
+            }
+            Some(model) => match ShortUrl::try_from(model)? {
+                // NOTE: links that aren't active yet shouldn't reveal that they exist
+                short_url if !short_url.is_active() => RetrievedUrl::NotFound,
+                short_url => RetrievedUrl::Found(Box::new(short_url)),
+            },
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn retrieve_and_consume(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        let id = id.to_owned();
+        self.db
+            .transaction(|txn| {
+                Box::pin(async move {
+                    // NOTE: locking the row makes concurrent consumers wait for this transaction,
+                    // after which they no longer find the item
+                    let Some(model) = short_url::Entity::find_by_id(&id)
+                        .lock_exclusive()
+                        .one(txn)
+                        .await
+                        .context("Failed to query for existing item")?
+                    else {
+                        return Ok(RetrievedUrl::NotFound);
+                    };
+                    if *model.expiration_time_seconds < OffsetDateTime::now_utc() {
+                        return Ok(RetrievedUrl::Expired);
+                    }
+                    let short_url = ShortUrl::try_from(model)?;
+                    if !short_url.is_active() {
+                        return Ok(RetrievedUrl::NotFound);
+                    }
+
+                    short_url::Entity::delete_by_id(&id)
+                        .exec(txn)
+                        .await
+                        .context("Failed to delete consumed item")?;
+                    Ok(RetrievedUrl::Found(Box::new(short_url)))
+                })
+            })
+            .await
+            .map_err(|txn_err| match txn_err {
+                TransactionError::Connection(db_err) => anyhow::Error::from(db_err)
+                    .context("Failed to execute database transaction due to database connection"),
+                TransactionError::Transaction(err) => err,
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn retrieve_url_stats(&self, id: &str) -> anyhow::Result<Option<ShortUrlStats>> {
+        short_url::Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .context("Failed to query for existing item")?
             .map(TryInto::try_into)
             .transpose()
     }
 
     #[instrument(skip(self))]
     async fn save_url(&self, short_url: ShortUrl) -> Result<ShortUrl, SaveUrlError> {
-        let short_id = short_url.short_id.into_inner();
-        let long_url = short_url.url.as_str().to_owned();
-        let expiration_time = short_url.expiration_time.into_inner();
+        /// Maximum number of times to attempt the save transaction before giving up.
+        const SAVE_ATTEMPTS: usize = 3;
+
+        let mut attempt = 1;
+        loop {
+            match self.try_save_url(short_url.clone()).await {
+                Err(SaveUrlError::Internal(err))
+                    if attempt < SAVE_ATTEMPTS
+                        && (is_serialization_failure(&err) || is_unique_violation(&err)) =>
+                {
+                    // NOTE: a unique violation means a concurrent save (e.g., from another replica)
+                    // inserted the same id first; retrying surfaces it as ItemAlreadyExists
+                    warn!(
+                        attempt,
+                        ?err,
+                        "Retrying save after a concurrent write conflict"
+                    );
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn replace_url(&self, short_url: ShortUrl) -> anyhow::Result<bool> {
+        let id = short_url.short_id.as_str().to_owned();
+        // NOTE: a single conditional UPDATE, so that a concurrent delete can't be undone
+        let update_result = short_url::Entity::update_many()
+            .set(Self::new_active_model(short_url))
+            .filter(short_url::Column::Id.eq(id))
+            .exec(&self.db)
+            .await
+            .context("Failed to replace item")?;
+        Ok(update_result.rows_affected > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_recently_created(
+        &self,
+        long_url: &str,
+        since: OffsetDateTime,
+    ) -> anyhow::Result<Option<ShortUrl>> {
+        short_url::Entity::find()
+            .filter(short_url::Column::LongUrl.eq(long_url))
+            .filter(short_url::Column::CreatedAtSeconds.gte(TimeUnixTimestamp(since)))
+            .filter(
+                short_url::Column::ExpirationTimeSeconds
+                    .gte(TimeUnixTimestamp(OffsetDateTime::now_utc())),
+            )
+            .order_by_desc(short_url::Column::CreatedAtSeconds)
+            .one(&self.db)
+            .await
+            .context("Failed to query for recently created items")?
+            .map(TryInto::try_into)
+            .transpose()
+    }
+
+    #[instrument(skip(self))]
+    async fn find_owner_hosts(&self, owner: &str) -> anyhow::Result<HashSet<String>> {
+        // NOTE: hosts aren't stored on their own, so they're parsed from each distinct URL
+        short_url::Entity::find()
+            .select_only()
+            .column(short_url::Column::LongUrl)
+            .distinct()
+            .filter(short_url::Column::Owner.eq(owner))
+            .filter(
+                short_url::Column::ExpirationTimeSeconds
+                    .gte(TimeUnixTimestamp(OffsetDateTime::now_utc())),
+            )
+            .into_tuple::<String>()
+            .all(&self.db)
+            .await
+            .context("Failed to query for an owner's URLs")?
+            .into_iter()
+            .map(|long_url| {
+                let url = Url::parse(&long_url).context("Failed to parse Url from db model")?;
+                Ok(url.host_str().unwrap_or_default().to_owned())
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn find_discoverable(&self, limit: u64) -> anyhow::Result<Vec<ShortUrl>> {
+        short_url::Entity::find()
+            .filter(short_url::Column::Discoverable.eq(true))
+            .filter(
+                short_url::Column::ExpirationTimeSeconds
+                    .gte(TimeUnixTimestamp(OffsetDateTime::now_utc())),
+            )
+            .order_by_asc(short_url::Column::Id)
+            .limit(limit)
+            .all(&self.db)
+            .await
+            .context("Failed to query for discoverable items")?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn list_urls(&self, limit: u64, after: Option<String>) -> anyhow::Result<Vec<ShortUrl>> {
+        let mut cursor = short_url::Entity::find().cursor_by(short_url::Column::Id);
+        if let Some(after) = after {
+            cursor.after(after);
+        }
+        cursor
+            .first(limit)
+            .all(&self.db)
+            .await
+            .context("Failed to query for a page of items")?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_url(&self, id: &str) -> anyhow::Result<bool> {
+        let delete_result = short_url::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await
+            .context("Failed to delete item from database")?;
+        Ok(delete_result.rows_affected > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn expire_url(&self, id: &str) -> anyhow::Result<bool> {
+        let expired_at = OffsetDateTime::now_utc() - Duration::seconds(1);
+        let update_result = short_url::Entity::update_many()
+            .col_expr(
+                short_url::Column::ExpirationTimeSeconds,
+                Expr::value(TimeUnixTimestamp(expired_at)),
+            )
+            .filter(short_url::Column::Id.eq(id))
+            .exec(&self.db)
+            .await
+            .context("Failed to expire item")?;
+        Ok(update_result.rows_affected > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn increment_hits(&self, id: &str) -> anyhow::Result<()> {
+        short_url::Entity::update_many()
+            .col_expr(
+                short_url::Column::HitCount,
+                Expr::col(short_url::Column::HitCount).add(1),
+            )
+            .filter(short_url::Column::Id.eq(id))
+            .exec(&self.db)
+            .await
+            .context("Failed to increment hit count")?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn increment_hits_within_limit(&self, id: &str) -> anyhow::Result<bool> {
+        // NOTE: checking the limit in the same statement keeps concurrent hits from overshooting
+        let update_result = short_url::Entity::update_many()
+            .col_expr(
+                short_url::Column::HitCount,
+                Expr::col(short_url::Column::HitCount).add(1),
+            )
+            .filter(short_url::Column::Id.eq(id))
+            .filter(short_url::Column::MaxHits.is_null().or(
+                Expr::col(short_url::Column::HitCount).lt(Expr::col(short_url::Column::MaxHits)),
+            ))
+            .exec(&self.db)
+            .await
+            .context("Failed to increment hit count within limit")?;
+        Ok(update_result.rows_affected > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_expired_urls(&self, batch_size: Option<NonZeroU64>) -> anyhow::Result<u64> {
+        /// How long to pause between batches, giving other queries a chance at the table.
+        const BATCH_PAUSE: std::time::Duration = std::time::Duration::from_millis(100);
+
+        let curr_time = TimeUnixTimestamp(OffsetDateTime::now_utc());
+        let Some(batch_size) = batch_size else {
+            let delete_result = short_url::Entity::delete_many()
+                .filter(short_url::Column::ExpirationTimeSeconds.lt(curr_time))
+                .exec(&self.db)
+                .await
+                .context("Failed to delete expired items from database")?;
+            info!(?delete_result, "Deleted expired items from database");
+            return Ok(delete_result.rows_affected);
+        };
+
+        let mut total_deleted = 0;
+        loop {
+            let expired_ids = Self::find_expired_ids(curr_time)
+                .limit(batch_size.get())
+                .into_query();
+            let delete_result = short_url::Entity::delete_many()
+                .filter(short_url::Column::Id.in_subquery(expired_ids))
+                .exec(&self.db)
+                .await
+                .context("Failed to delete a batch of expired items from database")?;
+            total_deleted += delete_result.rows_affected;
+            info!(
+                ?delete_result,
+                total_deleted, "Deleted a batch of expired items"
+            );
+
+            // NOTE: a partial batch means no expired items remained
+            if delete_result.rows_affected < batch_size.get() {
+                return Ok(total_deleted);
+            }
+            tokio::time::sleep(BATCH_PAUSE).await;
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn count_expired_urls(&self) -> anyhow::Result<u64> {
+        let curr_time = TimeUnixTimestamp(OffsetDateTime::now_utc());
+        Self::find_expired_ids(curr_time)
+            .count(&self.db)
+            .await
+            .context("Failed to count expired items in database")
+    }
+
+    #[instrument(skip(self))]
+    async fn ping(&self) -> anyhow::Result<()> {
+        self.db.ping().await.context("Failed to ping database")
+    }
+}
+
+impl UrlRepositoryImpl {
+    /// Selects the ids of the items that expired before `curr_time`, filtering on nothing but
+    /// `expiration_time_seconds` so that its index (see [`crate::migration`]) keeps the
+    /// expired URLs cleanup from scanning the whole table.
+    fn find_expired_ids(curr_time: TimeUnixTimestamp) -> Select<short_url::Entity> {
+        short_url::Entity::find()
+            .select_only()
+            .column(short_url::Column::Id)
+            .filter(short_url::Column::ExpirationTimeSeconds.lt(curr_time))
+    }
+
+    /// The model of a newly created (and so not yet followed) [`ShortUrl`].
+    fn new_active_model(short_url: ShortUrl) -> short_url::ActiveModel {
+        // NOTE: no link could ever be followed more than i64::MAX times
+        let max_hits = short_url
+            .max_hits
+            .map(|max_hits| i64::try_from(max_hits).unwrap_or(i64::MAX));
+        // NOTE: no cache age could ever need more than i64::MAX seconds
+        let cache_max_age_seconds = short_url
+            .cache_max_age_seconds
+            .map(|seconds| i64::try_from(seconds).unwrap_or(i64::MAX));
+        short_url::ActiveModel {
+            id: Set(short_url.short_id.into_inner()),
+            long_url: Set(short_url.url.into()),
+            expiration_time_seconds: Set(short_url.expiration_time.into_inner().into()),
+            original_url: Set(short_url.original_url),
+            created_at_seconds: Set(Some(OffsetDateTime::now_utc().into())),
+            hit_count: Set(0),
+            utm_template: Set(short_url.utm_template),
+            discoverable: Set(short_url.discoverable),
+            cache_max_age_seconds: Set(cache_max_age_seconds),
+            single_use: Set(Some(short_url.single_use)),
+            max_hits: Set(max_hits),
+            not_before_seconds: Set(short_url.not_before.map(Into::into)),
+            owner: Set(short_url.owner),
+        }
+    }
+
+    /// Makes a single attempt at the transaction behind [`UrlRepository::save_url`].
+    async fn try_save_url(&self, short_url: ShortUrl) -> Result<ShortUrl, SaveUrlError> {
+        let short_id = short_url.short_id.as_str().to_owned();
+        let to_insert = Self::new_active_model(short_url);
 
         let inserted_model = self
             .db
@@ -165,12 +775,6 @@ impl UrlRepository for UrlRepositoryImpl {
                             .context("Failed to delete existing expired item")?;
                     }
 
-                    let to_insert = short_url::ActiveModel {
-                        id: Set(short_id),
-                        long_url: Set(long_url),
-                        expiration_time_seconds: Set(expiration_time.into()),
-                    };
-
                     Ok(to_insert
                         .insert(txn)
                         .await
@@ -179,7 +783,7 @@ impl UrlRepository for UrlRepositoryImpl {
             })
             .await
             .map_err(|txn_err| match txn_err {
-                TransactionError::Connection(_) => anyhow::Error::from(txn_err)
+                TransactionError::Connection(db_err) => anyhow::Error::from(db_err)
                     .context("Failed to execute database transaction due to database connection")
                     .into(),
                 TransactionError::Transaction(save_url_error) => save_url_error,
@@ -187,18 +791,26 @@ impl UrlRepository for UrlRepositoryImpl {
 
         inserted_model.try_into().map_err(SaveUrlError::from)
     }
+}
 
-    #[instrument(skip(self))]
-    async fn delete_expired_urls(&self) -> anyhow::Result<()> {
-        let curr_time = TimeUnixTimestamp(OffsetDateTime::now_utc());
-        let delete_result = short_url::Entity::delete_many()
-            .filter(short_url::Column::ExpirationTimeSeconds.lt(curr_time))
-            .exec(&self.db)
-            .await
-            .context("Failed to delete expired items from database")?;
-        info!(?delete_result, "Deleted expired items from database");
-        Ok(())
-    }
+/// Whether the error came from a serialization failure (SQLSTATE 40001),
+/// which is safe to retry since the transaction was rolled back.
+fn is_serialization_failure(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<DbErr>())
+        .any(|db_err| db_err.to_string().contains("could not serialize access"))
+}
+
+/// Whether the error came from inserting an id that already exists (SQLSTATE 23505).
+fn is_unique_violation(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<DbErr>())
+        .any(|db_err| {
+            matches!(db_err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_)))
+                || db_err
+                    .to_string()
+                    .contains("duplicate key value violates unique constraint")
+        })
 }
 
 impl TryFrom<short_url::Model> for ShortUrl {
@@ -209,17 +821,111 @@ impl TryFrom<short_url::Model> for ShortUrl {
             id,
             long_url,
             expiration_time_seconds,
+            original_url,
+            created_at_seconds: _,
+            hit_count: _,
+            utm_template,
+            discoverable,
+            cache_max_age_seconds,
+            single_use,
+            max_hits,
+            not_before_seconds,
+            owner,
+        }: short_url::Model,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            short_id: ShortId::from_db(id),
+            url: Url::parse(&long_url).context("Failed to parse Url from db model")?,
+            expiration_time: ExpirationTime::from_db(*expiration_time_seconds),
+            original_url,
+            utm_template,
+            discoverable,
+            cache_max_age_seconds: cache_max_age_seconds
+                .map(TryInto::try_into)
+                .transpose()
+                .context("Failed to convert cache max age from db model")?,
+            single_use: single_use.unwrap_or(false),
+            max_hits: max_hits
+                .map(TryInto::try_into)
+                .transpose()
+                .context("Failed to convert max hits from db model")?,
+            not_before: not_before_seconds.map(|not_before| *not_before),
+            owner,
+        })
+    }
+}
+
+impl TryFrom<short_url::Model> for ShortUrlStats {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        short_url::Model {
+            long_url,
+            expiration_time_seconds,
+            hit_count,
+            cache_max_age_seconds,
+            max_hits,
+            ..
         }: short_url::Model,
     ) -> Result<Self, Self::Error> {
         Ok(Self {
-            short_id: ShortId::new(id).context("Failed to create ShortId from db model")?,
             url: Url::parse(&long_url).context("Failed to parse Url from db model")?,
-            expiration_time: ExpirationTime::new(*expiration_time_seconds)
-                .context("Failed to create ExpirationTime from db model")?,
+            expiration_time: *expiration_time_seconds,
+            hit_count: hit_count
+                .try_into()
+                .context("Failed to convert hit count from db model")?,
+            cache_max_age_seconds: cache_max_age_seconds
+                .map(TryInto::try_into)
+                .transpose()
+                .context("Failed to convert cache max age from db model")?,
+            max_hits: max_hits
+                .map(TryInto::try_into)
+                .transpose()
+                .context("Failed to convert max hits from db model")?,
         })
     }
 }
 
+// NOTE: only the required methods are mocked, so defaulted ones keep their behavior
+#[cfg(test)]
+mockall::mock! {
+    pub(crate) UrlRepository {}
+
+    #[async_trait]
+    impl UrlRepository for UrlRepository {
+        async fn retrieve_url(&self, id: &str) -> anyhow::Result<RetrievedUrl>;
+        async fn retrieve_and_consume(&self, id: &str) -> anyhow::Result<RetrievedUrl>;
+        async fn retrieve_url_stats(
+            &self,
+            id: &str,
+        ) -> anyhow::Result<Option<ShortUrlStats>>;
+        async fn save_url(&self, url: ShortUrl) -> Result<ShortUrl, SaveUrlError>;
+        async fn replace_url(&self, url: ShortUrl) -> anyhow::Result<bool>;
+        async fn find_recently_created(
+            &self,
+            long_url: &str,
+            since: OffsetDateTime,
+        ) -> anyhow::Result<Option<ShortUrl>>;
+        async fn find_owner_hosts(&self, owner: &str) -> anyhow::Result<HashSet<String>>;
+        async fn find_discoverable(&self, limit: u64) -> anyhow::Result<Vec<ShortUrl>>;
+        async fn list_urls(
+            &self,
+            limit: u64,
+            after: Option<String>,
+        ) -> anyhow::Result<Vec<ShortUrl>>;
+        async fn delete_url(&self, id: &str) -> anyhow::Result<bool>;
+        async fn expire_url(&self, id: &str) -> anyhow::Result<bool>;
+        async fn increment_hits(&self, id: &str) -> anyhow::Result<()>;
+        async fn increment_hits_within_limit(&self, id: &str) -> anyhow::Result<bool>;
+        async fn delete_expired_urls(
+            &self,
+            batch_size: Option<NonZeroU64>,
+        ) -> anyhow::Result<u64>;
+        async fn count_expired_urls(&self) -> anyhow::Result<u64>;
+        async fn ping(&self) -> anyhow::Result<()>;
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -230,41 +936,69 @@ mod tests {
     mod short_id {
         use super::*;
 
+        const BOUNDS: ShortIdLengthBounds = ShortIdLengthBounds {
+            min_len: 4,
+            max_len: 8,
+        };
+
         #[test]
         fn test_new_valid() {
             let valid_id = "valid123";
-            let short_id = ShortId::new(valid_id.to_string()).unwrap();
+            let short_id = ShortId::new(valid_id.to_string(), BOUNDS).unwrap();
             assert_eq!(short_id.inner, valid_id);
         }
 
+        #[test]
+        fn test_new_at_min_len() {
+            let short_id = ShortId::new("abcd".to_string(), BOUNDS).unwrap();
+            assert_eq!(short_id.inner, "abcd");
+        }
+
         #[test]
         fn test_new_too_short() {
-            let short_id = "short";
-            let err = ShortId::new(short_id.to_string()).unwrap_err();
+            let short_id = "abc";
+            let err = ShortId::new(short_id.to_string(), BOUNDS).unwrap_err();
             assert!(matches!(err, ShortIdValidationError::InvalidLength { .. }));
         }
 
         #[test]
         fn test_new_too_long() {
-            let long_id = "thisidiswaytoolongtobevalid";
-            let err = ShortId::new(long_id.to_string()).unwrap_err();
+            let long_id = "valid1234";
+            let err = ShortId::new(long_id.to_string(), BOUNDS).unwrap_err();
             assert!(matches!(err, ShortIdValidationError::InvalidLength { .. }));
         }
 
+        #[test]
+        fn test_new_default_bounds() {
+            let bounds = ShortIdLengthBounds::default();
+            assert!(ShortId::new("short".to_string(), bounds).is_err());
+            assert!(ShortId::new("valid123".to_string(), bounds).is_ok());
+            assert!(ShortId::new("thisidiswaytoolongtobevalid".to_string(), bounds).is_err());
+        }
+
         #[test]
         fn test_new_invalid_chars() {
-            let invalid_id = "invalid-id!";
-            let err = ShortId::new(invalid_id.to_string()).unwrap_err();
+            let invalid_id = "inv-id!";
+            let err = ShortId::new(invalid_id.to_string(), BOUNDS).unwrap_err();
             assert!(matches!(
                 err,
                 ShortIdValidationError::InvalidCharacters { invalid_chars } if invalid_chars == "-!"
             ));
         }
 
+        #[test]
+        fn test_invalid_length_serializes_limits() {
+            let err = ShortId::new("abc".to_string(), BOUNDS).unwrap_err();
+            assert_eq!(
+                serde_json::to_value(err).unwrap(),
+                serde_json::json!({ "min_len": 4, "max_len": 8 }),
+            );
+        }
+
         #[test]
         fn test_into_inner() {
             let valid_id = "valid123";
-            let short_id = ShortId::new(valid_id.to_string()).unwrap();
+            let short_id = ShortId::new(valid_id.to_string(), BOUNDS).unwrap();
             assert_eq!(short_id.into_inner(), valid_id);
         }
     }
@@ -275,31 +1009,55 @@ mod tests {
         #[test]
         fn test_new_valid() {
             let future_time = OffsetDateTime::now_utc() + Duration::days(1);
-            let expiration_time = ExpirationTime::new(future_time).unwrap();
+            let expiration_time =
+                ExpirationTime::new(future_time, ExpirationTime::DEFAULT_MAX_TTL).unwrap();
             assert_eq!(expiration_time.inner, future_time);
         }
 
         #[test]
         fn test_new_in_past() {
             let past_time = OffsetDateTime::now_utc() - Duration::days(1);
-            let err = ExpirationTime::new(past_time).unwrap_err();
+            let err = ExpirationTime::new(past_time, ExpirationTime::DEFAULT_MAX_TTL).unwrap_err();
             assert!(matches!(err, ExpirationTimeValidationError::InPast));
         }
 
         #[test]
         fn test_new_too_far_in_future() {
             let far_future_time = OffsetDateTime::now_utc() + Duration::days(11 * 365);
-            let err = ExpirationTime::new(far_future_time).unwrap_err();
+            let err =
+                ExpirationTime::new(far_future_time, ExpirationTime::DEFAULT_MAX_TTL).unwrap_err();
             assert!(matches!(
                 err,
                 ExpirationTimeValidationError::TooFarInFuture { .. }
             ));
         }
 
+        #[test]
+        fn test_new_just_under_configured_max_ttl() {
+            let max_ttl = Duration::hours(24);
+            let future_time = OffsetDateTime::now_utc() + max_ttl - Duration::minutes(1);
+            let expiration_time = ExpirationTime::new(future_time, max_ttl).unwrap();
+            assert_eq!(expiration_time.inner, future_time);
+        }
+
+        #[test]
+        fn test_new_just_over_configured_max_ttl() {
+            let max_ttl = Duration::hours(24);
+            let future_time = OffsetDateTime::now_utc() + max_ttl + Duration::minutes(1);
+            let err = ExpirationTime::new(future_time, max_ttl).unwrap_err();
+            assert!(matches!(
+                err,
+                ExpirationTimeValidationError::TooFarInFuture { max_ttl, .. }
+                    if max_ttl == Duration::hours(24)
+            ));
+            assert!(err.to_string().contains("the maximum TTL is 1d"), "{err}");
+        }
+
         #[test]
         fn test_into_inner() {
             let future_time = OffsetDateTime::now_utc() + Duration::days(1);
-            let expiration_time = ExpirationTime::new(future_time).unwrap();
+            let expiration_time =
+                ExpirationTime::new(future_time, ExpirationTime::DEFAULT_MAX_TTL).unwrap();
             assert_eq!(expiration_time.into_inner(), future_time);
         }
     }
@@ -312,6 +1070,16 @@ mod tests {
             id: id.to_owned(),
             long_url: url.to_owned(),
             expiration_time_seconds: expiration_time.into(),
+            original_url: None,
+            created_at_seconds: Some(OffsetDateTime::now_utc().into()),
+            hit_count: 0,
+            utm_template: None,
+            discoverable: false,
+            cache_max_age_seconds: None,
+            single_use: None,
+            max_hits: None,
+            not_before_seconds: None,
+            owner: None,
         }
     }
 
@@ -323,7 +1091,7 @@ mod tests {
         let repo = UrlRepositoryImpl { db };
 
         let result = repo.retrieve_url("nonexistent").await.unwrap();
-        assert!(result.is_none());
+        assert_eq!(result, RetrievedUrl::NotFound);
     }
 
     #[tokio::test]
@@ -336,7 +1104,24 @@ mod tests {
         let repo = UrlRepositoryImpl { db };
 
         let result = repo.retrieve_url("expired").await.unwrap();
-        assert!(result.is_none());
+        assert_eq!(result, RetrievedUrl::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_url_hits_exhausted() {
+        let model = short_url::Model {
+            hit_count: 2,
+            max_hits: Some(2),
+            ..new_model("limited", "https://example.com", Duration::days(1))
+        };
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([[model]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.retrieve_url("limited").await.unwrap();
+        assert_eq!(result, RetrievedUrl::Expired);
     }
 
     #[tokio::test]
@@ -350,15 +1135,32 @@ mod tests {
         let repo = UrlRepositoryImpl { db };
 
         let result = repo.retrieve_url("nonexpired").await.unwrap();
-        assert_eq!(result, Some(expected));
+        assert_eq!(result, RetrievedUrl::Found(Box::new(expected)));
     }
 
     #[tokio::test]
-    async fn test_save_url_newly_created() {
-        let model = new_model("valid123", "https://example.com", Duration::days(1));
+    async fn test_retrieve_url_not_yet_active() {
+        let model = short_url::Model {
+            not_before_seconds: Some((OffsetDateTime::now_utc() + Duration::hours(1)).into()),
+            ..new_model("pending1", "https://example.com", Duration::days(1))
+        };
 
         let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
-            .append_query_results([vec![], vec![model.clone()]])
+            .append_query_results([[model]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.retrieve_url("pending1").await.unwrap();
+        assert_eq!(result, RetrievedUrl::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_and_consume_deletes_in_transaction() {
+        let model = new_model("consume1", "https://example.com", Duration::days(1));
+        let expected: ShortUrl = model.clone().try_into().unwrap();
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([[model]])
             .append_exec_results([MockExecResult {
                 last_insert_id: 0,
                 rows_affected: 1,
@@ -366,17 +1168,197 @@ mod tests {
             .into_connection();
         let repo = UrlRepositoryImpl { db };
 
-        let short_url: ShortUrl = model.try_into().unwrap();
-        let actual = repo.save_url(short_url.clone()).await.unwrap();
-        assert_eq!(actual, short_url);
+        let result = repo.retrieve_and_consume("consume1").await.unwrap();
+        assert_eq!(result, RetrievedUrl::Found(Box::new(expected)));
+
+        let transaction_log = repo.db.into_transaction_log();
+        let [transaction] = transaction_log.as_slice() else {
+            panic!("expected a single transaction, got {transaction_log:?}");
+        };
+        let statements = transaction
+            .statements()
+            .iter()
+            .map(|statement| statement.sql.as_str())
+            .collect::<Vec<_>>();
+        assert!(statements.iter().any(|sql| sql.ends_with("FOR UPDATE")));
+        assert!(statements.iter().any(|sql| sql.starts_with("DELETE")));
     }
 
     #[tokio::test]
-    async fn test_save_url_conflict_nonexpired() {
-        let model = new_model("valid123", "https://example.com", Duration::days(1));
+    async fn test_retrieve_and_consume_expired_is_kept() {
+        let model = new_model("expired", "https://example.com", Duration::seconds(-1));
 
         let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
-            .append_query_results([[model.clone()]])
+            .append_query_results([[model]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.retrieve_and_consume("expired").await.unwrap();
+        assert_eq!(result, RetrievedUrl::Expired);
+        assert!(
+            repo.db
+                .into_transaction_log()
+                .iter()
+                .flat_map(sea_orm::Transaction::statements)
+                .all(|statement| !statement.sql.starts_with("DELETE"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_url_stats_expired() {
+        let model = short_url::Model {
+            hit_count: 42,
+            ..new_model("expired", "https://example.com", Duration::seconds(-1))
+        };
+        let expected_expiration_time = *model.expiration_time_seconds;
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([[model]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let stats = repo.retrieve_url_stats("expired").await.unwrap().unwrap();
+        assert_eq!(stats.url.as_str(), "https://example.com/");
+        assert_eq!(stats.expiration_time, expected_expiration_time);
+        assert_eq!(stats.hit_count, 42);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_url_stats_non_existent() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results::<short_url::Model, _, _>([[]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.retrieve_url_stats("nonexistent").await.unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_save_url_newly_created() {
+        let model = new_model("valid123", "https://example.com", Duration::days(1));
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([vec![], vec![model.clone()]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let short_url: ShortUrl = model.try_into().unwrap();
+        let actual = repo.save_url(short_url.clone()).await.unwrap();
+        assert_eq!(actual, short_url);
+    }
+
+    fn serialization_failure() -> sea_orm::DbErr {
+        sea_orm::DbErr::Query(sea_orm::RuntimeErr::Internal(
+            "could not serialize access due to concurrent update".to_owned(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_save_url_retries_serialization_failure() {
+        let model = new_model("valid123", "https://example.com", Duration::days(1));
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_errors([serialization_failure()])
+            .append_query_results([vec![], vec![model.clone()]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let short_url: ShortUrl = model.try_into().unwrap();
+        let actual = repo.save_url(short_url.clone()).await.unwrap();
+        assert_eq!(actual, short_url);
+    }
+
+    #[tokio::test]
+    async fn test_save_url_serialization_failure_retries_exhausted() {
+        let model = new_model("valid123", "https://example.com", Duration::days(1));
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_errors([
+                serialization_failure(),
+                serialization_failure(),
+                serialization_failure(),
+            ])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.save_url(model.try_into().unwrap()).await;
+        assert!(matches!(result, Err(SaveUrlError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_save_url_other_errors_not_retried() {
+        let model = new_model("valid123", "https://example.com", Duration::days(1));
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_errors([sea_orm::DbErr::Custom("test error".to_owned())])
+            .append_query_results([vec![], vec![model.clone()]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.save_url(model.try_into().unwrap()).await;
+        assert!(matches!(result, Err(SaveUrlError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_save_url_preserves_original_url() {
+        let model = short_url::Model {
+            original_url: Some("https://EXAMPLE.com:443".to_owned()),
+            ..new_model("valid123", "https://example.com/", Duration::days(1))
+        };
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([vec![], vec![model.clone()]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let short_url: ShortUrl = model.try_into().unwrap();
+        repo.save_url(short_url).await.unwrap();
+
+        let transaction_log = repo.db.into_transaction_log();
+        let insert = transaction_log
+            .iter()
+            .flat_map(sea_orm::Transaction::statements)
+            .find(|statement| statement.sql.starts_with("INSERT"))
+            .unwrap();
+        assert!(insert.sql.contains(r#""original_url""#));
+        assert!(
+            insert
+                .values
+                .as_ref()
+                .unwrap()
+                .0
+                .contains(&"https://EXAMPLE.com:443".into())
+        );
+    }
+
+    #[test]
+    fn test_is_equivalent_to_ignores_original_url() {
+        let short_url: ShortUrl = new_model("valid123", "https://example.com/", Duration::days(1))
+            .try_into()
+            .unwrap();
+        let with_original_url = ShortUrl {
+            original_url: Some("https://example.com".to_owned()),
+            ..short_url.clone()
+        };
+        assert!(short_url.is_equivalent_to(&with_original_url));
+        assert_ne!(short_url, with_original_url);
+    }
+
+    #[tokio::test]
+    async fn test_save_url_conflict_nonexpired() {
+        let model = new_model("valid123", "https://example.com", Duration::days(1));
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([[model.clone()]])
             .into_connection();
         let repo = UrlRepositoryImpl { db };
 
@@ -413,6 +1395,184 @@ mod tests {
         assert_eq!(actual, short_url);
     }
 
+    #[tokio::test]
+    async fn test_find_recently_created_found() {
+        let model = new_model("valid123", "https://example.com/", Duration::days(1));
+        let expected: ShortUrl = model.clone().try_into().unwrap();
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([[model]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let since = OffsetDateTime::now_utc() - Duration::minutes(1);
+        let result = repo
+            .find_recently_created("https://example.com/", since)
+            .await
+            .unwrap();
+        assert_eq!(result, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_find_recently_created_not_found() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results::<short_url::Model, _, _>([[]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let since = OffsetDateTime::now_utc() - Duration::minutes(1);
+        let result = repo
+            .find_recently_created("https://example.com/", since)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_find_discoverable() {
+        let model = short_url::Model {
+            discoverable: true,
+            ..new_model("valid123", "https://example.com/", Duration::days(1))
+        };
+        let expected: ShortUrl = model.clone().try_into().unwrap();
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([[model]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.find_discoverable(10).await.unwrap();
+        assert_eq!(result, vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn test_list_urls() {
+        let models = [
+            new_model("valid123", "https://example.com/", Duration::days(1)),
+            new_model("valid456", "https://example.com/", Duration::days(1)),
+        ];
+        let expected = models
+            .iter()
+            .cloned()
+            .map(|model| model.try_into().unwrap())
+            .collect::<Vec<ShortUrl>>();
+
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([models])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo
+            .list_urls(2, Some("valid000".to_owned()))
+            .await
+            .unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[tokio::test]
+    async fn test_delete_url_existing() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let deleted = repo.delete_url("valid123").await.unwrap();
+        assert!(deleted);
+    }
+
+    #[tokio::test]
+    async fn test_delete_url_non_existent() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 0,
+            }])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let deleted = repo.delete_url("valid123").await.unwrap();
+        assert!(!deleted);
+    }
+
+    #[tokio::test]
+    async fn test_expire_url() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_exec_results([1, 0].map(|rows_affected| MockExecResult {
+                last_insert_id: 0,
+                rows_affected,
+            }))
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        assert!(repo.expire_url("abc123").await.unwrap());
+        assert!(!repo.expire_url("missing1").await.unwrap());
+
+        let transaction_log = repo.db.into_transaction_log();
+        assert_eq!(
+            transaction_log[0].statements()[0].sql,
+            r#"UPDATE "urls" SET "expiration_time_seconds" = $1 WHERE "urls"."id" = $2"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_increment_hits() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        repo.increment_hits("abc123").await.unwrap();
+
+        assert_eq!(
+            repo.db.into_transaction_log(),
+            [sea_orm::Transaction::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"UPDATE "urls" SET "hit_count" = "hit_count" + $1 WHERE "urls"."id" = $2"#,
+                [1.into(), "abc123".into()],
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_increment_hits_within_limit() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_exec_results([1, 0].map(|rows_affected| MockExecResult {
+                last_insert_id: 0,
+                rows_affected,
+            }))
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        assert!(repo.increment_hits_within_limit("abc123").await.unwrap());
+        assert!(!repo.increment_hits_within_limit("abc123").await.unwrap());
+
+        let transaction_log = repo.db.into_transaction_log();
+        assert_eq!(
+            transaction_log[0],
+            sea_orm::Transaction::from_sql_and_values(
+                sea_orm::DatabaseBackend::Postgres,
+                r#"UPDATE "urls" SET "hit_count" = "hit_count" + $1 WHERE "urls"."id" = $2 AND ("urls"."max_hits" IS NULL OR "hit_count" < "max_hits")"#,
+                [1.into(), "abc123".into()],
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_increment_hits_error() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_exec_errors([sea_orm::DbErr::Custom("test error".to_owned())])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        assert!(repo.increment_hits("abc123").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_delete_expired_urls_success() {
         let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
@@ -423,8 +1583,33 @@ mod tests {
             .into_connection();
         let repo = UrlRepositoryImpl { db };
 
-        let result = repo.delete_expired_urls().await;
-        assert!(result.is_ok());
+        let result = repo.delete_expired_urls(None).await.unwrap();
+        assert_eq!(result, 42);
+        let transaction_log = repo.db.into_transaction_log();
+        assert_eq!(
+            transaction_log[0].statements()[0].sql,
+            r#"DELETE FROM "urls" WHERE "urls"."expiration_time_seconds" < $1"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired_urls_in_batches() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_exec_results([2, 2, 1].map(|rows_affected| MockExecResult {
+                last_insert_id: 0,
+                rows_affected,
+            }))
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.delete_expired_urls(NonZeroU64::new(2)).await.unwrap();
+        assert_eq!(result, 5);
+        let transaction_log = repo.db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 3);
+        assert_eq!(
+            transaction_log[0].statements()[0].sql,
+            r#"DELETE FROM "urls" WHERE "urls"."id" IN (SELECT "urls"."id" FROM "urls" WHERE "urls"."expiration_time_seconds" < $1 LIMIT $2)"#
+        );
     }
 
     #[tokio::test]
@@ -434,16 +1619,404 @@ mod tests {
             .into_connection();
         let repo = UrlRepositoryImpl { db };
 
-        let result = repo.delete_expired_urls().await;
+        let result = repo.delete_expired_urls(None).await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_count_expired_urls() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres)
+            .append_query_results([[std::collections::BTreeMap::from([(
+                "num_items",
+                sea_orm::Value::BigInt(Some(42)),
+            )])]])
+            .into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        let result = repo.count_expired_urls().await.unwrap();
+        assert_eq!(result, 42);
+        let transaction_log = repo.db.into_transaction_log();
+        assert_eq!(
+            transaction_log[0].statements()[0].sql,
+            r#"SELECT COUNT(*) AS num_items FROM (SELECT "urls"."id" FROM "urls" WHERE "urls"."expiration_time_seconds" < $1) AS "sub_query""#
+        );
+    }
+
+    mod timezone_independence {
+        use sea_orm::{DatabaseBackend, Value};
+        use time::UtcOffset;
+
+        use super::*;
+
+        const BACKENDS: [DatabaseBackend; 2] = [DatabaseBackend::Postgres, DatabaseBackend::Sqlite];
+
+        /// Expiration times read back from the database are compared as instants,
+        /// so a model carrying a far-from-UTC offset must not shift expiration.
+        fn with_offset(mut model: short_url::Model, offset_hours: i8) -> short_url::Model {
+            let offset = UtcOffset::from_hms(offset_hours, 0, 0).unwrap();
+            model.expiration_time_seconds = model.expiration_time_seconds.to_offset(offset).into();
+            model
+        }
+
+        fn bound_unix_seconds(db: DbConn) -> Vec<i64> {
+            db.into_transaction_log()
+                .iter()
+                .flat_map(sea_orm::Transaction::statements)
+                .flat_map(|stmt| stmt.values.iter().flat_map(|values| values.0.clone()))
+                .filter_map(|value| match value {
+                    Value::BigInt(Some(seconds)) => Some(seconds),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        #[tokio::test]
+        async fn test_retrieve_url_expired_with_offset() {
+            for backend in BACKENDS {
+                for offset_hours in [-12, 0, 14] {
+                    let model = with_offset(
+                        new_model("expired", "https://example.com", Duration::minutes(-1)),
+                        offset_hours,
+                    );
+                    let db = MockDatabase::new(backend)
+                        .append_query_results([[model]])
+                        .into_connection();
+                    let repo = UrlRepositoryImpl { db };
+
+                    let result = repo.retrieve_url("expired").await.unwrap();
+                    assert_eq!(
+                        result,
+                        RetrievedUrl::Expired,
+                        "{backend:?} at UTC{offset_hours:+}"
+                    );
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn test_retrieve_url_nonexpired_with_offset() {
+            for backend in BACKENDS {
+                for offset_hours in [-12, 0, 14] {
+                    let model = with_offset(
+                        new_model("nonexpired", "https://example.com", Duration::minutes(1)),
+                        offset_hours,
+                    );
+                    let db = MockDatabase::new(backend)
+                        .append_query_results([[model]])
+                        .into_connection();
+                    let repo = UrlRepositoryImpl { db };
+
+                    let result = repo.retrieve_url("nonexpired").await.unwrap();
+                    assert!(
+                        matches!(result, RetrievedUrl::Found(_)),
+                        "{backend:?} at UTC{offset_hours:+}"
+                    );
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn test_delete_expired_urls_binds_utc_unix_seconds() {
+            for backend in BACKENDS {
+                let db = MockDatabase::new(backend)
+                    .append_exec_results([MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 0,
+                    }])
+                    .into_connection();
+                let repo = UrlRepositoryImpl { db };
+
+                let before = OffsetDateTime::now_utc().unix_timestamp();
+                repo.delete_expired_urls(None).await.unwrap();
+                let after = OffsetDateTime::now_utc().unix_timestamp();
+
+                let bound = bound_unix_seconds(repo.db);
+                assert_eq!(bound.len(), 1, "{backend:?}");
+                assert!((before..=after).contains(&bound[0]), "{backend:?}");
+            }
+        }
+
+        #[tokio::test]
+        async fn test_save_url_binds_utc_unix_seconds() {
+            for backend in BACKENDS {
+                let expiration_time = (OffsetDateTime::now_utc() + Duration::days(1))
+                    .replace_nanosecond(0)
+                    .unwrap();
+                let model = new_model("abcd1234", "https://example.com", Duration::days(1));
+                let db = MockDatabase::new(backend)
+                    .append_query_results([vec![], vec![model]])
+                    .append_exec_results([MockExecResult {
+                        last_insert_id: 0,
+                        rows_affected: 1,
+                    }])
+                    .into_connection();
+                let repo = UrlRepositoryImpl { db };
+
+                let to_save = ShortUrl {
+                    short_id: ShortId::new("abcd1234".to_owned(), ShortIdLengthBounds::default())
+                        .unwrap(),
+                    url: Url::parse("https://example.com").unwrap(),
+                    expiration_time: ExpirationTime::new(
+                        expiration_time.to_offset(UtcOffset::from_hms(-12, 0, 0).unwrap()),
+                        ExpirationTime::DEFAULT_MAX_TTL,
+                    )
+                    .unwrap(),
+                    original_url: None,
+                    utm_template: None,
+                    discoverable: false,
+                    cache_max_age_seconds: None,
+                    single_use: false,
+                    max_hits: None,
+                    not_before: None,
+                    owner: None,
+                };
+                repo.save_url(to_save).await.unwrap();
+
+                let bound = bound_unix_seconds(repo.db);
+                assert!(
+                    bound.contains(&expiration_time.unix_timestamp()),
+                    "{backend:?}: {bound:?}"
+                );
+            }
+        }
+    }
+
+    mod sqlite {
+        use sea_orm::{ConnectionTrait, Database, DbBackend, Statement};
+
+        use super::*;
+
+        async fn new_repo() -> UrlRepositoryImpl {
+            let db = Database::connect("sqlite::memory:").await.unwrap();
+            crate::migration::up(&db).await.unwrap();
+            UrlRepositoryImpl { db }
+        }
+
+        fn new_short_url(id: &str, expires_in: Duration) -> ShortUrl {
+            new_model(id, "https://example.com/", expires_in)
+                .try_into()
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn test_save_retrieve_and_delete_expired() {
+            let repo = new_repo().await;
+            let active = new_short_url("active12", Duration::days(1));
+            let expired = new_short_url("expired1", Duration::seconds(-1));
+
+            assert_eq!(repo.save_url(active.clone()).await.unwrap(), active);
+            repo.save_url(expired).await.unwrap();
+            assert!(matches!(
+                repo.save_url(new_short_url("active12", Duration::days(2))).await,
+                Err(SaveUrlError::ItemAlreadyExists(existing)) if *existing == active
+            ));
+            assert_eq!(
+                repo.retrieve_url("active12").await.unwrap(),
+                RetrievedUrl::Found(Box::new(active.clone()))
+            );
+            assert_eq!(
+                repo.retrieve_url("expired1").await.unwrap(),
+                RetrievedUrl::Expired
+            );
+
+            assert_eq!(repo.count_expired_urls().await.unwrap(), 1);
+            assert_eq!(repo.delete_expired_urls(None).await.unwrap(), 1);
+            assert_eq!(
+                repo.retrieve_url("expired1").await.unwrap(),
+                RetrievedUrl::NotFound
+            );
+            assert_eq!(
+                repo.retrieve_url("active12").await.unwrap(),
+                RetrievedUrl::Found(Box::new(active))
+            );
+        }
+
+        #[tokio::test]
+        async fn test_expire_url_keeps_record() {
+            let repo = new_repo().await;
+            repo.save_url(new_short_url("active12", Duration::days(1)))
+                .await
+                .unwrap();
+
+            assert!(repo.expire_url("active12").await.unwrap());
+            assert_eq!(
+                repo.retrieve_url("active12").await.unwrap(),
+                RetrievedUrl::Expired
+            );
+            assert!(repo.retrieve_url_stats("active12").await.unwrap().is_some());
+            assert!(!repo.expire_url("missing1").await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn test_replace_url_revives_expired_item() {
+            let repo = new_repo().await;
+            repo.save_url(new_short_url("replace1", Duration::seconds(-1)))
+                .await
+                .unwrap();
+
+            let replacement = ShortUrl {
+                url: Url::parse("https://example.com/new").unwrap(),
+                ..new_short_url("replace1", Duration::days(1))
+            };
+            assert!(repo.replace_url(replacement.clone()).await.unwrap());
+            assert_eq!(
+                repo.retrieve_url("replace1").await.unwrap(),
+                RetrievedUrl::Found(Box::new(replacement))
+            );
+            assert!(
+                !repo
+                    .replace_url(new_short_url("missing1", Duration::days(1)))
+                    .await
+                    .unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn test_find_owner_hosts() {
+            let repo = new_repo().await;
+            for (id, url, owner, expires_in) in [
+                (
+                    "owned123",
+                    "https://a.example/1",
+                    Some("owner1"),
+                    Duration::days(1),
+                ),
+                (
+                    "owned456",
+                    "https://a.example/2",
+                    Some("owner1"),
+                    Duration::days(1),
+                ),
+                (
+                    "owned789",
+                    "https://b.example/",
+                    Some("owner1"),
+                    Duration::days(1),
+                ),
+                (
+                    "expired1",
+                    "https://c.example/",
+                    Some("owner1"),
+                    Duration::seconds(-1),
+                ),
+                (
+                    "other123",
+                    "https://d.example/",
+                    Some("owner2"),
+                    Duration::days(1),
+                ),
+                ("anon1234", "https://e.example/", None, Duration::days(1)),
+            ] {
+                repo.save_url(ShortUrl {
+                    url: Url::parse(url).unwrap(),
+                    owner: owner.map(str::to_owned),
+                    ..new_short_url(id, expires_in)
+                })
+                .await
+                .unwrap();
+            }
+
+            assert_eq!(
+                repo.find_owner_hosts("owner1").await.unwrap(),
+                HashSet::from(["a.example".to_owned(), "b.example".to_owned()])
+            );
+            assert!(repo.find_owner_hosts("owner3").await.unwrap().is_empty());
+        }
+
+        #[tokio::test]
+        async fn test_batched_delete_expired_urls() {
+            let repo = new_repo().await;
+            for id in ["expired1", "expired2", "expired3"] {
+                repo.save_url(new_short_url(id, Duration::seconds(-1)))
+                    .await
+                    .unwrap();
+            }
+
+            let deleted = repo.delete_expired_urls(NonZeroU64::new(2)).await.unwrap();
+            assert_eq!(deleted, 3);
+            assert_eq!(repo.count_expired_urls().await.unwrap(), 0);
+        }
+
+        #[tokio::test]
+        async fn test_expired_ids_query_uses_expiration_index() {
+            let repo = new_repo().await;
+            let query =
+                UrlRepositoryImpl::find_expired_ids(TimeUnixTimestamp(OffsetDateTime::now_utc()))
+                    .build(DbBackend::Sqlite);
+
+            let plan = repo
+                .db
+                .query_all_raw(Statement::from_sql_and_values(
+                    DbBackend::Sqlite,
+                    format!("EXPLAIN QUERY PLAN {}", query.sql),
+                    query.values.unwrap(),
+                ))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|row| row.try_get::<String>("", "detail").unwrap())
+                .collect::<Vec<_>>();
+            assert!(
+                plan.iter()
+                    .any(|detail| detail.contains("USING INDEX idx_urls_expiration_time_seconds")),
+                "{plan:?}"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_hits_and_consumption() {
+            let repo = new_repo().await;
+            let limited = ShortUrl {
+                max_hits: Some(1),
+                ..new_short_url("limited1", Duration::days(1))
+            };
+            let single_use = ShortUrl {
+                single_use: true,
+                ..new_short_url("single12", Duration::days(1))
+            };
+            repo.save_url(limited).await.unwrap();
+            repo.save_url(single_use.clone()).await.unwrap();
+
+            assert!(repo.increment_hits_within_limit("limited1").await.unwrap());
+            assert!(!repo.increment_hits_within_limit("limited1").await.unwrap());
+            let stats = repo.retrieve_url_stats("limited1").await.unwrap().unwrap();
+            assert_eq!(stats.hit_count, 1);
+
+            assert_eq!(
+                repo.retrieve_and_consume("single12").await.unwrap(),
+                RetrievedUrl::Found(Box::new(single_use))
+            );
+            assert_eq!(
+                repo.retrieve_and_consume("single12").await.unwrap(),
+                RetrievedUrl::NotFound
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ping() {
+        let db = MockDatabase::new(sea_orm::DatabaseBackend::Postgres).into_connection();
+        let repo = UrlRepositoryImpl { db };
+
+        assert!(repo.ping().await.is_ok());
+    }
+
     #[test]
     fn test_try_from_model_to_short_url() {
         let model = short_url::Model {
             id: "valid123".to_string(),
             long_url: "https://example.com".to_string(),
             expiration_time_seconds: (OffsetDateTime::now_utc() + Duration::days(1)).into(),
+            original_url: None,
+            created_at_seconds: None,
+            hit_count: 0,
+            utm_template: None,
+            discoverable: false,
+            cache_max_age_seconds: None,
+            single_use: None,
+            max_hits: None,
+            not_before_seconds: None,
+            owner: None,
         };
         let short_url: Result<ShortUrl, _> = model.try_into();
         assert!(short_url.is_ok());
@@ -455,8 +2028,48 @@ mod tests {
             id: "valid123".to_string(),
             long_url: "not a valid url".to_string(),
             expiration_time_seconds: (OffsetDateTime::now_utc() + Duration::days(1)).into(),
+            original_url: None,
+            created_at_seconds: None,
+            hit_count: 0,
+            utm_template: None,
+            discoverable: false,
+            cache_max_age_seconds: None,
+            single_use: None,
+            max_hits: None,
+            not_before_seconds: None,
+            owner: None,
         };
         let short_url: Result<ShortUrl, _> = model.try_into();
         assert!(short_url.is_err());
     }
+
+    #[test]
+    fn test_try_from_model_to_short_url_expired() {
+        let model = new_model("expired1", "https://example.com/", Duration::days(-1));
+        let expected_expiration_time = *model.expiration_time_seconds;
+
+        let short_url: ShortUrl = model.try_into().unwrap();
+        assert_eq!(
+            short_url.expiration_time.into_inner(),
+            expected_expiration_time
+        );
+    }
+
+    #[test]
+    fn test_try_from_model_to_short_url_beyond_max_ttl() {
+        let model = new_model(
+            "farfuture",
+            "https://example.com/",
+            Duration::days(20 * 365),
+        );
+        let short_url: Result<ShortUrl, _> = model.try_into();
+        assert!(short_url.is_ok());
+    }
+
+    #[test]
+    fn test_try_from_model_to_short_url_outside_length_bounds() {
+        let model = new_model("abc", "https://example.com/", Duration::days(1));
+        let short_url: ShortUrl = model.try_into().unwrap();
+        assert_eq!(short_url.short_id.into_inner(), "abc");
+    }
 }
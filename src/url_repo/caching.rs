@@ -0,0 +1,400 @@
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU64,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use tracing::{debug, instrument};
+
+use super::{CacheStatus, RetrievedUrl, SaveUrlError, ShortUrl, ShortUrlStats, UrlRepository};
+
+/// Wraps a [`UrlRepository`] to keep recently followed short URLs in memory,
+/// so that redirects to hot links skip the database.
+/// Short IDs that weren't found can be remembered too (for `negative_ttl`),
+/// so that scanners guessing random IDs skip the database as well.
+///
+/// NOTE: writes through this process invalidate their cached items right away
+/// (once written, so that a concurrent read can't re-cache the old item), but writes from other replicas are only seen once the cached items' TTL passes.
+pub(crate) struct CachingUrlRepository {
+    inner: Arc<dyn UrlRepository>,
+    negative_ttl: Duration,
+    entries: Mutex<HashMap<String, CachedUrl>>,
+}
+
+struct CachedUrl {
+    /// [`None`] when the short ID wasn't found (a tombstone)
+    short_url: Option<ShortUrl>,
+    cached_until: Instant,
+}
+impl CachedUrl {
+    /// Whether this can still be served, which is never once the short URL itself expires.
+    fn is_fresh(&self, now: Instant) -> bool {
+        now < self.cached_until
+            && self.short_url.as_ref().is_none_or(|short_url| {
+                OffsetDateTime::now_utc() <= short_url.expiration_time.inner
+            })
+    }
+}
+
+impl CachingUrlRepository {
+    /// How long an item may be served from the cache, at most (it may expire sooner).
+    const TTL: Duration = Duration::from_mins(1);
+    /// Past this many cached items, the ones that went stale are forgotten.
+    const MAX_ENTRIES: usize = 10_000;
+
+    /// Creates a cache in front of `inner`, which only caches not found short IDs
+    /// when `negative_ttl` is non-zero.
+    pub(crate) fn new(inner: Arc<dyn UrlRepository>, negative_ttl: Duration) -> Self {
+        Self {
+            inner,
+            negative_ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, CachedUrl>> {
+        // NOTE: a poisoned lock only means another request panicked mid-update,
+        // and every entry is still checked for freshness before being served
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get(&self, id: &str, now: Instant) -> Option<RetrievedUrl> {
+        let mut entries = self.entries();
+        match entries.get(id) {
+            Some(cached) if cached.is_fresh(now) => Some(
+                cached
+                    .short_url
+                    .as_ref()
+                    .map_or(RetrievedUrl::NotFound, |short_url| {
+                        RetrievedUrl::Found(Box::new(short_url.clone()))
+                    }),
+            ),
+            Some(_) => {
+                entries.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, id: &str, short_url: Option<ShortUrl>, now: Instant) {
+        let ttl = if short_url.is_some() {
+            Self::TTL
+        } else {
+            self.negative_ttl
+        };
+        if ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries();
+        if entries.len() >= Self::MAX_ENTRIES {
+            entries.retain(|_, cached| cached.is_fresh(now));
+        }
+        if entries.len() < Self::MAX_ENTRIES {
+            entries.insert(
+                id.to_owned(),
+                CachedUrl {
+                    short_url,
+                    cached_until: now + ttl,
+                },
+            );
+        }
+    }
+
+    fn invalidate(&self, id: &str) {
+        self.entries().remove(id);
+    }
+}
+
+#[async_trait]
+impl UrlRepository for CachingUrlRepository {
+    async fn retrieve_url(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        let (retrieved_url, _) = self.retrieve_url_cached(id).await?;
+        Ok(retrieved_url)
+    }
+
+    #[instrument(skip(self))]
+    async fn retrieve_url_cached(
+        &self,
+        id: &str,
+    ) -> anyhow::Result<(RetrievedUrl, Option<CacheStatus>)> {
+        if let Some(retrieved_url) = self.get(id, Instant::now()) {
+            debug!("Serving short URL from cache");
+            return Ok((retrieved_url, Some(CacheStatus::Hit)));
+        }
+        let retrieved_url = self.inner.retrieve_url(id).await?;
+        match &retrieved_url {
+            // NOTE: a limited URL can run out of hits at any moment,
+            // so it's always read from the inner repository
+            RetrievedUrl::Found(short_url) if short_url.max_hits.is_some() => {}
+            RetrievedUrl::Found(short_url) => {
+                self.insert(id, Some(short_url.as_ref().clone()), Instant::now());
+            }
+            // NOTE: this also briefly delays short URLs that are just about to become active
+            RetrievedUrl::NotFound => self.insert(id, None, Instant::now()),
+            RetrievedUrl::Expired => {}
+        }
+        Ok((retrieved_url, Some(CacheStatus::Miss)))
+    }
+
+    async fn retrieve_and_consume(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        let result = self.inner.retrieve_and_consume(id).await;
+        self.invalidate(id);
+        result
+    }
+
+    async fn retrieve_url_stats(&self, id: &str) -> anyhow::Result<Option<ShortUrlStats>> {
+        self.inner.retrieve_url_stats(id).await
+    }
+
+    async fn save_url(&self, short_url: ShortUrl) -> Result<ShortUrl, SaveUrlError> {
+        let id = short_url.short_id.inner.clone();
+        let result = self.inner.save_url(short_url).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn replace_url(&self, short_url: ShortUrl) -> anyhow::Result<bool> {
+        let id = short_url.short_id.inner.clone();
+        let result = self.inner.replace_url(short_url).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn find_recently_created(
+        &self,
+        long_url: &str,
+        since: OffsetDateTime,
+    ) -> anyhow::Result<Option<ShortUrl>> {
+        self.inner.find_recently_created(long_url, since).await
+    }
+
+    async fn find_owner_hosts(&self, owner: &str) -> anyhow::Result<HashSet<String>> {
+        self.inner.find_owner_hosts(owner).await
+    }
+
+    async fn find_discoverable(&self, limit: u64) -> anyhow::Result<Vec<ShortUrl>> {
+        self.inner.find_discoverable(limit).await
+    }
+
+    async fn list_urls(&self, limit: u64, after: Option<String>) -> anyhow::Result<Vec<ShortUrl>> {
+        self.inner.list_urls(limit, after).await
+    }
+
+    async fn delete_url(&self, id: &str) -> anyhow::Result<bool> {
+        let result = self.inner.delete_url(id).await;
+        self.invalidate(id);
+        result
+    }
+
+    async fn expire_url(&self, id: &str) -> anyhow::Result<bool> {
+        let result = self.inner.expire_url(id).await;
+        self.invalidate(id);
+        result
+    }
+
+    async fn increment_hits(&self, id: &str) -> anyhow::Result<()> {
+        self.inner.increment_hits(id).await
+    }
+
+    async fn increment_hits_within_limit(&self, id: &str) -> anyhow::Result<bool> {
+        self.inner.increment_hits_within_limit(id).await
+    }
+
+    /// NOTE: cached items are never served once expired, so there is nothing to invalidate.
+    async fn delete_expired_urls(&self, batch_size: Option<NonZeroU64>) -> anyhow::Result<u64> {
+        self.inner.delete_expired_urls(batch_size).await
+    }
+
+    async fn count_expired_urls(&self) -> anyhow::Result<u64> {
+        self.inner.count_expired_urls().await
+    }
+
+    async fn ping(&self) -> anyhow::Result<()> {
+        self.inner.ping().await
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::url_repo::in_memory::InMemoryUrlRepository;
+
+    fn new_repo_with_negative_ttl(
+        negative_ttl: Duration,
+    ) -> (Arc<dyn UrlRepository>, CachingUrlRepository) {
+        let inner: Arc<dyn UrlRepository> = Arc::new(InMemoryUrlRepository::default());
+        let repo = CachingUrlRepository::new(Arc::clone(&inner), negative_ttl);
+        (inner, repo)
+    }
+
+    fn new_repo() -> (Arc<dyn UrlRepository>, CachingUrlRepository) {
+        new_repo_with_negative_ttl(Duration::ZERO)
+    }
+
+    #[tokio::test]
+    async fn test_miss_then_hit() {
+        let (inner, repo) = new_repo();
+        let short_url =
+            ShortUrl::new_for_test("cached12", "https://example.com/", time::Duration::days(1));
+        inner.save_url(short_url.clone()).await.unwrap();
+
+        let found = RetrievedUrl::Found(Box::new(short_url));
+        assert_eq!(repo.retrieve_url("cached12").await.unwrap(), found);
+
+        // NOTE: bypassing the cache shows that the second lookup never reaches the inner repo
+        inner.delete_url("cached12").await.unwrap();
+        assert_eq!(repo.retrieve_url("cached12").await.unwrap(), found);
+        assert_eq!(
+            repo.retrieve_url("missing1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_invalidates() {
+        let (inner, repo) = new_repo();
+        let old = ShortUrl::new_for_test(
+            "cached12",
+            "https://example.com/old",
+            time::Duration::days(1),
+        );
+        inner.save_url(old).await.unwrap();
+        repo.retrieve_url("cached12").await.unwrap();
+
+        inner.expire_url("cached12").await.unwrap();
+        let new = ShortUrl::new_for_test(
+            "cached12",
+            "https://example.com/new",
+            time::Duration::days(1),
+        );
+        repo.save_url(new.clone()).await.unwrap();
+        assert_eq!(
+            repo.retrieve_url("cached12").await.unwrap(),
+            RetrievedUrl::Found(Box::new(new))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deletes_invalidate() {
+        let (inner, repo) = new_repo();
+        for id in ["delete12", "expire12"] {
+            let short_url =
+                ShortUrl::new_for_test(id, "https://example.com/", time::Duration::days(1));
+            inner.save_url(short_url).await.unwrap();
+            repo.retrieve_url(id).await.unwrap();
+        }
+
+        assert!(repo.delete_url("delete12").await.unwrap());
+        assert!(repo.expire_url("expire12").await.unwrap());
+        assert_eq!(
+            repo.retrieve_url("delete12").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+        assert_eq!(
+            repo.retrieve_url("expire12").await.unwrap(),
+            RetrievedUrl::Expired
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_items_are_never_served() {
+        let (inner, repo) = new_repo();
+        let expired = ShortUrl::new_for_test(
+            "expired1",
+            "https://example.com/",
+            time::Duration::seconds(-1),
+        );
+        inner.save_url(expired.clone()).await.unwrap();
+        repo.insert("expired1", Some(expired), Instant::now());
+
+        assert_eq!(
+            repo.retrieve_url("expired1").await.unwrap(),
+            RetrievedUrl::Expired
+        );
+        assert!(repo.entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_limited_items_are_not_cached() {
+        let (inner, repo) = new_repo();
+        let limited = ShortUrl {
+            max_hits: Some(1),
+            ..ShortUrl::new_for_test("limited1", "https://example.com/", time::Duration::days(1))
+        };
+        inner.save_url(limited).await.unwrap();
+        assert!(matches!(
+            repo.retrieve_url("limited1").await.unwrap(),
+            RetrievedUrl::Found(_)
+        ));
+
+        assert!(repo.increment_hits_within_limit("limited1").await.unwrap());
+        assert_eq!(
+            repo.retrieve_url("limited1").await.unwrap(),
+            RetrievedUrl::Expired
+        );
+    }
+
+    #[test]
+    fn test_stale_items_are_not_served() {
+        let (_, repo) = new_repo();
+        let short_url =
+            ShortUrl::new_for_test("cached12", "https://example.com/", time::Duration::days(1));
+        let now = Instant::now();
+        repo.insert("cached12", Some(short_url.clone()), now);
+
+        assert_eq!(
+            repo.get("cached12", now),
+            Some(RetrievedUrl::Found(Box::new(short_url)))
+        );
+        assert_eq!(repo.get("cached12", now + CachingUrlRepository::TTL), None);
+        assert!(repo.entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_cached_within_negative_ttl() {
+        let (inner, repo) = new_repo_with_negative_ttl(Duration::from_secs(5));
+        assert_eq!(
+            repo.retrieve_url("scanned1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+
+        // NOTE: bypassing the cache shows that the second lookup never reaches the inner repo
+        let short_url =
+            ShortUrl::new_for_test("scanned1", "https://example.com/", time::Duration::days(1));
+        inner.save_url(short_url).await.unwrap();
+        assert_eq!(
+            repo.retrieve_url("scanned1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+
+        let now = Instant::now();
+        assert!(repo.get("scanned1", now + Duration::from_secs(5)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_save_evicts_not_found() {
+        let (_, repo) = new_repo_with_negative_ttl(Duration::from_secs(5));
+        repo.retrieve_url("created1").await.unwrap();
+
+        let short_url =
+            ShortUrl::new_for_test("created1", "https://example.com/", time::Duration::days(1));
+        repo.save_url(short_url.clone()).await.unwrap();
+        assert_eq!(
+            repo.retrieve_url("created1").await.unwrap(),
+            RetrievedUrl::Found(Box::new(short_url))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_not_cached_without_negative_ttl() {
+        let (_, repo) = new_repo();
+        repo.retrieve_url("scanned1").await.unwrap();
+        assert!(repo.entries().is_empty());
+    }
+}
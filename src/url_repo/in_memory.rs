@@ -0,0 +1,560 @@
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroU64,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use rearch::CapsuleHandle;
+use time::OffsetDateTime;
+use tokio::sync::RwLock;
+use tracing::{info, instrument};
+
+use super::{
+    ExpirationTime, RetrievedUrl, SaveUrlError, ShortUrl, ShortUrlStats, UrlRepository,
+    hits_exhausted,
+};
+
+/// A [`UrlRepository`] that lives only as long as the process, for local demos and tests.
+#[must_use]
+pub fn in_memory_url_repository_capsule(_: CapsuleHandle) -> Arc<dyn UrlRepository> {
+    Arc::new(InMemoryUrlRepository::default())
+}
+
+#[derive(Default)]
+pub(crate) struct InMemoryUrlRepository {
+    items: RwLock<HashMap<String, Item>>,
+}
+
+struct Item {
+    short_url: ShortUrl,
+    created_at: OffsetDateTime,
+    hit_count: u64,
+}
+impl Item {
+    fn is_expired(&self) -> bool {
+        self.short_url.expiration_time.inner < OffsetDateTime::now_utc()
+    }
+
+    /// Whether readers should see this item as expired, which also covers having used up
+    /// all of its redirects.
+    fn is_unreadable(&self) -> bool {
+        self.is_expired() || hits_exhausted(self.hit_count, self.short_url.max_hits)
+    }
+}
+
+#[async_trait]
+impl UrlRepository for InMemoryUrlRepository {
+    #[instrument(skip(self))]
+    async fn retrieve_url(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        Ok(match self.items.read().await.get(id) {
+            None => RetrievedUrl::NotFound,
+            Some(item) if item.is_unreadable() => RetrievedUrl::Expired,
+            Some(item) if !item.short_url.is_active() => RetrievedUrl::NotFound,
+            Some(item) => RetrievedUrl::Found(Box::new(item.short_url.clone())),
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn retrieve_and_consume(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        let mut items = self.items.write().await;
+        Ok(match items.get(id) {
+            None => RetrievedUrl::NotFound,
+            Some(item) if item.is_expired() => RetrievedUrl::Expired,
+            Some(item) if !item.short_url.is_active() => RetrievedUrl::NotFound,
+            Some(_) => items.remove(id).map_or(RetrievedUrl::NotFound, |item| {
+                RetrievedUrl::Found(Box::new(item.short_url))
+            }),
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn retrieve_url_stats(&self, id: &str) -> anyhow::Result<Option<ShortUrlStats>> {
+        Ok(self.items.read().await.get(id).map(|item| ShortUrlStats {
+            url: item.short_url.url.clone(),
+            expiration_time: item.short_url.expiration_time.inner,
+            hit_count: item.hit_count,
+            cache_max_age_seconds: item.short_url.cache_max_age_seconds,
+            max_hits: item.short_url.max_hits,
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn save_url(&self, short_url: ShortUrl) -> Result<ShortUrl, SaveUrlError> {
+        let mut items = self.items.write().await;
+        let short_id = short_url.short_id.inner.clone();
+
+        // NOTE: like the database implementation, expired items are overwritten
+        if let Some(existing) = items.get(&short_id)
+            && !existing.is_expired()
+        {
+            return Err(SaveUrlError::ItemAlreadyExists(Box::new(
+                existing.short_url.clone(),
+            )));
+        }
+
+        items.insert(
+            short_id,
+            Item {
+                short_url: short_url.clone(),
+                created_at: OffsetDateTime::now_utc(),
+                hit_count: 0,
+            },
+        );
+        drop(items);
+        Ok(short_url)
+    }
+
+    #[instrument(skip(self))]
+    async fn replace_url(&self, short_url: ShortUrl) -> anyhow::Result<bool> {
+        let mut items = self.items.write().await;
+        let Some(item) = items.get_mut(short_url.short_id.as_str()) else {
+            return Ok(false);
+        };
+        *item = Item {
+            short_url,
+            created_at: OffsetDateTime::now_utc(),
+            hit_count: 0,
+        };
+        drop(items);
+        Ok(true)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_recently_created(
+        &self,
+        long_url: &str,
+        since: OffsetDateTime,
+    ) -> anyhow::Result<Option<ShortUrl>> {
+        Ok(self
+            .items
+            .read()
+            .await
+            .values()
+            .filter(|item| {
+                item.short_url.url.as_str() == long_url
+                    && item.created_at >= since
+                    && !item.is_expired()
+            })
+            .max_by_key(|item| item.created_at)
+            .map(|item| item.short_url.clone()))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_owner_hosts(&self, owner: &str) -> anyhow::Result<HashSet<String>> {
+        Ok(self
+            .items
+            .read()
+            .await
+            .values()
+            .filter(|item| item.short_url.owner.as_deref() == Some(owner) && !item.is_expired())
+            .map(|item| item.short_url.url.host_str().unwrap_or_default().to_owned())
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn find_discoverable(&self, limit: u64) -> anyhow::Result<Vec<ShortUrl>> {
+        let mut discoverable = self
+            .items
+            .read()
+            .await
+            .values()
+            .filter(|item| item.short_url.discoverable && !item.is_expired())
+            .map(|item| item.short_url.clone())
+            .collect::<Vec<_>>();
+        discoverable.sort_unstable_by(|a, b| a.short_id.cmp(&b.short_id));
+        discoverable.truncate(limit.try_into().unwrap_or(usize::MAX));
+        Ok(discoverable)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_urls(&self, limit: u64, after: Option<String>) -> anyhow::Result<Vec<ShortUrl>> {
+        let mut page = self
+            .items
+            .read()
+            .await
+            .iter()
+            .filter(|(id, _)| after.as_ref().is_none_or(|after| *id > after))
+            .map(|(_, item)| item.short_url.clone())
+            .collect::<Vec<_>>();
+        page.sort_unstable_by(|a, b| a.short_id.cmp(&b.short_id));
+        page.truncate(limit.try_into().unwrap_or(usize::MAX));
+        Ok(page)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_url(&self, id: &str) -> anyhow::Result<bool> {
+        Ok(self.items.write().await.remove(id).is_some())
+    }
+
+    #[instrument(skip(self))]
+    async fn expire_url(&self, id: &str) -> anyhow::Result<bool> {
+        let expired_at = OffsetDateTime::now_utc() - time::Duration::seconds(1);
+        Ok(match self.items.write().await.get_mut(id) {
+            Some(item) => {
+                item.short_url.expiration_time = ExpirationTime::from_db(expired_at);
+                true
+            }
+            None => false,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn increment_hits(&self, id: &str) -> anyhow::Result<()> {
+        if let Some(item) = self.items.write().await.get_mut(id) {
+            item.hit_count += 1;
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn increment_hits_within_limit(&self, id: &str) -> anyhow::Result<bool> {
+        Ok(match self.items.write().await.get_mut(id) {
+            Some(item)
+                if item
+                    .short_url
+                    .max_hits
+                    .is_none_or(|max_hits| item.hit_count < max_hits) =>
+            {
+                item.hit_count += 1;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_expired_urls(&self, _batch_size: Option<NonZeroU64>) -> anyhow::Result<u64> {
+        // NOTE: batching only matters for databases, where deletes can hold row locks
+        let mut items = self.items.write().await;
+        let num_items = items.len();
+        items.retain(|_, item| !item.is_expired());
+        let deleted = num_items - items.len();
+        drop(items);
+        info!(deleted, "Deleted expired items from memory");
+        Ok(deleted.try_into().unwrap_or(u64::MAX))
+    }
+
+    #[instrument(skip(self))]
+    async fn count_expired_urls(&self) -> anyhow::Result<u64> {
+        let num_expired = self
+            .items
+            .read()
+            .await
+            .values()
+            .filter(|item| item.is_expired())
+            .count();
+        Ok(num_expired.try_into().unwrap_or(u64::MAX))
+    }
+
+    async fn ping(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+
+    /// Bypasses [`ExpirationTime::new`] validation so that already-expired items can be created.
+    async fn repo_with(short_urls: impl IntoIterator<Item = ShortUrl>) -> InMemoryUrlRepository {
+        let repo = InMemoryUrlRepository::default();
+        {
+            let mut items = repo.items.write().await;
+            for short_url in short_urls {
+                items.insert(
+                    short_url.short_id.inner.clone(),
+                    Item {
+                        short_url,
+                        created_at: OffsetDateTime::now_utc(),
+                        hit_count: 0,
+                    },
+                );
+            }
+        }
+        repo
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_url_non_existent() {
+        let repo = repo_with([]).await;
+        assert_eq!(
+            repo.retrieve_url("nonexistent").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_url_expired() {
+        let short_url =
+            ShortUrl::new_for_test("expired", "https://example.com", Duration::seconds(-1));
+        let repo = repo_with([short_url]).await;
+        assert_eq!(
+            repo.retrieve_url("expired").await.unwrap(),
+            RetrievedUrl::Expired
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_url_nonexpired() {
+        let short_url =
+            ShortUrl::new_for_test("nonexpired", "https://example.com", Duration::days(1));
+        let repo = repo_with([short_url.clone()]).await;
+        assert_eq!(
+            repo.retrieve_url("nonexpired").await.unwrap(),
+            RetrievedUrl::Found(Box::new(short_url))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_url_not_before_windows() {
+        let not_before = |id, not_before_in: Duration, expires_in| ShortUrl {
+            not_before: Some(OffsetDateTime::now_utc() + not_before_in),
+            ..ShortUrl::new_for_test(id, "https://example.com", expires_in)
+        };
+        let pending = not_before("pending1", Duration::hours(1), Duration::days(1));
+        let active = not_before("active12", Duration::hours(-1), Duration::days(1));
+        let expired = not_before("expired1", Duration::days(-2), Duration::days(-1));
+        let repo = repo_with([pending, active.clone(), expired]).await;
+
+        assert_eq!(
+            repo.retrieve_url("pending1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+        assert_eq!(
+            repo.retrieve_and_consume("pending1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+        assert!(repo.retrieve_url_stats("pending1").await.unwrap().is_some());
+        assert_eq!(
+            repo.retrieve_url("active12").await.unwrap(),
+            RetrievedUrl::Found(Box::new(active))
+        );
+        assert_eq!(
+            repo.retrieve_url("expired1").await.unwrap(),
+            RetrievedUrl::Expired
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_url_newly_created() {
+        let short_url =
+            ShortUrl::new_for_test("valid123", "https://example.com", Duration::days(1));
+        let repo = repo_with([]).await;
+
+        let actual = repo.save_url(short_url.clone()).await.unwrap();
+        assert_eq!(actual, short_url);
+        assert_eq!(
+            repo.retrieve_url("valid123").await.unwrap(),
+            RetrievedUrl::Found(Box::new(short_url))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_url_conflict_nonexpired() {
+        let existing = ShortUrl::new_for_test("conflict", "https://example.com", Duration::days(1));
+        let repo = repo_with([existing.clone()]).await;
+
+        let to_save = ShortUrl::new_for_test("conflict", "https://other.com", Duration::days(1));
+        let err = repo.save_url(to_save).await.unwrap_err();
+        assert!(matches!(err, SaveUrlError::ItemAlreadyExists(item) if *item == existing));
+    }
+
+    #[tokio::test]
+    async fn test_save_url_conflict_expired() {
+        let existing =
+            ShortUrl::new_for_test("conflict", "https://example.com", Duration::seconds(-1));
+        let repo = repo_with([existing]).await;
+
+        let to_save = ShortUrl::new_for_test("conflict", "https://other.com", Duration::days(1));
+        let actual = repo.save_url(to_save.clone()).await.unwrap();
+        assert_eq!(actual, to_save);
+        assert_eq!(
+            repo.retrieve_url("conflict").await.unwrap(),
+            RetrievedUrl::Found(Box::new(to_save))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_recently_created() {
+        let short_url =
+            ShortUrl::new_for_test("recent123", "https://example.com/", Duration::days(1));
+        let repo = repo_with([short_url.clone()]).await;
+
+        let since = OffsetDateTime::now_utc() - Duration::minutes(1);
+        let found = repo
+            .find_recently_created("https://example.com/", since)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(short_url));
+
+        let since = OffsetDateTime::now_utc() + Duration::minutes(1);
+        let not_found = repo
+            .find_recently_created("https://example.com/", since)
+            .await
+            .unwrap();
+        assert_eq!(not_found, None);
+    }
+
+    #[tokio::test]
+    async fn test_increment_hits_and_stats() {
+        let short_url =
+            ShortUrl::new_for_test("hits1234", "https://example.com/", Duration::days(1));
+        let repo = repo_with([short_url]).await;
+
+        repo.increment_hits("hits1234").await.unwrap();
+        repo.increment_hits("hits1234").await.unwrap();
+
+        let stats = repo.retrieve_url_stats("hits1234").await.unwrap().unwrap();
+        assert_eq!(stats.hit_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_increment_hits_within_limit() {
+        let limited = ShortUrl {
+            max_hits: Some(2),
+            ..ShortUrl::new_for_test("limited1", "https://example.com/", Duration::days(1))
+        };
+        let repo = repo_with([limited]).await;
+
+        assert!(repo.increment_hits_within_limit("limited1").await.unwrap());
+        assert!(repo.increment_hits_within_limit("limited1").await.unwrap());
+        assert!(!repo.increment_hits_within_limit("limited1").await.unwrap());
+        assert!(!repo.increment_hits_within_limit("missing1").await.unwrap());
+
+        let stats = repo.retrieve_url_stats("limited1").await.unwrap().unwrap();
+        assert_eq!(stats.hit_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_find_discoverable_lists_only_discoverable_live_urls() {
+        let discoverable = |short_url| ShortUrl {
+            discoverable: true,
+            ..short_url
+        };
+        let live = discoverable(ShortUrl::new_for_test(
+            "live1234",
+            "https://example.com/",
+            Duration::days(1),
+        ));
+        let expired = discoverable(ShortUrl::new_for_test(
+            "expired1",
+            "https://example.com/",
+            Duration::seconds(-1),
+        ));
+        let hidden = ShortUrl::new_for_test("hidden12", "https://example.com/", Duration::days(1));
+        let repo = repo_with([live.clone(), expired, hidden]).await;
+
+        assert_eq!(repo.find_discoverable(10).await.unwrap(), vec![live]);
+        assert!(repo.find_discoverable(0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_urls_pages_by_id() {
+        let first = ShortUrl::new_for_test("page0001", "https://example.com/", Duration::days(1));
+        let second =
+            ShortUrl::new_for_test("page0002", "https://example.com/", Duration::seconds(-1));
+        let third = ShortUrl::new_for_test("page0003", "https://example.com/", Duration::days(1));
+        let repo = repo_with([third.clone(), first.clone(), second.clone()]).await;
+
+        assert_eq!(
+            repo.list_urls(2, None).await.unwrap(),
+            vec![first, second.clone()]
+        );
+        assert_eq!(
+            repo.list_urls(2, Some(second.short_id.into_inner()))
+                .await
+                .unwrap(),
+            vec![third]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_url() {
+        let short_url =
+            ShortUrl::new_for_test("delete12", "https://example.com/", Duration::days(1));
+        let repo = repo_with([short_url]).await;
+
+        assert!(repo.delete_url("delete12").await.unwrap());
+        assert!(!repo.delete_url("delete12").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expire_url() {
+        let short_url =
+            ShortUrl::new_for_test("expire12", "https://example.com/", Duration::days(1));
+        let repo = repo_with([short_url]).await;
+
+        assert!(repo.expire_url("expire12").await.unwrap());
+        assert_eq!(
+            repo.retrieve_url("expire12").await.unwrap(),
+            RetrievedUrl::Expired
+        );
+        assert!(repo.retrieve_url_stats("expire12").await.unwrap().is_some());
+        assert!(!repo.expire_url("missing1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_and_consume() {
+        let short_url =
+            ShortUrl::new_for_test("consume1", "https://example.com/", Duration::days(1));
+        let expired =
+            ShortUrl::new_for_test("expired", "https://example.com/", Duration::seconds(-1));
+        let repo = repo_with([short_url.clone(), expired]).await;
+
+        assert_eq!(
+            repo.retrieve_and_consume("consume1").await.unwrap(),
+            RetrievedUrl::Found(Box::new(short_url))
+        );
+        assert_eq!(
+            repo.retrieve_and_consume("consume1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+        assert_eq!(
+            repo.retrieve_and_consume("expired").await.unwrap(),
+            RetrievedUrl::Expired
+        );
+        assert!(repo.retrieve_url_stats("expired").await.unwrap().is_some());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_retrieve_and_consume_concurrently() {
+        let short_url =
+            ShortUrl::new_for_test("consume1", "https://example.com/", Duration::days(1));
+        let repo = Arc::new(repo_with([short_url]).await);
+
+        let consumers = (0..16)
+            .map(|_| {
+                let repo = Arc::clone(&repo);
+                tokio::spawn(async move { repo.retrieve_and_consume("consume1").await.unwrap() })
+            })
+            .collect::<Vec<_>>();
+        let mut num_found = 0;
+        for consumer in consumers {
+            if matches!(consumer.await.unwrap(), RetrievedUrl::Found(_)) {
+                num_found += 1;
+            }
+        }
+        assert_eq!(num_found, 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired_urls() {
+        let expired =
+            ShortUrl::new_for_test("expired", "https://example.com", Duration::seconds(-1));
+        let nonexpired =
+            ShortUrl::new_for_test("nonexpired", "https://example.com", Duration::days(1));
+        let repo = repo_with([expired, nonexpired]).await;
+
+        assert_eq!(repo.count_expired_urls().await.unwrap(), 1);
+        assert_eq!(repo.delete_expired_urls(None).await.unwrap(), 1);
+        assert_eq!(repo.count_expired_urls().await.unwrap(), 0);
+
+        let items = repo.items.into_inner();
+        assert!(!items.contains_key("expired"));
+        assert!(items.contains_key("nonexpired"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::{collections::HashSet, num::NonZeroU64, sync::Arc};
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use tracing::{info, instrument};
+
+use super::{RetrievedUrl, SaveUrlError, ShortUrl, ShortUrlStats, UrlRepository};
+
+/// Wraps a primary [`UrlRepository`] so that lookups of short IDs it doesn't have
+/// fall back to a read-only secondary one, such as the old database during a migration.
+///
+/// NOTE: everything else, including all writes, only ever goes to the primary.
+pub(crate) struct ReadThroughUrlRepository {
+    pub(crate) primary: Arc<dyn UrlRepository>,
+    pub(crate) secondary: Arc<dyn UrlRepository>,
+}
+
+#[async_trait]
+impl UrlRepository for ReadThroughUrlRepository {
+    #[instrument(skip(self))]
+    async fn retrieve_url(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        match self.primary.retrieve_url(id).await? {
+            RetrievedUrl::NotFound => {
+                info!("Short ID not found in primary; reading through to secondary");
+                self.secondary.retrieve_url(id).await
+            }
+            retrieved_url => Ok(retrieved_url),
+        }
+    }
+
+    /// NOTE: the secondary is read-only, so only the primary's items can be consumed.
+    async fn retrieve_and_consume(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        self.primary.retrieve_and_consume(id).await
+    }
+
+    #[instrument(skip(self))]
+    async fn retrieve_url_stats(&self, id: &str) -> anyhow::Result<Option<ShortUrlStats>> {
+        match self.primary.retrieve_url_stats(id).await? {
+            Some(stats) => Ok(Some(stats)),
+            None => self.secondary.retrieve_url_stats(id).await,
+        }
+    }
+
+    async fn save_url(&self, short_url: ShortUrl) -> Result<ShortUrl, SaveUrlError> {
+        self.primary.save_url(short_url).await
+    }
+
+    /// NOTE: the secondary is read-only, so only the primary's items can be replaced.
+    async fn replace_url(&self, short_url: ShortUrl) -> anyhow::Result<bool> {
+        self.primary.replace_url(short_url).await
+    }
+
+    async fn find_recently_created(
+        &self,
+        long_url: &str,
+        since: OffsetDateTime,
+    ) -> anyhow::Result<Option<ShortUrl>> {
+        self.primary.find_recently_created(long_url, since).await
+    }
+
+    async fn find_owner_hosts(&self, owner: &str) -> anyhow::Result<HashSet<String>> {
+        self.primary.find_owner_hosts(owner).await
+    }
+
+    async fn find_discoverable(&self, limit: u64) -> anyhow::Result<Vec<ShortUrl>> {
+        self.primary.find_discoverable(limit).await
+    }
+
+    async fn list_urls(&self, limit: u64, after: Option<String>) -> anyhow::Result<Vec<ShortUrl>> {
+        self.primary.list_urls(limit, after).await
+    }
+
+    async fn delete_url(&self, id: &str) -> anyhow::Result<bool> {
+        self.primary.delete_url(id).await
+    }
+
+    async fn expire_url(&self, id: &str) -> anyhow::Result<bool> {
+        self.primary.expire_url(id).await
+    }
+
+    async fn increment_hits(&self, id: &str) -> anyhow::Result<()> {
+        self.primary.increment_hits(id).await
+    }
+
+    async fn increment_hits_within_limit(&self, id: &str) -> anyhow::Result<bool> {
+        self.primary.increment_hits_within_limit(id).await
+    }
+
+    async fn delete_expired_urls(&self, batch_size: Option<NonZeroU64>) -> anyhow::Result<u64> {
+        self.primary.delete_expired_urls(batch_size).await
+    }
+
+    async fn count_expired_urls(&self) -> anyhow::Result<u64> {
+        self.primary.count_expired_urls().await
+    }
+
+    async fn ping(&self) -> anyhow::Result<()> {
+        self.primary.ping().await
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::url_repo::in_memory::InMemoryUrlRepository;
+
+    async fn repo_with(short_urls: impl IntoIterator<Item = ShortUrl>) -> Arc<dyn UrlRepository> {
+        let repo = InMemoryUrlRepository::default();
+        for short_url in short_urls {
+            repo.save_url(short_url).await.unwrap();
+        }
+        Arc::new(repo)
+    }
+
+    #[tokio::test]
+    async fn test_primary_miss_reads_through_to_secondary() {
+        let migrated = ShortUrl::new_for_test(
+            "migrated",
+            "https://example.com/old",
+            time::Duration::days(1),
+        );
+        let repo = ReadThroughUrlRepository {
+            primary: repo_with([]).await,
+            secondary: repo_with([migrated.clone()]).await,
+        };
+
+        let result = repo.retrieve_url("migrated").await.unwrap();
+        assert_eq!(result, RetrievedUrl::Found(Box::new(migrated)));
+        assert!(repo.retrieve_url_stats("migrated").await.unwrap().is_some());
+        assert_eq!(
+            repo.retrieve_url("missing1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_primary_hit_skips_secondary() {
+        let current = ShortUrl::new_for_test(
+            "shared12",
+            "https://example.com/new",
+            time::Duration::days(1),
+        );
+        let repo = ReadThroughUrlRepository {
+            primary: repo_with([current.clone()]).await,
+            secondary: repo_with([ShortUrl::new_for_test(
+                "shared12",
+                "https://example.com/old",
+                time::Duration::days(1),
+            )])
+            .await,
+        };
+
+        let result = repo.retrieve_url("shared12").await.unwrap();
+        assert_eq!(result, RetrievedUrl::Found(Box::new(current)));
+    }
+
+    #[tokio::test]
+    async fn test_writes_only_go_to_primary() {
+        let primary = repo_with([]).await;
+        let secondary = repo_with([]).await;
+        let repo = ReadThroughUrlRepository {
+            primary: Arc::clone(&primary),
+            secondary: Arc::clone(&secondary),
+        };
+
+        repo.save_url(ShortUrl::new_for_test(
+            "written1",
+            "https://example.com/",
+            time::Duration::days(1),
+        ))
+        .await
+        .unwrap();
+        assert!(matches!(
+            primary.retrieve_url("written1").await.unwrap(),
+            RetrievedUrl::Found(_)
+        ));
+        assert_eq!(
+            secondary.retrieve_url("written1").await.unwrap(),
+            RetrievedUrl::NotFound
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::{collections::HashSet, num::NonZeroU64, sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use tracing::Span;
+
+use super::{RetrievedUrl, SaveUrlError, ShortUrl, ShortUrlStats, UrlRepository};
+
+/// Wraps a [`UrlRepository`] to record how long each call took (in microseconds)
+/// as the `db_query_us` field of the calling request's span.
+///
+/// NOTE: the field must be declared by the span (e.g. `fields(db_query_us)`) to be recorded,
+/// and holds the latest call's duration when a request makes several.
+pub(crate) struct TimedUrlRepository {
+    pub(crate) inner: Arc<dyn UrlRepository>,
+}
+
+async fn timed<T>(call: impl Future<Output = T> + Send) -> T {
+    let start = Instant::now();
+    let output = call.await;
+    record_elapsed_us(&Span::current(), "db_query_us", start);
+    output
+}
+
+/// Records the time since `start`, in microseconds, as the `field` of `span`.
+pub(crate) fn record_elapsed_us(span: &Span, field: &str, start: Instant) {
+    let elapsed_us = u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX);
+    span.record(field, elapsed_us);
+}
+
+#[async_trait]
+impl UrlRepository for TimedUrlRepository {
+    async fn retrieve_url(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        timed(self.inner.retrieve_url(id)).await
+    }
+
+    async fn retrieve_and_consume(&self, id: &str) -> anyhow::Result<RetrievedUrl> {
+        timed(self.inner.retrieve_and_consume(id)).await
+    }
+
+    async fn retrieve_url_stats(&self, id: &str) -> anyhow::Result<Option<ShortUrlStats>> {
+        timed(self.inner.retrieve_url_stats(id)).await
+    }
+
+    async fn save_url(&self, short_url: ShortUrl) -> Result<ShortUrl, SaveUrlError> {
+        timed(self.inner.save_url(short_url)).await
+    }
+
+    async fn replace_url(&self, short_url: ShortUrl) -> anyhow::Result<bool> {
+        timed(self.inner.replace_url(short_url)).await
+    }
+
+    async fn find_recently_created(
+        &self,
+        long_url: &str,
+        since: OffsetDateTime,
+    ) -> anyhow::Result<Option<ShortUrl>> {
+        timed(self.inner.find_recently_created(long_url, since)).await
+    }
+
+    async fn find_owner_hosts(&self, owner: &str) -> anyhow::Result<HashSet<String>> {
+        timed(self.inner.find_owner_hosts(owner)).await
+    }
+
+    async fn find_discoverable(&self, limit: u64) -> anyhow::Result<Vec<ShortUrl>> {
+        timed(self.inner.find_discoverable(limit)).await
+    }
+
+    async fn list_urls(&self, limit: u64, after: Option<String>) -> anyhow::Result<Vec<ShortUrl>> {
+        timed(self.inner.list_urls(limit, after)).await
+    }
+
+    async fn delete_url(&self, id: &str) -> anyhow::Result<bool> {
+        timed(self.inner.delete_url(id)).await
+    }
+
+    async fn expire_url(&self, id: &str) -> anyhow::Result<bool> {
+        timed(self.inner.expire_url(id)).await
+    }
+
+    async fn increment_hits(&self, id: &str) -> anyhow::Result<()> {
+        timed(self.inner.increment_hits(id)).await
+    }
+
+    async fn increment_hits_within_limit(&self, id: &str) -> anyhow::Result<bool> {
+        timed(self.inner.increment_hits_within_limit(id)).await
+    }
+
+    async fn delete_expired_urls(&self, batch_size: Option<NonZeroU64>) -> anyhow::Result<u64> {
+        timed(self.inner.delete_expired_urls(batch_size)).await
+    }
+
+    async fn count_expired_urls(&self) -> anyhow::Result<u64> {
+        timed(self.inner.count_expired_urls()).await
+    }
+
+    async fn ping(&self) -> anyhow::Result<()> {
+        timed(self.inner.ping()).await
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tracing::{Instrument, Subscriber, field::Field, info_span, span::Record};
+    use tracing_subscriber::{Layer, layer::Context, layer::SubscriberExt, registry::LookupSpan};
+
+    use super::*;
+    use crate::url_repo::in_memory::InMemoryUrlRepository;
+
+    /// Captures the names of the fields recorded onto spans after they were created.
+    #[derive(Clone, Default)]
+    struct RecordedFields(Arc<Mutex<Vec<String>>>);
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for RecordedFields {
+        fn on_record(&self, _: &tracing::span::Id, values: &Record<'_>, _: Context<'_, S>) {
+            struct Visitor<'a>(&'a mut Vec<String>);
+            impl tracing::field::Visit for Visitor<'_> {
+                fn record_debug(&mut self, field: &Field, _: &dyn std::fmt::Debug) {
+                    self.0.push(field.name().to_owned());
+                }
+            }
+            values.record(&mut Visitor(&mut self.0.lock().unwrap()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_records_db_query_duration() {
+        let recorded_fields = RecordedFields::default();
+        let subscriber = tracing_subscriber::registry().with(recorded_fields.clone());
+        let repo = TimedUrlRepository {
+            inner: Arc::new(InMemoryUrlRepository::default()),
+        };
+
+        let _default = tracing::subscriber::set_default(subscriber);
+        let span = info_span!("request", db_query_us = tracing::field::Empty);
+        repo.retrieve_url("timed123")
+            .instrument(span)
+            .await
+            .unwrap();
+
+        assert_eq!(*recorded_fields.0.lock().unwrap(), ["db_query_us"]);
+    }
+}
@@ -1,71 +1,592 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    str::FromStr,
+    sync::Arc,
+};
 
 use anyhow::{Context, anyhow};
 use async_trait::async_trait;
+use futures::{StreamExt, stream};
 use rand::{Rng, rngs::ThreadRng};
 use rearch::CapsuleHandle;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use time::{OffsetDateTime, format_description::well_known::Rfc3339};
-use tracing::{error, instrument, warn};
-use url::Url;
+use time::{Duration, OffsetDateTime, Time, UtcOffset, format_description::well_known::Rfc3339};
+use tracing::{Span, error, field::Empty, instrument, warn};
+use url::{Url, form_urlencoded};
 
-use crate::url_repo::{
-    self, ExpirationTime, ExpirationTimeValidationError, SaveUrlError, ShortId,
-    ShortIdValidationError, UrlRepository, url_repository_capsule,
+use crate::{
+    config::{
+        allow_private_urls_capsule, ambiguous_time_policy_capsule, base_url_capsule,
+        collapse_bulk_duplicates_capsule, default_ttl_capsule, echo_expiration_offset_capsule,
+        enforce_scheme_on_read_capsule, expiration_policy_capsule, hash_bytes_capsule,
+        id_alphabet_capsule, max_bulk_size_capsule, max_hosts_per_api_key_capsule,
+        max_list_limit_capsule, max_ttl_capsule, max_url_length_capsule, min_cache_seconds_capsule,
+        min_distinct_id_chars_capsule, namespaces_capsule, normalize_trailing_dot_hosts_capsule,
+        normalize_urls_capsule, post_retry_attempts_capsule, request_timings_capsule,
+        reserved_ids_capsule, short_id_length_bounds_capsule, shortener_domains_capsule,
+        sitemap_capsule, strip_tracking_params_capsule, target_cooldown_capsule,
+        url_scheme_allowlist_capsule,
+    },
+    url_repo::{
+        self, CacheStatus, ExpirationTime, ExpirationTimeValidationError, NAMESPACE_SEPARATOR,
+        RetrievedUrl, SaveUrlError, ShortId, ShortIdLengthBounds, ShortIdValidationError,
+        UrlRepository, timed::record_elapsed_us, url_repository_capsule,
+    },
+    url_validation::{DisallowedUrlError, check_public_destination},
 };
 
 #[derive(Deserialize)]
 pub struct PutUrlPayload {
     pub url: String,
-    pub expiration_timestamp: String,
+    /// When omitted, the URL expires after the server's default TTL
+    pub expiration_timestamp: Option<String>,
+    /// Query parameters (e.g. `utm_source=x&utm_medium=y`) to merge onto the URL on redirect
+    pub utm_template: Option<String>,
+    /// Whether the URL may be listed publicly (e.g. in the sitemap)
+    #[serde(default)]
+    pub discoverable: bool,
+    /// How long redirects may be cached for, instead of until expiration
+    pub cache_max_age_seconds: Option<u64>,
+    /// Whether the URL is deleted after its first redirect
+    #[serde(default)]
+    pub single_use: bool,
+    /// How many redirects the URL allows before it's gone, if limited
+    pub max_hits: Option<u64>,
+    /// When the URL starts redirecting; until then, it's treated as not found
+    pub not_before_timestamp: Option<String>,
 }
 
-#[derive(Deserialize)]
+/// Everything about a [`UrlRestService::put_url`] besides the short ID and URL,
+/// where the [`Default`] is a plain short URL that expires after the default TTL.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PutUrlOptions<'a> {
+    pub expiration_timestamp: Option<&'a str>,
+    pub utm_template: Option<&'a str>,
+    pub discoverable: bool,
+    pub cache_max_age_seconds: Option<u64>,
+    pub single_use: bool,
+    pub max_hits: Option<u64>,
+    pub not_before_timestamp: Option<&'a str>,
+    pub precondition: PutPrecondition,
+    /// Which API key (by its [`crate::auth::api_key_id`]) is creating the URL, if any
+    pub owner: Option<&'a str>,
+}
+
+/// What a conditional [`UrlRestService::put_url`] requires of its short ID beforehand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PutPrecondition {
+    /// The short ID is saved to whether or not it already exists.
+    #[default]
+    None,
+    /// The short ID must not exist yet, even with the same content (`If-None-Match: *`).
+    CreateOnly,
+    /// The short ID must already exist (`If-Match: *`), expired or not,
+    /// and its item is replaced rather than anything new being created.
+    MustExist,
+}
+
+#[derive(Debug, Deserialize)]
 pub struct PostUrlPayload {
     pub url: String,
-    pub expiration_timestamp: String,
+    /// When omitted, the URL expires after the server's default TTL
+    pub expiration_timestamp: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PostUrlQuery {
+    /// Where to redirect once the URL is shortened, instead of responding with it
+    pub redirect: Option<PostUrlRedirect>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostUrlRedirect {
+    /// The new link's preview page (`/{id}+`)
+    Preview,
+}
+
+#[derive(Deserialize)]
+pub struct ListUrlsQuery {
+    /// When omitted, the server's maximum page size is used
+    pub limit: Option<u64>,
+    /// The `next_cursor` of the previous page, if any
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct QrCodeQuery {
+    /// The width and height in pixels; defaults to [`QR_CODE_DEFAULT_SIZE`]
+    pub size: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteUrlQuery {
+    /// Whether deleting a short ID that doesn't exist (e.g. on a retry) still succeeds
+    #[serde(default)]
+    pub idempotent: bool,
 }
 
 #[derive(Debug, Serialize)]
+pub struct UrlPage {
+    pub items: Vec<ListedUrl>,
+    /// Opaque cursor for the next page, or none when this is the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ShortenedUrl {
     pub shortened_url_id: String,
+    /// The full short URL (`BASE_URL` joined with the ID), or just the ID when not configured
+    pub short_url: String,
     pub long_url: String,
     /// Timestamp in ISO-8601 format
     pub expiration_timestamp: String,
 }
 
+/// A [`ShortenedUrl`] along with the metadata only shown to admins.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ListedUrl {
+    #[serde(flatten)]
+    pub shortened_url: ShortenedUrl,
+    /// The URL exactly as submitted, before normalization (when recorded)
+    pub original_url: Option<String>,
+}
+
+/// Everything stored about a short URL, along with how its short ID came about,
+/// for admins investigating it.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct UrlDebugInfo {
+    #[serde(flatten)]
+    pub listed_url: ListedUrl,
+    pub utm_template: Option<String>,
+    pub discoverable: bool,
+    pub cache_max_age_seconds: Option<u64>,
+    pub single_use: bool,
+    pub max_hits: Option<u64>,
+    /// Timestamp in ISO-8601 format
+    pub not_before_timestamp: Option<String>,
+    /// Which API key (by its [`crate::auth::api_key_id`]) created this URL, if any
+    pub owner: Option<String>,
+    /// Whether the short ID is the one that the first attempt of a `POST` derives
+    /// from this item's URL, i.e. it was generated rather than chosen.
+    ///
+    /// IDs generated after a collision (with a random salt), or by a `POST` that formatted
+    /// its expiration timestamp differently than it's stored, can't be re-derived,
+    /// so they look chosen too.
+    pub generated_id: bool,
+}
+
 #[derive(Debug)]
 pub struct Redirect {
     pub url: String,
     pub max_age_seconds: u64,
+    /// Whether caches must revalidate the redirect before reusing it,
+    /// since its URL expires soon after
+    pub revalidate: bool,
+    /// Whether the in-memory cache served the short URL (when it's enabled)
+    pub cache_status: Option<CacheStatus>,
+}
+impl Redirect {
+    /// The `Cache-Control` header value to send along with this redirect.
+    #[must_use]
+    pub fn cache_control(&self) -> String {
+        match self.max_age_seconds {
+            // NOTE: about-to-expire entries must not be cached past their expiration
+            0 => "no-store".to_owned(),
+            _ if self.revalidate => "no-cache".to_owned(),
+            max_age_seconds => format!("public, max-age={max_age_seconds}"),
+        }
+    }
+
+    /// How long a redirect to a URL expiring at `expiration_time` may be cached,
+    /// saturating at 0 for (about to be) expired URLs.
+    ///
+    /// A link's `cache_max_age_seconds` can shorten, but never extend, this.
+    fn max_age_seconds(expiration_time: OffsetDateTime, cache_max_age_seconds: Option<u64>) -> u64 {
+        let until_expiration = Self::seconds_until(expiration_time);
+        cache_max_age_seconds.map_or(until_expiration, |max_age| max_age.min(until_expiration))
+    }
+
+    /// The whole seconds left until `expiration_time`, saturating at 0.
+    fn seconds_until(expiration_time: OffsetDateTime) -> u64 {
+        (expiration_time - OffsetDateTime::now_utc())
+            .whole_seconds()
+            .try_into()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UrlStats {
+    pub shortened_url_id: String,
+    pub long_url: String,
+    /// Timestamp in ISO-8601 format
+    pub expiration_timestamp: String,
+    /// How many times the short URL has been redirected to
+    pub hit_count: u64,
+    /// How long the redirect may currently be cached for (matching its `Cache-Control`)
+    pub max_age_seconds: u64,
+    /// The link's configured cache age, if it overrides caching until expiration
+    pub cache_max_age_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Health {
+    pub status: HealthStatus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    DbUnreachable,
+}
+
+/// The publicly visible policy that shortened URLs must follow, for client-side pre-validation.
+#[derive(Debug, Serialize)]
+pub struct UrlPolicy {
+    pub allowed_schemes: Vec<String>,
+    /// Whether URLs to known shorteners are rejected
+    /// (the blocklist itself is intentionally not exposed).
+    pub shortener_blocklist_active: bool,
 }
 
 pub fn url_rest_service_capsule(
     CapsuleHandle { mut get, .. }: CapsuleHandle,
 ) -> Arc<dyn UrlRestService> {
     let url_repo = Arc::clone(get.as_ref(url_repository_capsule));
-    Arc::new(UrlRestServiceImpl { url_repo })
+    let expiration_policy = *get.as_ref(expiration_policy_capsule);
+    let ambiguous_time_policy = *get.as_ref(ambiguous_time_policy_capsule);
+    let echo_expiration_offset = *get.as_ref(echo_expiration_offset_capsule);
+    let shortener_domains = get.as_ref(shortener_domains_capsule).clone();
+    let allowed_schemes = get.as_ref(url_scheme_allowlist_capsule).clone();
+    let enforce_scheme_on_read = *get.as_ref(enforce_scheme_on_read_capsule);
+    let base_url = get.as_ref(base_url_capsule).clone();
+    let target_cooldown = *get.as_ref(target_cooldown_capsule);
+    let max_hosts_per_owner = *get.as_ref(max_hosts_per_api_key_capsule);
+    let default_ttl = *get.as_ref(default_ttl_capsule);
+    let max_ttl = *get.as_ref(max_ttl_capsule);
+    let min_distinct_id_chars = *get.as_ref(min_distinct_id_chars_capsule);
+    let normalize_trailing_dot_hosts = *get.as_ref(normalize_trailing_dot_hosts_capsule);
+    let normalize_urls = *get.as_ref(normalize_urls_capsule);
+    let strip_tracking_params = *get.as_ref(strip_tracking_params_capsule);
+    let reserved_ids = get.as_ref(reserved_ids_capsule).clone();
+    let allow_private_urls = *get.as_ref(allow_private_urls_capsule);
+    let max_url_length = *get.as_ref(max_url_length_capsule);
+    let max_bulk_size = *get.as_ref(max_bulk_size_capsule);
+    let collapse_bulk_duplicates = *get.as_ref(collapse_bulk_duplicates_capsule);
+    let short_id_length_bounds = *get.as_ref(short_id_length_bounds_capsule);
+    let hash_bytes = *get.as_ref(hash_bytes_capsule);
+    let post_retry_attempts = *get.as_ref(post_retry_attempts_capsule);
+    let id_alphabet = get.as_ref(id_alphabet_capsule).clone();
+    let sitemap_enabled = *get.as_ref(sitemap_capsule);
+    let namespaces_enabled = *get.as_ref(namespaces_capsule);
+    let request_timings = *get.as_ref(request_timings_capsule);
+    let max_list_limit = *get.as_ref(max_list_limit_capsule);
+    let min_cache_seconds = *get.as_ref(min_cache_seconds_capsule);
+    Arc::new(UrlRestServiceImpl {
+        url_repo,
+        options: UrlServiceOptions {
+            base_url,
+            expiration: ExpirationOptions {
+                expiration_policy,
+                ambiguous_time_policy,
+                echo_expiration_offset,
+                default_ttl,
+                max_ttl,
+                min_cache_seconds,
+            },
+            destinations: DestinationOptions {
+                shortener_domains,
+                allowed_schemes,
+                enforce_scheme_on_read,
+                allow_private_urls,
+                max_url_length,
+                target_cooldown,
+                max_hosts_per_owner,
+            },
+            normalization: NormalizationOptions {
+                normalize_trailing_dot_hosts,
+                normalize_urls,
+                strip_tracking_params,
+            },
+            short_ids: ShortIdOptions {
+                min_distinct_id_chars,
+                reserved_ids,
+                namespaces_enabled,
+                short_id_length_bounds,
+                hash_bytes,
+                post_retry_attempts,
+                id_alphabet,
+            },
+            max_bulk_size,
+            collapse_bulk_duplicates,
+            sitemap_enabled,
+            request_timings,
+            max_list_limit,
+        },
+    })
+}
+
+/// Whether `host` is `domain` or one of its subdomains.
+pub(crate) fn host_matches_domain(host: &str, domain: &str) -> bool {
+    host.eq_ignore_ascii_case(domain)
+        || host
+            .len()
+            .checked_sub(domain.len() + 1)
+            .is_some_and(|dot_index| {
+                host.as_bytes()[dot_index] == b'.'
+                    && host[dot_index + 1..].eq_ignore_ascii_case(domain)
+            })
+}
+
+/// Restricts which instants a URL may expire at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExpirationPolicy {
+    /// Any (otherwise valid) expiration time is allowed.
+    #[default]
+    Any,
+    /// Expiration times are rounded up to the next midnight UTC.
+    SnapToMidnightUtc,
+    /// Expiration times not at exactly midnight UTC are rejected.
+    RequireMidnightUtc,
+}
+impl ExpirationPolicy {
+    fn apply(self, proposed_time: OffsetDateTime) -> Result<OffsetDateTime, ExpirationPolicyError> {
+        let is_midnight = proposed_time.time() == Time::MIDNIGHT;
+        match self {
+            Self::Any => Ok(proposed_time),
+            Self::SnapToMidnightUtc | Self::RequireMidnightUtc if is_midnight => Ok(proposed_time),
+            Self::SnapToMidnightUtc => proposed_time
+                .replace_time(Time::MIDNIGHT)
+                .checked_add(Duration::days(1))
+                .ok_or(ExpirationPolicyError::NoNextMidnightUtc),
+            Self::RequireMidnightUtc => Err(ExpirationPolicyError::NotMidnightUtc),
+        }
+    }
+}
+impl FromStr for ExpirationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Self::Any),
+            "midnight_utc" => Ok(Self::SnapToMidnightUtc),
+            "midnight_utc_strict" => Ok(Self::RequireMidnightUtc),
+            _ => Err(format!(
+                "unknown expiration policy {s}; expected any, midnight_utc, or midnight_utc_strict"
+            )),
+        }
+    }
+}
+/// How RFC3339 times that the `time` crate can't represent directly are handled:
+/// leap seconds (`23:59:60`) and the end of a day (`24:00:00`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AmbiguousTimePolicy {
+    /// Such timestamps are rejected with an error suggesting the equivalent time.
+    #[default]
+    Reject,
+    /// Such timestamps are moved forward to the start of the following second/day,
+    /// regardless of whether a leap second actually occurred then.
+    Normalize,
+}
+impl FromStr for AmbiguousTimePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "normalize" => Ok(Self::Normalize),
+            _ => Err(format!(
+                "unknown ambiguous time policy {s}; expected reject or normalize"
+            )),
+        }
+    }
+}
+/// The characters that `POST`ed short IDs are encoded with; base62 by default,
+/// but e.g. easily-confused characters like `0`/`O` and `1`/`l` can be left out.
+///
+/// NOTE: only ASCII alphanumerics are allowed, since [`ShortId::new`] rejects any others
+/// with [`ShortIdValidationError::InvalidCharacters`]. Smaller alphabets make longer IDs,
+/// which are still truncated to the maximum short ID length.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdAlphabet(Vec<u8>);
+impl IdAlphabet {
+    const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    /// Encodes `value` in this alphabet, most significant digit first.
+    fn encode(&self, mut value: u128) -> String {
+        let base = self.0.len() as u128;
+        let mut encoded = Vec::new();
+        loop {
+            let digit = usize::try_from(value % base).expect("digits are less than the base");
+            encoded.push(self.0[digit]);
+            value /= base;
+            if value == 0 {
+                break;
+            }
+        }
+        encoded.reverse();
+        // NOTE: alphabets are pure ASCII, so this can't fail
+        String::from_utf8(encoded).expect("alphabets are ASCII")
+    }
+}
+impl Default for IdAlphabet {
+    fn default() -> Self {
+        Self(Self::BASE62.to_vec())
+    }
+}
+impl FromStr for IdAlphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(invalid_char) = s.chars().find(|c| !c.is_ascii_alphanumeric()) {
+            return Err(format!(
+                "invalid ID alphabet character {invalid_char:?}; expected only ASCII alphanumerics"
+            ));
+        }
+        let mut distinct_chars = s.as_bytes().to_vec();
+        distinct_chars.sort_unstable();
+        distinct_chars.dedup();
+        if distinct_chars.len() != s.len() {
+            return Err(format!("ID alphabet {s} repeats characters"));
+        }
+        if s.len() < 2 {
+            return Err(format!("ID alphabet {s} needs at least 2 characters"));
+        }
+        Ok(Self(s.as_bytes().to_vec()))
+    }
+}
+#[derive(Debug, Error)]
+pub enum ExpirationPolicyError {
+    #[error("expiration time must be exactly midnight UTC")]
+    NotMidnightUtc,
+    #[error("expiration time has no following midnight UTC")]
+    NoNextMidnightUtc,
 }
 
 #[async_trait]
 pub trait UrlRestService: Send + Sync {
     async fn get_url(&self, id: &str) -> Result<Redirect, GetUrlError>;
+    /// Like [`UrlRestService::get_url`], but without consuming single-use URLs
+    /// or counting a hit, for existence checks.
+    async fn head_url(&self, id: &str) -> Result<Redirect, GetUrlError>;
+    async fn get_url_stats(&self, id: &str) -> Result<UrlStats, GetUrlStatsError>;
+    /// Looks up where `id` points without redirecting there (or counting a hit).
+    async fn preview_url(&self, id: &str) -> Result<ShortenedUrl, GetUrlError>;
+    /// Saves `url` under `id`, subject to the [`PutPrecondition`] in `options`
+    /// (which handlers derive from `If-None-Match`/`If-Match`), so that the precondition
+    /// is checked atomically with the save rather than by a separate lookup beforehand.
     async fn put_url(
         &self,
         id: String,
         url: &str,
-        expiration_timestamp: &str,
+        options: PutUrlOptions<'_>,
     ) -> Result<(ShortenedUrl, UrlCreationStatus), PutUrlError>;
+    /// Returns the [`ShortenedUrl`] along with whether it was newly created (or deduplicated)
+    /// and the number of short ID generation attempts needed.
     async fn post_url(
         &self,
         url: &str,
-        expiration_timestamp: &str,
-    ) -> Result<ShortenedUrl, PostUrlError>;
+        expiration_timestamp: Option<&str>,
+        owner: Option<&str>,
+    ) -> Result<(ShortenedUrl, UrlCreationStatus, usize), PostUrlError>;
+    /// Shortens each of `items` like [`UrlRestService::post_url`], returning their results
+    /// in the same order so that one bad item doesn't fail the whole batch.
+    async fn post_urls(
+        &self,
+        items: Vec<PostUrlPayload>,
+        owner: Option<&str>,
+    ) -> Result<Vec<PostUrlsItem>, PostUrlsError>;
+    async fn delete_url(&self, id: &str) -> Result<(), DeleteUrlError>;
+    /// Expires `id` right away (without deleting it), so that it can no longer be followed.
+    async fn expire_url(&self, id: &str) -> Result<(), ExpireUrlError>;
+    fn url_policy(&self) -> UrlPolicy;
+    async fn health(&self) -> Health;
+    /// Renders the sitemap XML listing the (non-expired) URLs that were marked discoverable.
+    async fn sitemap(&self) -> Result<String, SitemapError>;
+    /// Renders an SVG QR code encoding the full short URL of `id`,
+    /// as close to (without exceeding) `size` pixels wide as whole modules allow.
+    async fn qr_code(&self, id: &str, size: Option<u32>) -> Result<String, QrCodeError>;
+    /// Lists a page of every item (expired or not) ordered by short ID, for administration.
+    async fn list_urls(
+        &self,
+        limit: Option<u64>,
+        cursor: Option<&str>,
+    ) -> Result<UrlPage, ListUrlsError>;
+    /// Looks up everything stored about (non-expired) `id`, for administration.
+    async fn debug_url(&self, id: &str) -> Result<UrlDebugInfo, GetUrlError>;
+}
+
+/// The outcome of a single item of [`UrlRestService::post_urls`].
+#[derive(Debug)]
+pub enum PostUrlsItem {
+    Posted(Result<ShortenedUrl, PostUrlError>),
+    /// The item is identical to the earlier item at this index, whose result it shares
+    /// (when in-batch duplicates are collapsed).
+    DuplicateOf(usize),
 }
 
 #[derive(Debug)]
 pub enum GetUrlError {
+    NotFound,
+    /// The URL existed, but has expired.
+    Gone,
+    /// The stored URL's scheme is no longer allowed by the current scheme allowlist.
+    DisallowedScheme(String),
+    Db(anyhow::Error),
+}
+
+#[derive(Debug)]
+pub enum GetUrlStatsError {
+    NotFound,
+    Db(anyhow::Error),
+}
+
+#[derive(Debug)]
+pub enum SitemapError {
+    /// The sitemap is disabled, or there is no `BASE_URL` to resolve its locations against.
+    Disabled,
+    Db(anyhow::Error),
+}
+
+#[derive(Debug)]
+pub enum QrCodeError {
+    /// There is no `BASE_URL` for the QR code to encode the short ID against.
+    Disabled,
+    /// The URL doesn't exist or has expired.
+    NotFound,
+    Db(anyhow::Error),
+}
+
+/// The default width and height of QR codes, in pixels.
+pub const QR_CODE_DEFAULT_SIZE: u32 = 256;
+/// The largest width and height of QR codes, in pixels.
+pub const QR_CODE_MAX_SIZE: u32 = 2048;
+
+/// The default number of hash bytes that `POST`ed short IDs are derived from.
+pub const DEFAULT_HASH_BYTES: usize = 5;
+/// The most hash bytes that `POST`ed short IDs can be derived from (a `u128`).
+pub const MAX_HASH_BYTES: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum ListUrlsError {
+    #[error("invalid cursor")]
+    InvalidCursor,
+    #[error("internal/database error: {0}")]
+    Db(anyhow::Error),
+}
+
+#[derive(Debug)]
+pub enum DeleteUrlError {
+    NotFound,
+    Db(anyhow::Error),
+}
+
+#[derive(Debug)]
+pub enum ExpireUrlError {
     NotFound,
     Db(anyhow::Error),
 }
@@ -74,93 +595,924 @@ pub enum GetUrlError {
 pub enum UrlCreationStatus {
     NewlyCreated,
     AlreadyExists,
+    /// An existing item was replaced (see [`PutPrecondition::MustExist`]).
+    Replaced,
+}
+
+#[derive(Debug, Error)]
+pub enum TimestampParseError {
+    #[error("{0}")]
+    Rfc3339(#[from] time::error::Parse),
+    #[error("invalid Unix timestamp: {0}")]
+    InvalidUnixTimestamp(#[from] std::num::ParseIntError),
+    #[error("Unix timestamp out of range: {0}")]
+    UnixTimestampOutOfRange(#[from] time::error::ComponentRange),
+    #[error("unknown relative duration unit '{0}'; expected one of s, m, h, d, or w")]
+    UnknownDurationUnit(char),
+    #[error("relative duration is out of range")]
+    RelativeDurationOutOfRange,
+    #[error("expiration_timestamp must include a timezone offset, e.g. Z")]
+    MissingOffset,
+    #[error("leap seconds (:60) are not supported; use :00 of the following minute instead")]
+    LeapSecond,
+    #[error("24:00:00 is not supported; use 00:00:00 of the following day instead")]
+    EndOfDay,
+}
+
+/// Parses an expiration timestamp given in RFC3339 format,
+/// as (all-digit) Unix epoch seconds, which is handy from shell scripts (`date +%s`),
+/// or as a duration relative to now, like `12h` or `7d`.
+///
+/// Leap seconds and `24:00:00` are handled according to `ambiguous_time_policy`.
+fn parse_expiration_timestamp(
+    expiration_timestamp: &str,
+    ambiguous_time_policy: AmbiguousTimePolicy,
+) -> Result<OffsetDateTime, TimestampParseError> {
+    if is_all_digits(expiration_timestamp) {
+        let unix_timestamp = expiration_timestamp.parse()?;
+        return Ok(OffsetDateTime::from_unix_timestamp(unix_timestamp)?);
+    }
+
+    if let Some((error, representable_timestamp, offset)) =
+        split_ambiguous_time(expiration_timestamp)
+    {
+        return match ambiguous_time_policy {
+            AmbiguousTimePolicy::Reject => Err(error),
+            AmbiguousTimePolicy::Normalize => {
+                Ok(OffsetDateTime::parse(&representable_timestamp, &Rfc3339)? + offset)
+            }
+        };
+    }
+
+    OffsetDateTime::parse(expiration_timestamp, &Rfc3339).or_else(|rfc3339_err| {
+        // NOTE: only strings shaped like a relative duration (number + unit) are treated as one,
+        // so that other malformed input still reports the more helpful RFC3339 error
+        let mut chars = expiration_timestamp.chars();
+        match chars.next_back() {
+            Some(unit) if unit.is_ascii_alphabetic() && is_all_digits(chars.as_str()) => {
+                parse_relative_duration(chars.as_str(), unit)
+            }
+            // NOTE: the RFC3339 error for a missing offset is rather cryptic, so detect it here
+            _ if OffsetDateTime::parse(&format!("{expiration_timestamp}Z"), &Rfc3339).is_ok() => {
+                Err(TimestampParseError::MissingOffset)
+            }
+            _ => Err(rfc3339_err.into()),
+        }
+    })
+}
+
+/// Detects an RFC3339 leap second (`23:59:60`) or end of day (`24:00:00`),
+/// returning the matching error along with a representable timestamp and the offset
+/// that moves it to the start of the following second/day.
+///
+/// NOTE: the `time` crate would otherwise reject most of these with a cryptic error,
+/// but silently clamp the leap seconds that do exist (at the end of a month, in UTC)
+/// to `23:59:59.999999999`, which is truncated to `23:59:59` when stored.
+fn split_ambiguous_time(
+    expiration_timestamp: &str,
+) -> Option<(TimestampParseError, String, Duration)> {
+    // NOTE: RFC3339 times always sit at the same (ASCII) indices: `YYYY-MM-DDTHH:MM:SS`
+    let bytes = expiration_timestamp.as_bytes();
+    if !matches!(bytes.get(10), Some(b'T' | b't'))
+        || bytes.get(13) != Some(&b':')
+        || bytes.get(16) != Some(&b':')
+    {
+        return None;
+    }
+    let (date, time) = expiration_timestamp.split_at(11);
+    if let Some(rest) = time.strip_prefix("24:00:00") {
+        let representable = format!("{date}23:00:00{rest}");
+        return Some((TimestampParseError::EndOfDay, representable, Duration::HOUR));
+    }
+    let (hour_and_minute, second) = time.split_at(6);
+    let rest = second.strip_prefix("60")?;
+    let representable = format!("{date}{hour_and_minute}59{rest}");
+    Some((
+        TimestampParseError::LeapSecond,
+        representable,
+        Duration::SECOND,
+    ))
+}
+
+/// Strips the trailing dot from a fully-qualified host (e.g. `example.com.`),
+/// which resolves identically to its non-qualified form.
+fn strip_host_trailing_dot(mut url: Url) -> Result<Url, url::ParseError> {
+    if let Some(host) = url.host_str().and_then(|host| host.strip_suffix('.')) {
+        let host = host.to_owned();
+        url.set_host(Some(&host))?;
+    }
+    Ok(url)
+}
+
+/// Normalizes equivalent spellings of `url` into one canonical form, by lowercasing its host,
+/// stripping the trailing slash from an otherwise empty path, dropping an empty query or
+/// fragment (a bare `?` or `#`), and decoding percent-encoded unreserved characters in its path
+/// (e.g. `%7E` into `~`).
+///
+/// NOTE: [`Url::parse`] already drops default ports (and lowercases hosts) for the schemes
+/// it knows, like `http` and `https`, which also always serialize an empty path as `/`.
+fn normalize_url(mut url: Url) -> Result<Url, url::ParseError> {
+    if let Some(host) = url.host_str()
+        && host.bytes().any(|byte| byte.is_ascii_uppercase())
+    {
+        let host = host.to_ascii_lowercase();
+        url.set_host(Some(&host))?;
+    }
+    if url.path() == "/" {
+        url.set_path("");
+    }
+    if url.query() == Some("") {
+        url.set_query(None);
+    }
+    if url.fragment() == Some("") {
+        url.set_fragment(None);
+    }
+    if let Some(path) = decode_unreserved(url.path()) {
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+/// Decodes the percent-encoded unreserved characters (letters, digits, `-`, `.`, `_`, and `~`)
+/// in `path`, which mean the same either way, returning [`None`] when there are none.
+fn decode_unreserved(path: &str) -> Option<String> {
+    let decoded_byte = |escape: &[u8]| {
+        let hex = std::str::from_utf8(escape.get(1..3)?).ok()?;
+        let byte = u8::from_str_radix(hex, 16).ok()?;
+        (escape[0] == b'%' && (byte.is_ascii_alphanumeric() || b"-._~".contains(&byte)))
+            .then_some(byte)
+    };
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(byte) = decoded_byte(&bytes[i..]) {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    // NOTE: only ASCII escapes are decoded, so the path stays valid UTF-8
+    (decoded.len() < bytes.len()).then(|| String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Merges the query parameters of `utm_template` onto `url`,
+/// replacing any of the URL's own parameters with the same name.
+fn apply_utm_template(mut url: Url, utm_template: &str) -> Url {
+    let template_pairs = form_urlencoded::parse(utm_template.as_bytes())
+        .into_owned()
+        .collect::<Vec<_>>();
+    if template_pairs.is_empty() {
+        return url;
+    }
+
+    let retained_pairs = url
+        .query_pairs()
+        .into_owned()
+        .filter(|(name, _)| {
+            !template_pairs
+                .iter()
+                .any(|(template_name, _)| template_name == name)
+        })
+        .collect::<Vec<_>>();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(retained_pairs)
+        .extend_pairs(template_pairs);
+    url
+}
+
+/// Removes common tracking query parameters (`utm_*`, `fbclid`, and `gclid`) from `url`,
+/// keeping the rest of its query in order.
+fn strip_tracking_params(mut url: Url) -> Url {
+    let is_tracking_param =
+        |name: &str| name.starts_with("utm_") || matches!(name, "fbclid" | "gclid");
+    if !url.query_pairs().any(|(name, _)| is_tracking_param(&name)) {
+        return url;
+    }
+
+    let retained_pairs = url
+        .query_pairs()
+        .into_owned()
+        .filter(|(name, _)| !is_tracking_param(name))
+        .collect::<Vec<_>>();
+    if retained_pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(retained_pairs);
+    }
+    url
+}
+
+/// Deterministically derives a short ID for a POST request, so that identical requests
+/// (even on different replicas) produce the same ID when using the same `salt`.
+///
+/// Taking more `hash_bytes` (at most 16) makes for longer IDs that collide less often.
+fn candidate_short_id(
+    salt: &[u8; blake3::KEY_LEN],
+    url: &str,
+    expiration_timestamp: &str,
+    hash_bytes: usize,
+    alphabet: &IdAlphabet,
+) -> String {
+    let hash = blake3::Hasher::new_keyed(salt)
+        .update(url.as_bytes())
+        .update(expiration_timestamp.as_bytes())
+        .finalize();
+
+    let mut hash_buf = [0; MAX_HASH_BYTES];
+    hash_buf[..hash_bytes].copy_from_slice(&hash.as_bytes()[..hash_bytes]);
+    alphabet.encode(u128::from_le_bytes(hash_buf))
+}
+
+/// Escapes `unescaped` for use as XML character data or an attribute value.
+fn escape_xml(unescaped: &str) -> String {
+    let mut escaped = String::with_capacity(unescaped.len());
+    for c in unescaped.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Encodes the last short ID of a page as the (opaque) cursor for the next page.
+fn encode_cursor(short_id: &str) -> String {
+    short_id.bytes().fold(
+        String::with_capacity(short_id.len() * 2),
+        |mut cursor, byte| {
+            let _ = write!(cursor, "{byte:02x}");
+            cursor
+        },
+    )
+}
+
+fn decode_cursor(cursor: &str) -> Option<String> {
+    let bytes = cursor
+        .as_bytes()
+        .chunks(2)
+        .map(|hex_byte| {
+            let hex_byte = std::str::from_utf8(hex_byte)
+                .ok()
+                .filter(|b| b.len() == 2)?;
+            u8::from_str_radix(hex_byte, 16).ok()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn is_all_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn parse_relative_duration(
+    amount: &str,
+    unit: char,
+) -> Result<OffsetDateTime, TimestampParseError> {
+    let unit_seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(TimestampParseError::UnknownDurationUnit(unit)),
+    };
+    amount
+        .parse::<i64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(unit_seconds))
+        .and_then(|seconds| OffsetDateTime::now_utc().checked_add(Duration::seconds(seconds)))
+        .ok_or(TimestampParseError::RelativeDurationOutOfRange)
 }
 
 #[derive(Debug, Error)]
 pub enum PutUrlError {
     #[error("failed to parse timestamp: {0}")]
-    TimestampParse(#[from] time::error::Parse),
+    TimestampParse(#[from] TimestampParseError),
     #[error("invalid expiration time: {0}")]
     InvalidExpirationTime(#[from] ExpirationTimeValidationError),
+    #[error("disallowed expiration time: {0}")]
+    DisallowedExpirationTime(#[from] ExpirationPolicyError),
     #[error("invalid short ID: {0}")]
     InvalidShortId(#[from] ShortIdValidationError),
+    #[error("invalid namespace: {0}")]
+    InvalidNamespace(ShortIdValidationError),
     #[error("invalid URL: {0}")]
     InvalidUrl(#[from] url::ParseError),
+    #[error("URL scheme is not allowed: {0}")]
+    DisallowedScheme(String),
+    #[error("URL is longer than the maximum of {max} characters")]
+    UrlTooLong { max: usize },
+    #[error("URL points to a known URL shortener ({host}); please shorten the destination instead")]
+    KnownShortener { host: String },
+    #[error("disallowed URL: {0}")]
+    DisallowedUrl(#[from] DisallowedUrlError),
+    #[error("URL was already shortened within the last {cooldown_seconds} seconds")]
+    TargetCooldown { cooldown_seconds: u64 },
+    #[error("this API key may only shorten URLs to {max} distinct hosts at a time")]
+    TooManyHosts { max: usize },
+    #[error("not-before time must be before the expiration time")]
+    NotBeforeAfterExpiration,
+    #[error("max hits must be at least 1")]
+    ZeroMaxHits,
+    #[error("a single-use URL can't also have max hits")]
+    SingleUseWithMaxHits,
+    #[error("short ID must contain at least {min_distinct_chars} distinct characters")]
+    LowEntropyShortId { min_distinct_chars: usize },
+    #[error("short ID is reserved")]
+    ReservedId,
+    #[error("short ID already exists")]
+    ShortIdExists,
+    #[error("short ID does not exist")]
+    ShortIdMissing,
+    /// NOTE: short URLs have no strong `ETag`s, so only `If-Match: *` can ever match
+    #[error("only `*` is supported as an If-Match precondition")]
+    EntityTagPrecondition,
+    /// NOTE: `existing_url` is left out of the message, since it may be private
     #[error("short ID is already taken")]
-    ShortIdAlreadyTaken,
+    ShortIdAlreadyTaken { existing_url: String },
     #[error("internal/database error: {0}")]
     Internal(anyhow::Error), // NOTE: no #[from] so we have to be explicit
 }
 
+#[derive(Debug, Error)]
+pub enum PostUrlsError {
+    #[error("too many items; at most {max} may be shortened at once")]
+    TooManyItems { max: usize },
+}
+
 #[derive(Debug, Error)]
 pub enum PostUrlError {
     #[error("failed to parse timestamp: {0}")]
-    TimestampParse(#[from] time::error::Parse),
+    TimestampParse(#[from] TimestampParseError),
     #[error("invalid expiration time: {0}")]
     InvalidExpirationTime(#[from] ExpirationTimeValidationError),
+    #[error("disallowed expiration time: {0}")]
+    DisallowedExpirationTime(#[from] ExpirationPolicyError),
     #[error("invalid URL: {0}")]
     InvalidUrl(#[from] url::ParseError),
+    #[error("URL scheme is not allowed: {0}")]
+    DisallowedScheme(String),
+    #[error("URL is longer than the maximum of {max} characters")]
+    UrlTooLong { max: usize },
+    #[error("URL points to a known URL shortener ({host}); please shorten the destination instead")]
+    KnownShortener { host: String },
+    #[error("disallowed URL: {0}")]
+    DisallowedUrl(#[from] DisallowedUrlError),
+    #[error("URL was already shortened within the last {cooldown_seconds} seconds")]
+    TargetCooldown { cooldown_seconds: u64 },
+    #[error("this API key may only shorten URLs to {max} distinct hosts at a time")]
+    TooManyHosts { max: usize },
     #[error("internal/database error: {0}")]
     Internal(anyhow::Error), // NOTE: no #[from] so we have to be explicit
 }
 
 struct UrlRestServiceImpl {
     url_repo: Arc<dyn UrlRepository>,
+    options: UrlServiceOptions,
+}
+
+/// How a [`UrlRestServiceImpl`] is configured (see [`url_rest_service_capsule`]).
+struct UrlServiceOptions {
+    base_url: Option<Url>,
+    expiration: ExpirationOptions,
+    destinations: DestinationOptions,
+    normalization: NormalizationOptions,
+    short_ids: ShortIdOptions,
+    /// The maximum number of items in a single [`UrlRestService::post_urls`] call
+    max_bulk_size: usize,
+    /// Whether identical items in one [`UrlRestService::post_urls`] call are only posted once
+    collapse_bulk_duplicates: bool,
+    /// Whether the sitemap of discoverable URLs is served
+    sitemap_enabled: bool,
+    /// Whether request spans record how long validation took
+    request_timings: bool,
+    /// The most items in a single [`UrlRestService::list_urls`] page
+    max_list_limit: u64,
+}
+
+/// When URLs expire, and how their expirations are parsed, echoed, and cached.
+struct ExpirationOptions {
+    expiration_policy: ExpirationPolicy,
+    ambiguous_time_policy: AmbiguousTimePolicy,
+    /// Whether responses give expiration timestamps in the offset they were requested in,
+    /// rather than in UTC (which they're always stored in)
+    echo_expiration_offset: bool,
+    /// How long URLs live when no expiration timestamp is given
+    default_ttl: std::time::Duration,
+    /// The longest that a URL may live for
+    max_ttl: Duration,
+    /// Below how many seconds until expiration redirects must be revalidated by caches
+    min_cache_seconds: u64,
+}
+
+/// Which long URLs may be shortened (and redirected to).
+struct DestinationOptions {
+    shortener_domains: Vec<String>,
+    allowed_schemes: Vec<String>,
+    /// Whether to re-check stored URLs against `allowed_schemes` before redirecting
+    enforce_scheme_on_read: bool,
+    /// Whether URLs to loopback, link-local, or private hosts may be shortened
+    allow_private_urls: bool,
+    /// The maximum length of a (normalized) destination URL
+    max_url_length: usize,
+    /// How long after shortening a URL before it may be shortened again (if at all)
+    target_cooldown: Option<std::time::Duration>,
+    /// The most distinct hosts that a single API key's URLs may point to (if limited)
+    max_hosts_per_owner: Option<usize>,
+}
+
+/// How long URLs are normalized before they're hashed and stored.
+struct NormalizationOptions {
+    /// Whether to strip trailing dots from hosts before saving or matching them
+    normalize_trailing_dot_hosts: bool,
+    /// Whether long URLs are normalized (see [`normalize_url`]) before they're hashed and stored
+    normalize_urls: bool,
+    /// Whether tracking query parameters are removed from long URLs before they're stored
+    strip_tracking_params: bool,
+}
+
+/// Which short IDs may be chosen, and how `POST`ed ones are generated.
+struct ShortIdOptions {
+    /// The minimum number of distinct characters in a short ID (if enforced)
+    min_distinct_id_chars: Option<usize>,
+    /// Short IDs that may not be chosen
+    reserved_ids: Vec<String>,
+    /// Whether short IDs may be created under a namespace
+    namespaces_enabled: bool,
+    /// The lengths that new short IDs must fall within
+    short_id_length_bounds: ShortIdLengthBounds,
+    /// How many hash bytes `POST`ed short IDs are derived from
+    hash_bytes: usize,
+    /// How many short IDs a `POST` tries before giving up on collisions
+    post_retry_attempts: usize,
+    /// The characters `POST`ed short IDs are encoded with
+    id_alphabet: IdAlphabet,
+}
+
+impl UrlRestServiceImpl {
+    /// Applies the configured normalizations to a long URL before it's hashed or stored.
+    fn normalize_long_url(&self, mut url: Url) -> Result<Url, url::ParseError> {
+        let normalization = &self.options.normalization;
+        if normalization.normalize_trailing_dot_hosts {
+            url = strip_host_trailing_dot(url)?;
+        }
+        if normalization.normalize_urls {
+            url = normalize_url(url)?;
+        }
+        if normalization.strip_tracking_params {
+            url = strip_tracking_params(url);
+        }
+        Ok(url)
+    }
+
+    /// The (normalized, when any normalizations are configured and `url` is valid)
+    /// URL that `POST`ed short IDs are derived from.
+    fn url_to_hash(&self, url: &str) -> String {
+        // NOTE: hash the normalized URL (when valid) so that equivalent URLs dedupe too
+        let normalization = &self.options.normalization;
+        Url::parse(url)
+            .ok()
+            .filter(|_| {
+                normalization.normalize_trailing_dot_hosts
+                    || normalization.normalize_urls
+                    || normalization.strip_tracking_params
+            })
+            .and_then(|parsed_url| self.normalize_long_url(parsed_url).ok())
+            .map_or_else(|| url.to_owned(), Into::into)
+    }
+
+    /// The short ID that a `POST` attempt with `salt` derives (see [`candidate_short_id`]).
+    fn post_attempt_id(
+        &self,
+        salt: &[u8; blake3::KEY_LEN],
+        url_to_hash: &str,
+        expiration_timestamp: &str,
+    ) -> String {
+        let mut attempt_id = candidate_short_id(
+            salt,
+            url_to_hash,
+            expiration_timestamp,
+            self.options.short_ids.hash_bytes,
+            &self.options.short_ids.id_alphabet,
+        );
+        // NOTE: alphabets are pure ASCII, so truncating can't split a character
+        attempt_id.truncate(self.options.short_ids.short_id_length_bounds.max_len);
+        attempt_id
+    }
+
+    /// Retrieves the (non-expired) URL that `id` redirects to, without consuming it
+    /// or counting a hit, as long as its scheme is still allowed.
+    async fn retrieve_redirectable_url(
+        &self,
+        id: &str,
+    ) -> Result<(Box<url_repo::ShortUrl>, Option<CacheStatus>), GetUrlError> {
+        let (retrieved_url, cache_status) = self
+            .url_repo
+            .retrieve_url_cached(id)
+            .await
+            .map_err(GetUrlError::Db)?;
+        match retrieved_url {
+            RetrievedUrl::Found(url)
+                if self.options.destinations.enforce_scheme_on_read
+                    && !self
+                        .options
+                        .destinations
+                        .allowed_schemes
+                        .iter()
+                        .any(|scheme| scheme.eq_ignore_ascii_case(url.url.scheme())) =>
+            {
+                warn!(url = %url.url, "Refusing to redirect to disallowed scheme");
+                Err(GetUrlError::DisallowedScheme(url.url.scheme().to_owned()))
+            }
+            RetrievedUrl::Found(url) => Ok((url, cache_status)),
+            RetrievedUrl::Expired => Err(GetUrlError::Gone),
+            RetrievedUrl::NotFound => Err(GetUrlError::NotFound),
+        }
+    }
+
+    /// The [`Redirect`] to `url`, with its UTM template applied.
+    fn redirect_to(&self, url: url_repo::ShortUrl, cache_status: Option<CacheStatus>) -> Redirect {
+        let expiration_time = url.expiration_time.into_inner();
+        // NOTE: caching a single-use or limited redirect would let it be followed
+        // past its limit
+        let max_age_seconds = if url.single_use || url.max_hits.is_some() {
+            0
+        } else {
+            Redirect::max_age_seconds(expiration_time, url.cache_max_age_seconds)
+        };
+        // NOTE: the remaining TTL (rather than the max age, which a link may deliberately
+        // shorten) decides whether a cache could serve the redirect past expiration
+        let revalidate =
+            Redirect::seconds_until(expiration_time) < self.options.expiration.min_cache_seconds;
+        let url = match url.utm_template {
+            Some(utm_template) => apply_utm_template(url.url, &utm_template),
+            None => url.url,
+        };
+        Redirect {
+            url: url.into(),
+            max_age_seconds,
+            revalidate,
+            cache_status,
+        }
+    }
 }
 
 #[async_trait]
 impl UrlRestService for UrlRestServiceImpl {
-    #[instrument(skip(self))]
+    #[instrument(skip(self), fields(db_query_us = Empty))]
     async fn get_url(&self, id: &str) -> Result<Redirect, GetUrlError> {
+        let (url, cache_status) = self.retrieve_redirectable_url(id).await?;
+        // NOTE: consuming is atomic, so only one of any concurrent requests can redirect
+        let url = if url.single_use {
+            match self.url_repo.retrieve_and_consume(id).await {
+                Ok(RetrievedUrl::Found(url)) => url,
+                Ok(RetrievedUrl::Expired) => return Err(GetUrlError::Gone),
+                Ok(RetrievedUrl::NotFound) => return Err(GetUrlError::NotFound),
+                Err(err) => return Err(GetUrlError::Db(err)),
+            }
+        } else {
+            url
+        };
+
+        if url.max_hits.is_some() {
+            // NOTE: a limited URL's hit must be counted before redirecting,
+            // so that concurrent requests can't overshoot its limit
+            let within_limit = self
+                .url_repo
+                .increment_hits_within_limit(id)
+                .await
+                .map_err(GetUrlError::Db)?;
+            if !within_limit {
+                return Err(GetUrlError::Gone);
+            }
+        } else if !url.single_use {
+            // NOTE: counting hits shouldn't hold up (or fail) the redirect,
+            // and a consumed single-use URL no longer has a hit count to increment
+            let url_repo = Arc::clone(&self.url_repo);
+            let id = id.to_owned();
+            tokio::spawn(async move {
+                if let Err(err) = url_repo.increment_hits(&id).await {
+                    warn!(?err, id, "Failed to increment hit count");
+                }
+            });
+        }
+        Ok(self.redirect_to(*url, cache_status))
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn head_url(&self, id: &str) -> Result<Redirect, GetUrlError> {
+        let (url, cache_status) = self.retrieve_redirectable_url(id).await?;
+        Ok(self.redirect_to(*url, cache_status))
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn preview_url(&self, id: &str) -> Result<ShortenedUrl, GetUrlError> {
         match self.url_repo.retrieve_url(id).await {
-            Ok(Some(url)) => Ok(Redirect {
-                url: url.url.as_str().to_owned(),
-                max_age_seconds: (url.expiration_time.into_inner() - OffsetDateTime::now_utc())
-                    .whole_seconds()
-                    .try_into()
-                    .unwrap_or(0),
-            }),
-            Ok(None) => Err(GetUrlError::NotFound),
+            Ok(RetrievedUrl::Found(url)) => ShortenedUrl::new(*url, self.options.base_url.as_ref())
+                .context("Failed to convert ShortUrl into external format")
+                .map_err(GetUrlError::Db),
+            Ok(RetrievedUrl::Expired) => Err(GetUrlError::Gone),
+            Ok(RetrievedUrl::NotFound) => Err(GetUrlError::NotFound),
             Err(err) => Err(GetUrlError::Db(err)),
         }
     }
 
-    #[instrument(skip(self))]
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn get_url_stats(&self, id: &str) -> Result<UrlStats, GetUrlStatsError> {
+        match self.url_repo.retrieve_url_stats(id).await {
+            Ok(Some(stats))
+                if stats.expiration_time >= OffsetDateTime::now_utc() && !stats.is_exhausted() =>
+            {
+                Ok(UrlStats {
+                    shortened_url_id: id.to_owned(),
+                    long_url: stats.url.into(),
+                    expiration_timestamp: stats
+                        .expiration_time
+                        .format(&Rfc3339)
+                        .context("Failed to format expiration timestamp")
+                        .map_err(GetUrlStatsError::Db)?,
+                    hit_count: stats.hit_count,
+                    max_age_seconds: Redirect::max_age_seconds(
+                        stats.expiration_time,
+                        stats.cache_max_age_seconds,
+                    ),
+                    cache_max_age_seconds: stats.cache_max_age_seconds,
+                })
+            }
+            Ok(_) => Err(GetUrlStatsError::NotFound),
+            Err(err) => Err(GetUrlStatsError::Db(err)),
+        }
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty, validation_us = Empty))]
     async fn put_url(
         &self,
         id: String,
         long_url: &str,
-        expiration_timestamp: &str,
+        PutUrlOptions {
+            expiration_timestamp,
+            utm_template,
+            discoverable,
+            cache_max_age_seconds,
+            single_use,
+            max_hits,
+            not_before_timestamp,
+            precondition,
+            owner,
+        }: PutUrlOptions<'_>,
     ) -> Result<(ShortenedUrl, UrlCreationStatus), PutUrlError> {
-        let expiration_time =
-            OffsetDateTime::parse(expiration_timestamp, &Rfc3339)?.to_offset(time::UtcOffset::UTC);
+        let validation_start = std::time::Instant::now();
+        // NOTE: consuming a single-use URL deletes it, leaving no hit count to limit
+        match max_hits {
+            Some(0) => return Err(PutUrlError::ZeroMaxHits),
+            Some(_) if single_use => return Err(PutUrlError::SingleUseWithMaxHits),
+            _ => {}
+        }
+        let (expiration_time, requested_offset) = match expiration_timestamp {
+            Some(expiration_timestamp) => {
+                let expiration_time = parse_expiration_timestamp(
+                    expiration_timestamp,
+                    self.options.expiration.ambiguous_time_policy,
+                )?;
+                (
+                    expiration_time.to_offset(UtcOffset::UTC),
+                    expiration_time.offset(),
+                )
+            }
+            None => (
+                OffsetDateTime::now_utc() + self.options.expiration.default_ttl,
+                UtcOffset::UTC,
+            ),
+        };
+        // NOTE: expiration times are always stored in UTC, and only echoed back
+        // in the offset they were given in when configured
+        let response_offset = if self.options.expiration.echo_expiration_offset {
+            requested_offset
+        } else {
+            UtcOffset::UTC
+        };
+        let expiration_time = self
+            .options
+            .expiration
+            .expiration_policy
+            .apply(expiration_time)?;
+        let not_before = not_before_timestamp
+            .map(|not_before_timestamp| {
+                parse_expiration_timestamp(
+                    not_before_timestamp,
+                    self.options.expiration.ambiguous_time_policy,
+                )
+            })
+            .transpose()?
+            .map(|not_before| not_before.to_offset(UtcOffset::UTC));
+        if not_before.is_some_and(|not_before| not_before >= expiration_time) {
+            return Err(PutUrlError::NotBeforeAfterExpiration);
+        }
 
-        let to_save = url_repo::ShortUrl {
-            short_id: ShortId::new(id)?,
-            url: Url::parse(long_url)?,
-            expiration_time: ExpirationTime::new(expiration_time)?,
+        // NOTE: a namespaced ID is stored with its namespace as a prefix,
+        // so that it only needs to be unique within that namespace
+        let (namespace, id) = match id.split_once(NAMESPACE_SEPARATOR) {
+            Some((namespace, id)) if self.options.short_ids.namespaces_enabled => {
+                (Some(namespace.to_owned()), id.to_owned())
+            }
+            _ => (None, id),
         };
 
-        match self.url_repo.save_url(to_save.clone()).await {
-            Ok(short_url) => Ok((
-                short_url
-                    .try_into()
-                    .context("Failed to convert new ShortUrl into external format")
-                    .map_err(PutUrlError::Internal)?,
-                UrlCreationStatus::NewlyCreated,
-            )),
-            Err(SaveUrlError::ItemAlreadyExists(existing_short_url))
-                if to_save == *existing_short_url =>
+        if let Some(min_distinct_chars) = self.options.short_ids.min_distinct_id_chars {
+            let distinct_chars = id.chars().collect::<HashSet<_>>().len();
+            if distinct_chars < min_distinct_chars {
+                return Err(PutUrlError::LowEntropyShortId { min_distinct_chars });
+            }
+        }
+
+        let short_id = ShortId::new(id, self.options.short_ids.short_id_length_bounds)?;
+        let short_id = match namespace {
+            // NOTE: within a namespace, only `stats` and `qr` collide with routes
+            // (`/{id}/stats` and `/{id}/qr`), and the `admin` namespace is kept
+            // for administrative routes (`/admin/urls`)
+            Some(namespace)
+                if matches!(short_id.as_str(), "stats" | "qr") || namespace == "admin" =>
+            {
+                return Err(PutUrlError::ReservedId);
+            }
+            Some(namespace) => {
+                let namespace_bounds = ShortIdLengthBounds {
+                    min_len: 1,
+                    ..self.options.short_ids.short_id_length_bounds
+                };
+                let namespace = ShortId::new(namespace, namespace_bounds)
+                    .map_err(PutUrlError::InvalidNamespace)?;
+                short_id.in_namespace(&namespace)
+            }
+            None if self
+                .options
+                .short_ids
+                .reserved_ids
+                .iter()
+                .any(|reserved_id| reserved_id == short_id.as_str()) =>
+            {
+                return Err(PutUrlError::ReservedId);
+            }
+            None => short_id,
+        };
+        let url = self.normalize_long_url(Url::parse(long_url)?)?;
+        if url.as_str().len() > self.options.destinations.max_url_length {
+            return Err(PutUrlError::UrlTooLong {
+                max: self.options.destinations.max_url_length,
+            });
+        }
+        if !self
+            .options
+            .destinations
+            .allowed_schemes
+            .iter()
+            .any(|scheme| scheme.eq_ignore_ascii_case(url.scheme()))
+        {
+            return Err(PutUrlError::DisallowedScheme(url.scheme().to_owned()));
+        }
+        if !self.options.destinations.allow_private_urls {
+            check_public_destination(&url)?;
+        }
+        if let Some(host) = url.host_str()
+            && self
+                .options
+                .destinations
+                .shortener_domains
+                .iter()
+                .any(|domain| host_matches_domain(host, domain))
+        {
+            return Err(PutUrlError::KnownShortener {
+                host: host.to_owned(),
+            });
+        }
+
+        let to_save = url_repo::ShortUrl {
+            short_id,
+            url,
+            expiration_time: ExpirationTime::new(expiration_time, self.options.expiration.max_ttl)?,
+            original_url: Some(long_url.to_owned()),
+            utm_template: utm_template
+                .map(|utm_template| utm_template.trim_start_matches('?'))
+                .filter(|utm_template| !utm_template.is_empty())
+                .map(str::to_owned),
+            discoverable,
+            cache_max_age_seconds,
+            single_use,
+            max_hits,
+            not_before,
+            owner: owner.map(str::to_owned),
+        };
+        if self.options.request_timings {
+            record_elapsed_us(&Span::current(), "validation_us", validation_start);
+        }
+
+        // NOTE: without a requested expiration, the default TTL is resolved anew on every
+        // request, so an existing item's expiration time is just as good
+        let is_equivalent = |existing: &url_repo::ShortUrl| {
+            if expiration_timestamp.is_some() {
+                to_save.is_equivalent_to(existing)
+            } else {
+                to_save.is_equivalent_apart_from_expiration(existing)
+            }
+        };
+
+        // NOTE: re-saving an equivalent item is exempt so that retries remain idempotent
+        if let Some(target_cooldown) = self.options.destinations.target_cooldown
+            && let Some(recent) = self
+                .url_repo
+                .find_recently_created(
+                    to_save.url.as_str(),
+                    OffsetDateTime::now_utc() - target_cooldown,
+                )
+                .await
+                .map_err(PutUrlError::Internal)?
+            && !is_equivalent(&recent)
+        {
+            return Err(PutUrlError::TargetCooldown {
+                cooldown_seconds: target_cooldown.as_secs(),
+            });
+        }
+
+        // NOTE: this isn't atomic with the save, so concurrent creates can overshoot the cap
+        if let Some(max_hosts) = self.options.destinations.max_hosts_per_owner
+            && let Some(owner) = owner
+        {
+            let owner_hosts = self
+                .url_repo
+                .find_owner_hosts(owner)
+                .await
+                .map_err(PutUrlError::Internal)?;
+            let host = to_save.url.host_str().unwrap_or_default();
+            if owner_hosts.len() >= max_hosts && !owner_hosts.contains(host) {
+                return Err(PutUrlError::TooManyHosts { max: max_hosts });
+            }
+        }
+
+        if precondition == PutPrecondition::MustExist {
+            let replaced = self
+                .url_repo
+                .replace_url(to_save.clone())
+                .await
+                .map_err(PutUrlError::Internal)?;
+            if !replaced {
+                return Err(PutUrlError::ShortIdMissing);
+            }
+            return Ok((
+                ShortenedUrl::with_expiration_offset(
+                    to_save,
+                    self.options.base_url.as_ref(),
+                    response_offset,
+                )
+                .context("Failed to convert replaced ShortUrl into external format")
+                .map_err(PutUrlError::Internal)?,
+                UrlCreationStatus::Replaced,
+            ));
+        }
+
+        match self.url_repo.save_url(to_save.clone()).await {
+            Err(SaveUrlError::ItemAlreadyExists(_))
+                if precondition == PutPrecondition::CreateOnly =>
+            {
+                Err(PutUrlError::ShortIdExists)
+            }
+            Ok(short_url) => Ok((
+                ShortenedUrl::with_expiration_offset(
+                    short_url,
+                    self.options.base_url.as_ref(),
+                    response_offset,
+                )
+                .context("Failed to convert new ShortUrl into external format")
+                .map_err(PutUrlError::Internal)?,
+                UrlCreationStatus::NewlyCreated,
+            )),
+            Err(SaveUrlError::ItemAlreadyExists(existing_short_url))
+                if is_equivalent(&existing_short_url) =>
             {
                 Ok((
-                    (*existing_short_url)
-                        .try_into()
-                        .context("Failed to convert existing ShortUrl into external format")
-                        .map_err(PutUrlError::Internal)?,
+                    ShortenedUrl::with_expiration_offset(
+                        *existing_short_url,
+                        self.options.base_url.as_ref(),
+                        response_offset,
+                    )
+                    .context("Failed to convert existing ShortUrl into external format")
+                    .map_err(PutUrlError::Internal)?,
                     UrlCreationStatus::AlreadyExists,
                 ))
             }
-            Err(SaveUrlError::ItemAlreadyExists(_)) => Err(PutUrlError::ShortIdAlreadyTaken),
+            Err(SaveUrlError::ItemAlreadyExists(existing_short_url)) => {
+                Err(PutUrlError::ShortIdAlreadyTaken {
+                    existing_url: existing_short_url.url.into(),
+                })
+            }
             Err(SaveUrlError::Internal(internal_err)) => Err(PutUrlError::Internal(internal_err)),
         }
     }
@@ -169,41 +1521,83 @@ impl UrlRestService for UrlRestServiceImpl {
     async fn post_url(
         &self,
         url: &str,
-        expiration_timestamp: &str,
-    ) -> Result<ShortenedUrl, PostUrlError> {
-        const PUT_ATTEMPTS: usize = 3;
-        const BYTES_TO_TAKE: usize = 5;
+        expiration_timestamp: Option<&str>,
+        owner: Option<&str>,
+    ) -> Result<(ShortenedUrl, UrlCreationStatus, usize), PostUrlError> {
+        let url_to_hash = self.url_to_hash(url);
 
         // NOTE: start with zeroed salt so we can hopefully dedupe
         // if the user made the same POST request before
         let mut salt = [0; blake3::KEY_LEN];
 
-        for _ in 0..PUT_ATTEMPTS {
-            let hash = blake3::Hasher::new_keyed(&salt)
-                .update(url.as_bytes())
-                .update(expiration_timestamp.as_bytes())
-                .finalize();
-
-            let mut base62_buf = [0; 16];
-            base62_buf[..BYTES_TO_TAKE].copy_from_slice(&hash.as_bytes()[..BYTES_TO_TAKE]);
-            let attempt_id = base62::encode(u128::from_le_bytes(base62_buf));
+        for attempt in 1..=self.options.short_ids.post_retry_attempts {
+            // NOTE: a missing expiration is hashed as is (rather than as the default TTL,
+            // which changes every request), so that repeated requests dedupe
+            let attempt_id = self.post_attempt_id(
+                &salt,
+                &url_to_hash,
+                expiration_timestamp.unwrap_or_default(),
+            );
 
             // NOTE: we defer our url creation logic to a PUT request with the attempt_id
             match self
-                .put_url(attempt_id.clone(), url, expiration_timestamp)
+                .put_url(
+                    attempt_id.clone(),
+                    url,
+                    PutUrlOptions {
+                        expiration_timestamp,
+                        owner,
+                        ..PutUrlOptions::default()
+                    },
+                )
                 .await
             {
-                Ok((shortened_url, _)) => return Ok(shortened_url),
+                Ok((shortened_url, creation_status)) => {
+                    return Ok((shortened_url, creation_status, attempt));
+                }
                 // NOTE: these are unrecoverable errors; early return to prevent retries
                 Err(PutUrlError::InvalidUrl(inner)) => {
                     return Err(PostUrlError::InvalidUrl(inner));
                 }
+                Err(PutUrlError::DisallowedScheme(scheme)) => {
+                    return Err(PostUrlError::DisallowedScheme(scheme));
+                }
+                Err(PutUrlError::UrlTooLong { max }) => {
+                    return Err(PostUrlError::UrlTooLong { max });
+                }
+                Err(PutUrlError::KnownShortener { host }) => {
+                    return Err(PostUrlError::KnownShortener { host });
+                }
+                Err(PutUrlError::DisallowedUrl(inner)) => {
+                    return Err(PostUrlError::DisallowedUrl(inner));
+                }
+                Err(PutUrlError::TargetCooldown { cooldown_seconds }) => {
+                    return Err(PostUrlError::TargetCooldown { cooldown_seconds });
+                }
+                Err(PutUrlError::TooManyHosts { max }) => {
+                    return Err(PostUrlError::TooManyHosts { max });
+                }
                 Err(PutUrlError::TimestampParse(inner)) => {
                     return Err(PostUrlError::TimestampParse(inner));
                 }
                 Err(PutUrlError::InvalidExpirationTime(inner)) => {
                     return Err(PostUrlError::InvalidExpirationTime(inner));
                 }
+                Err(PutUrlError::DisallowedExpirationTime(inner)) => {
+                    return Err(PostUrlError::DisallowedExpirationTime(inner));
+                }
+                // NOTE: POSTed URLs have no not-before time, hit limit, or precondition,
+                // so these should never happen
+                Err(
+                    err @ (PutUrlError::NotBeforeAfterExpiration
+                    | PutUrlError::ZeroMaxHits
+                    | PutUrlError::SingleUseWithMaxHits
+                    | PutUrlError::ShortIdExists
+                    | PutUrlError::ShortIdMissing
+                    | PutUrlError::EntityTagPrecondition),
+                ) => {
+                    return Err(PostUrlError::Internal(err.into()));
+                }
                 Err(PutUrlError::Internal(err)) => {
                     error!(?err, "Encountered internal error in delegated PUT call");
                     return Err(PostUrlError::Internal(
@@ -211,13 +1605,22 @@ impl UrlRestService for UrlRestServiceImpl {
                     ));
                 }
                 // NOTE: these are retryable errors; continue on
-                Err(PutUrlError::InvalidShortId(err)) => {
+                Err(PutUrlError::InvalidShortId(err) | PutUrlError::InvalidNamespace(err)) => {
                     // NOTE: this can be caused by:
                     // - A bug, in which we are not generating ShortIds of proper length
                     // - In _very_ rare scenarios when a lot of the trailing hashed bits are 0
                     warn!(?attempt_id, ?err, "Generated invalid ShortId");
                 }
-                Err(PutUrlError::ShortIdAlreadyTaken) => {
+                Err(PutUrlError::LowEntropyShortId { .. }) => {
+                    warn!(
+                        ?attempt_id,
+                        "Generated ShortId with too few distinct characters"
+                    );
+                }
+                Err(PutUrlError::ReservedId) => {
+                    warn!(?attempt_id, "Generated ShortId that is reserved");
+                }
+                Err(PutUrlError::ShortIdAlreadyTaken { .. }) => {
                     warn!(?attempt_id, "Generated ShortId that was already taken");
                 }
             }
@@ -227,23 +1630,315 @@ impl UrlRestService for UrlRestServiceImpl {
 
         Err(PostUrlError::Internal(anyhow!("Exhausted retry attempts")))
     }
+
+    #[instrument(skip(self, items), fields(num_items = items.len()))]
+    async fn post_urls(
+        &self,
+        items: Vec<PostUrlPayload>,
+        owner: Option<&str>,
+    ) -> Result<Vec<PostUrlsItem>, PostUrlsError> {
+        const CONCURRENCY: usize = 16;
+
+        if items.len() > self.options.max_bulk_size {
+            return Err(PostUrlsError::TooManyItems {
+                max: self.options.max_bulk_size,
+            });
+        }
+
+        // NOTE: the index of the first item identical to each item (its own, when not collapsing)
+        let mut first_indices_by_item = HashMap::new();
+        let first_indices = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                if self.options.collapse_bulk_duplicates {
+                    *first_indices_by_item
+                        .entry((item.url.as_str(), item.expiration_timestamp.as_deref()))
+                        .or_insert(index)
+                } else {
+                    index
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let unique_items = items
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| first_indices[*index] == *index);
+        let mut results = stream::iter(unique_items)
+            .map(|(index, item)| async move {
+                let result = self
+                    .post_url(&item.url, item.expiration_timestamp.as_deref(), owner)
+                    .await
+                    .map(|(shortened_url, _, _)| shortened_url);
+                (index, PostUrlsItem::Posted(result))
+            })
+            .buffer_unordered(CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        results.extend(
+            first_indices
+                .into_iter()
+                .enumerate()
+                .filter(|(index, first_index)| index != first_index)
+                .map(|(index, first_index)| (index, PostUrlsItem::DuplicateOf(first_index))),
+        );
+        results.sort_unstable_by_key(|(index, _)| *index);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn delete_url(&self, id: &str) -> Result<(), DeleteUrlError> {
+        match self.url_repo.delete_url(id).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(DeleteUrlError::NotFound),
+            Err(err) => Err(DeleteUrlError::Db(err)),
+        }
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn expire_url(&self, id: &str) -> Result<(), ExpireUrlError> {
+        match self.url_repo.expire_url(id).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ExpireUrlError::NotFound),
+            Err(err) => Err(ExpireUrlError::Db(err)),
+        }
+    }
+
+    fn url_policy(&self) -> UrlPolicy {
+        UrlPolicy {
+            allowed_schemes: self.options.destinations.allowed_schemes.clone(),
+            shortener_blocklist_active: !self.options.destinations.shortener_domains.is_empty(),
+        }
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn health(&self) -> Health {
+        let status = match self.url_repo.ping().await {
+            Ok(()) => HealthStatus::Ok,
+            Err(err) => {
+                error!(?err, "Health check failed to reach the database");
+                HealthStatus::DbUnreachable
+            }
+        };
+        Health { status }
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn sitemap(&self) -> Result<String, SitemapError> {
+        // NOTE: the most URLs that the sitemap protocol allows in a single file
+        const MAX_SITEMAP_URLS: u64 = 50_000;
+
+        let Some(base_url) = self
+            .options
+            .base_url
+            .as_ref()
+            .filter(|_| self.options.sitemap_enabled)
+        else {
+            return Err(SitemapError::Disabled);
+        };
+
+        let short_urls = self
+            .url_repo
+            .find_discoverable(MAX_SITEMAP_URLS)
+            .await
+            .map_err(SitemapError::Db)?;
+        let url_entries = short_urls
+            .into_iter()
+            .map(|short_url| {
+                let ShortenedUrl { short_url, .. } = ShortenedUrl::new(short_url, Some(base_url))
+                    .context("Failed to convert discoverable ShortUrl into external format")
+                    .map_err(SitemapError::Db)?;
+                Ok(format!(
+                    "  <url><loc>{}</loc></url>\n",
+                    escape_xml(&short_url)
+                ))
+            })
+            .collect::<Result<String, _>>()?;
+        Ok(format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n",
+                r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+                "\n{}</urlset>\n",
+            ),
+            url_entries
+        ))
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn qr_code(&self, id: &str, size: Option<u32>) -> Result<String, QrCodeError> {
+        let Some(base_url) = self.options.base_url.as_ref() else {
+            return Err(QrCodeError::Disabled);
+        };
+
+        // NOTE: unlike get_url, rendering a QR code doesn't count as a hit
+        let short_url = match self.url_repo.retrieve_url(id).await {
+            Ok(RetrievedUrl::Found(short_url)) => *short_url,
+            Ok(RetrievedUrl::Expired | RetrievedUrl::NotFound) => {
+                return Err(QrCodeError::NotFound);
+            }
+            Err(err) => return Err(QrCodeError::Db(err)),
+        };
+        let ShortenedUrl { short_url, .. } = ShortenedUrl::new(short_url, Some(base_url))
+            .context("Failed to convert ShortUrl into external format")
+            .map_err(QrCodeError::Db)?;
+
+        let size = size.unwrap_or(QR_CODE_DEFAULT_SIZE).min(QR_CODE_MAX_SIZE);
+        let qr_code = qrcode::QrCode::new(short_url.as_bytes())
+            .context("Failed to encode short URL as a QR code")
+            .map_err(QrCodeError::Db)?;
+        Ok(qr_code
+            .render::<qrcode::render::svg::Color>()
+            .min_dimensions(size, size)
+            .max_dimensions(size, size)
+            .build())
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn list_urls(
+        &self,
+        limit: Option<u64>,
+        cursor: Option<&str>,
+    ) -> Result<UrlPage, ListUrlsError> {
+        let limit = limit.map_or(self.options.max_list_limit, |limit| {
+            limit.clamp(1, self.options.max_list_limit)
+        });
+        let after = cursor
+            .map(|cursor| decode_cursor(cursor).ok_or(ListUrlsError::InvalidCursor))
+            .transpose()?;
+
+        // NOTE: fetch one extra item to find out whether there is a next page
+        let mut short_urls = self
+            .url_repo
+            .list_urls(limit + 1, after)
+            .await
+            .map_err(ListUrlsError::Db)?;
+        let has_next_page = short_urls.len() as u64 > limit;
+        short_urls.truncate(limit.try_into().unwrap_or(usize::MAX));
+        let next_cursor = short_urls
+            .last()
+            .filter(|_| has_next_page)
+            .map(|short_url| encode_cursor(short_url.short_id.as_str()));
+
+        let items = short_urls
+            .into_iter()
+            .map(|short_url| {
+                let original_url = short_url.original_url.clone();
+                let shortened_url = ShortenedUrl::new(short_url, self.options.base_url.as_ref())?;
+                Ok(ListedUrl {
+                    shortened_url,
+                    original_url,
+                })
+            })
+            .collect::<anyhow::Result<_>>()
+            .context("Failed to convert listed ShortUrls into external format")
+            .map_err(ListUrlsError::Db)?;
+        Ok(UrlPage { items, next_cursor })
+    }
+
+    #[instrument(skip(self), fields(db_query_us = Empty))]
+    async fn debug_url(&self, id: &str) -> Result<UrlDebugInfo, GetUrlError> {
+        let short_url = match self.url_repo.retrieve_url(id).await {
+            Ok(RetrievedUrl::Found(short_url)) => *short_url,
+            Ok(RetrievedUrl::Expired) => return Err(GetUrlError::Gone),
+            Ok(RetrievedUrl::NotFound) => return Err(GetUrlError::NotFound),
+            Err(err) => return Err(GetUrlError::Db(err)),
+        };
+
+        // NOTE: a POST hashes its URL as submitted and its expiration timestamp as given
+        // (or none), so try both of the ways the expiration could've been given
+        let url_to_hash = self.url_to_hash(
+            short_url
+                .original_url
+                .as_deref()
+                .unwrap_or(short_url.url.as_str()),
+        );
+        let expiration_timestamp = short_url
+            .expiration_time
+            .clone()
+            .into_inner()
+            .format(&Rfc3339)
+            .context("Failed to format expiration timestamp")
+            .map_err(GetUrlError::Db)?;
+        let zeroed_salt = [0; blake3::KEY_LEN];
+        let generated_id = ["", expiration_timestamp.as_str()]
+            .into_iter()
+            .any(|expiration| self.post_attempt_id(&zeroed_salt, &url_to_hash, expiration) == id);
+
+        let not_before_timestamp = short_url
+            .not_before
+            .map(|not_before| not_before.format(&Rfc3339))
+            .transpose()
+            .context("Failed to format not-before timestamp")
+            .map_err(GetUrlError::Db)?;
+        let utm_template = short_url.utm_template.clone();
+        let discoverable = short_url.discoverable;
+        let cache_max_age_seconds = short_url.cache_max_age_seconds;
+        let single_use = short_url.single_use;
+        let max_hits = short_url.max_hits;
+        let owner = short_url.owner.clone();
+        let original_url = short_url.original_url.clone();
+        let shortened_url = ShortenedUrl::new(short_url, self.options.base_url.as_ref())
+            .context("Failed to convert ShortUrl into external format")
+            .map_err(GetUrlError::Db)?;
+        Ok(UrlDebugInfo {
+            listed_url: ListedUrl {
+                shortened_url,
+                original_url,
+            },
+            utm_template,
+            discoverable,
+            cache_max_age_seconds,
+            single_use,
+            max_hits,
+            not_before_timestamp,
+            owner,
+            generated_id,
+        })
+    }
 }
 
-impl TryFrom<url_repo::ShortUrl> for ShortenedUrl {
-    type Error = anyhow::Error;
+impl ShortenedUrl {
+    /// Converts a [`url_repo::ShortUrl`] into its external format,
+    /// resolving its full short URL against `base_url` (when configured).
+    fn new(short_url: url_repo::ShortUrl, base_url: Option<&Url>) -> anyhow::Result<Self> {
+        Self::with_expiration_offset(short_url, base_url, UtcOffset::UTC)
+    }
 
-    fn try_from(
+    /// Like [`ShortenedUrl::new`], but formats the expiration timestamp in `expiration_offset`.
+    fn with_expiration_offset(
         url_repo::ShortUrl {
             short_id,
             url,
             expiration_time,
+            original_url: _,
+            utm_template: _,
+            discoverable: _,
+            cache_max_age_seconds: _,
+            single_use: _,
+            max_hits: _,
+            not_before: _,
+            owner: _,
         }: url_repo::ShortUrl,
-    ) -> Result<Self, Self::Error> {
+        base_url: Option<&Url>,
+        expiration_offset: UtcOffset,
+    ) -> anyhow::Result<Self> {
+        let shortened_url_id = short_id.into_inner();
+        let short_url = match base_url {
+            Some(base_url) => base_url
+                .join(&shortened_url_id)
+                .context("Failed to join short ID onto base URL")?
+                .into(),
+            None => shortened_url_id.clone(),
+        };
         Ok(Self {
-            shortened_url_id: short_id.into_inner(),
+            shortened_url_id,
+            short_url,
             long_url: url.into(),
             expiration_timestamp: expiration_time
                 .into_inner()
+                .to_offset(expiration_offset)
                 .format(&Rfc3339)
                 .context("Failed to format expiration timestamp")?,
         })
@@ -253,29 +1948,53 @@ impl TryFrom<url_repo::ShortUrl> for ShortenedUrl {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
-    use mockall::{mock, predicate::*};
-    use time::Duration;
-
-    use crate::url_repo::ShortUrl;
+    use crate::url_repo::{MockUrlRepository, ShortUrl, in_memory::InMemoryUrlRepository};
+    use mockall::predicate::*;
 
     use super::*;
 
-    mock! {
-        UrlRepository {}
-
-        #[async_trait]
-        impl UrlRepository for UrlRepository {
-            async fn retrieve_url(&self, id: &str) -> anyhow::Result<Option<url_repo::ShortUrl>>;
-            async fn save_url(&self, url: url_repo::ShortUrl) -> Result<url_repo::ShortUrl, SaveUrlError>;
-            async fn delete_expired_urls(&self) -> anyhow::Result<()>;
-        }
-    }
-
-    fn new_short_url(id: &str, url_str: &str, expires_in: Duration) -> url_repo::ShortUrl {
-        url_repo::ShortUrl {
-            short_id: ShortId::new(id.to_owned()).unwrap(),
-            url: Url::parse(url_str).unwrap(),
-            expiration_time: ExpirationTime::new(OffsetDateTime::now_utc() + expires_in).unwrap(),
+    fn new_service(mock_repo: MockUrlRepository) -> UrlRestServiceImpl {
+        UrlRestServiceImpl {
+            url_repo: Arc::new(mock_repo),
+            options: UrlServiceOptions {
+                base_url: None,
+                expiration: ExpirationOptions {
+                    expiration_policy: ExpirationPolicy::default(),
+                    ambiguous_time_policy: AmbiguousTimePolicy::default(),
+                    echo_expiration_offset: false,
+                    default_ttl: std::time::Duration::from_hours(24),
+                    max_ttl: ExpirationTime::DEFAULT_MAX_TTL,
+                    min_cache_seconds: 0,
+                },
+                destinations: DestinationOptions {
+                    shortener_domains: Vec::new(),
+                    allowed_schemes: vec!["http".to_owned(), "https".to_owned()],
+                    enforce_scheme_on_read: false,
+                    allow_private_urls: false,
+                    max_url_length: 2048,
+                    target_cooldown: None,
+                    max_hosts_per_owner: None,
+                },
+                normalization: NormalizationOptions {
+                    normalize_trailing_dot_hosts: false,
+                    normalize_urls: false,
+                    strip_tracking_params: false,
+                },
+                short_ids: ShortIdOptions {
+                    min_distinct_id_chars: None,
+                    reserved_ids: vec!["health".to_owned(), "policy".to_owned()],
+                    namespaces_enabled: false,
+                    short_id_length_bounds: ShortIdLengthBounds::default(),
+                    hash_bytes: DEFAULT_HASH_BYTES,
+                    post_retry_attempts: 3,
+                    id_alphabet: IdAlphabet::default(),
+                },
+                max_bulk_size: 10,
+                collapse_bulk_duplicates: false,
+                sitemap_enabled: false,
+                request_timings: false,
+                max_list_limit: 3,
+            },
         }
     }
 
@@ -284,18 +2003,18 @@ mod tests {
         let mut mock_repo = MockUrlRepository::new();
         let short_id = "testurl123";
         let long_url = "https://example.com/long";
-        let expected_short_url = new_short_url("testurl", long_url, Duration::days(1));
+        let expected_short_url =
+            url_repo::ShortUrl::new_for_test("testurl", long_url, Duration::days(1));
 
-        let mock_return_value = Ok(Some(expected_short_url.clone()));
+        let mock_return_value = Ok(RetrievedUrl::Found(Box::new(expected_short_url.clone())));
         mock_repo
             .expect_retrieve_url()
             .with(eq(short_id))
             .once()
             .return_once(move |_| mock_return_value);
+        mock_repo.expect_increment_hits().returning(|_| Ok(()));
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let result = service.get_url(short_id).await.unwrap();
         assert_eq!(result.url, long_url);
         assert!(
@@ -304,6 +2023,138 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_get_url_applies_utm_template() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl {
+            utm_template: Some("utm_source=newsletter&utm_medium=email".to_owned()),
+            ..url_repo::ShortUrl::new_for_test(
+                "testurl",
+                "https://example.com/page?ref=home&utm_source=old",
+                Duration::days(1),
+            )
+        };
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+        mock_repo.expect_increment_hits().returning(|_| Ok(()));
+
+        let service = new_service(mock_repo);
+        let result = service.get_url("testurl").await.unwrap();
+        assert_eq!(
+            result.url,
+            "https://example.com/page?ref=home&utm_source=newsletter&utm_medium=email"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_url_applies_utm_template_without_existing_query() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl {
+            utm_template: Some("utm_campaign=launch".to_owned()),
+            ..url_repo::ShortUrl::new_for_test("testurl", "https://example.com/", Duration::days(1))
+        };
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+        mock_repo.expect_increment_hits().returning(|_| Ok(()));
+
+        let service = new_service(mock_repo);
+        let result = service.get_url("testurl").await.unwrap();
+        assert_eq!(result.url, "https://example.com/?utm_campaign=launch");
+    }
+
+    #[tokio::test]
+    async fn test_get_url_increments_hits() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url =
+            url_repo::ShortUrl::new_for_test("testurl", "https://example.com/", Duration::days(1));
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+        let hit_incremented = Arc::new(tokio::sync::Notify::new());
+        let notify_hit_incremented = Arc::clone(&hit_incremented);
+        mock_repo
+            .expect_increment_hits()
+            .with(eq("testurl"))
+            .once()
+            .returning(move |_| {
+                notify_hit_incremented.notify_one();
+                Err(anyhow::anyhow!("test error"))
+            });
+
+        let service = new_service(mock_repo);
+        // NOTE: a failure to count the hit must not fail the redirect
+        assert!(service.get_url("testurl").await.is_ok());
+
+        // NOTE: the hit is counted in a spawned task, so wait for it rather than racing it
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            hit_incremented.notified(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_url_cache_control_matches_ttl() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url =
+            url_repo::ShortUrl::new_for_test("testurl", "https://example.com/", Duration::hours(1));
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+
+        mock_repo.expect_increment_hits().returning(|_| Ok(()));
+
+        let service = new_service(mock_repo);
+        let cache_control = service.get_url("testurl").await.unwrap().cache_control();
+        let max_age: u64 = cache_control
+            .strip_prefix("public, max-age=")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(
+            // NOTE: slight tolerance is allowed in case of slow tests
+            (3595..=3600).contains(&max_age)
+        );
+    }
+
+    #[test]
+    fn test_redirect_cache_control_no_store_when_expiring() {
+        let redirect = Redirect {
+            url: "https://example.com/".to_owned(),
+            max_age_seconds: 0,
+            revalidate: true,
+            cache_status: None,
+        };
+        assert_eq!(redirect.cache_control(), "no-store");
+    }
+
+    #[tokio::test]
+    async fn test_get_url_no_cache_below_min_cache_seconds() {
+        // NOTE: the extra half second keeps exactly 3600 whole seconds left while the test runs
+        let expires_in = Duration::hours(1) + Duration::milliseconds(500);
+        for (min_cache_seconds, expected) in [(3600, "public, max-age=3600"), (3601, "no-cache")] {
+            let mut mock_repo = MockUrlRepository::new();
+            let short_url =
+                url_repo::ShortUrl::new_for_test("testurl", "https://example.com/", expires_in);
+            mock_repo
+                .expect_retrieve_url()
+                .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+            mock_repo.expect_increment_hits().returning(|_| Ok(()));
+            let mut service = new_service(mock_repo);
+            service.options.expiration.min_cache_seconds = min_cache_seconds;
+
+            let redirect = service.get_url("testurl").await.unwrap();
+            assert_eq!(redirect.cache_control(), expected, "{min_cache_seconds}");
+        }
+    }
+
     #[tokio::test]
     async fn test_get_url_not_found() {
         let mut mock_repo = MockUrlRepository::new();
@@ -313,15 +2164,27 @@ mod tests {
             .expect_retrieve_url()
             .with(eq(short_id))
             .once()
-            .return_once(|_| Ok(None));
+            .return_once(|_| Ok(RetrievedUrl::NotFound));
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let get_url_err = service.get_url(short_id).await.unwrap_err();
         assert!(matches!(get_url_err, GetUrlError::NotFound));
     }
 
+    #[tokio::test]
+    async fn test_get_url_expired() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_retrieve_url()
+            .with(eq("expired123"))
+            .once()
+            .return_once(|_| Ok(RetrievedUrl::Expired));
+
+        let service = new_service(mock_repo);
+        let get_url_err = service.get_url("expired123").await.unwrap_err();
+        assert!(matches!(get_url_err, GetUrlError::Gone));
+    }
+
     #[tokio::test]
     async fn test_get_url_db_error() {
         let mut mock_repo = MockUrlRepository::new();
@@ -333,205 +2196,2611 @@ mod tests {
             .once()
             .return_once(|_| Err(anyhow::anyhow!("test error")));
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let get_url_err = service.get_url(short_id).await.unwrap_err();
         assert!(matches!(get_url_err, GetUrlError::Db(err) if err.to_string() == "test error"));
     }
 
     #[tokio::test]
-    async fn test_put_url_newly_created() {
+    async fn test_get_url_disallowed_scheme_enforced_on_read() {
         let mut mock_repo = MockUrlRepository::new();
-        let short_id = "newurl123".to_owned();
-        let long_url = "https://example.com";
-        let expected_short_url = new_short_url(&short_id, long_url, Duration::days(1));
-        let expiration_timestamp_str = expected_short_url
-            .expiration_time
-            .clone()
-            .into_inner()
-            .format(&Rfc3339)
-            .unwrap();
-
+        let short_url = url_repo::ShortUrl::new_for_test(
+            "ftpurl123",
+            "ftp://example.com/file",
+            Duration::days(1),
+        );
         mock_repo
-            .expect_save_url()
-            .with(eq(expected_short_url.clone()))
+            .expect_retrieve_url()
             .once()
-            .return_once({
-                let expected_short_url = expected_short_url.clone();
-                move |_| Ok(expected_short_url)
-            });
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
+        let mut service = new_service(mock_repo);
+        service.options.destinations.enforce_scheme_on_read = true;
+        let get_url_err = service.get_url("ftpurl123").await.unwrap_err();
+        assert!(matches!(get_url_err, GetUrlError::DisallowedScheme(scheme) if scheme == "ftp"));
+    }
+
+    #[tokio::test]
+    async fn test_get_url_disallowed_scheme_keeps_single_use_url() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl {
+            single_use: true,
+            ..url_repo::ShortUrl::new_for_test(
+                "ftpurl123",
+                "ftp://example.com/file",
+                Duration::days(1),
+            )
         };
-        let (shortened_url, status) = service
-            .put_url(short_id, long_url, &expiration_timestamp_str)
-            .await
-            .unwrap();
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+        mock_repo.expect_retrieve_and_consume().never();
 
-        assert_eq!(
-            shortened_url.shortened_url_id,
-            expected_short_url.short_id.into_inner()
+        let mut service = new_service(mock_repo);
+        service.options.destinations.enforce_scheme_on_read = true;
+        let get_url_err = service.get_url("ftpurl123").await.unwrap_err();
+        assert!(matches!(get_url_err, GetUrlError::DisallowedScheme(scheme) if scheme == "ftp"));
+    }
+
+    #[tokio::test]
+    async fn test_get_url_disallowed_scheme_not_enforced_on_read() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl::new_for_test(
+            "ftpurl123",
+            "ftp://example.com/file",
+            Duration::days(1),
         );
-        assert_eq!(status, UrlCreationStatus::NewlyCreated);
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+
+        mock_repo.expect_increment_hits().returning(|_| Ok(()));
+
+        let service = new_service(mock_repo);
+        let redirect = service.get_url("ftpurl123").await.unwrap();
+        assert_eq!(redirect.url, "ftp://example.com/file");
     }
 
     #[tokio::test]
-    async fn test_put_url_already_exists_same_content() {
+    async fn test_get_url_stats_success() {
         let mut mock_repo = MockUrlRepository::new();
-        let short_id = "existurl123".to_owned();
-        let long_url = "https://example.com";
-        let existing_short_url = new_short_url(&short_id, long_url, Duration::days(1));
-        let expiration_timestamp_str = existing_short_url
-            .expiration_time
-            .clone()
-            .into_inner()
-            .format(&Rfc3339)
+        let expiration_time = time::macros::datetime!(2100-01-01 00:00:00 UTC);
+        mock_repo
+            .expect_retrieve_url_stats()
+            .with(eq("testurl"))
+            .once()
+            .return_once(move |_| {
+                Ok(Some(url_repo::ShortUrlStats {
+                    url: Url::parse("https://example.com/").unwrap(),
+                    expiration_time,
+                    hit_count: 7,
+                    cache_max_age_seconds: None,
+                    max_hits: None,
+                }))
+            });
+
+        let service = new_service(mock_repo);
+        let stats = service.get_url_stats("testurl").await.unwrap();
+        assert_eq!(stats.shortened_url_id, "testurl");
+        assert_eq!(stats.long_url, "https://example.com/");
+        assert_eq!(stats.expiration_timestamp, "2100-01-01T00:00:00Z");
+        assert_eq!(stats.hit_count, 7);
+    }
+
+    #[tokio::test]
+    async fn test_get_url_stats_max_age_matches_redirect() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url =
+            url_repo::ShortUrl::new_for_test("testurl", "https://example.com/", Duration::hours(1));
+        let stats = url_repo::ShortUrlStats {
+            url: short_url.url.clone(),
+            expiration_time: short_url.expiration_time.clone().into_inner(),
+            hit_count: 0,
+            cache_max_age_seconds: None,
+            max_hits: None,
+        };
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+        mock_repo
+            .expect_retrieve_url_stats()
+            .once()
+            .return_once(move |_| Ok(Some(stats)));
+        mock_repo.expect_increment_hits().returning(|_| Ok(()));
+
+        let service = new_service(mock_repo);
+        let redirect = service.get_url("testurl").await.unwrap();
+        let stats = service.get_url_stats("testurl").await.unwrap();
+        assert!(
+            // NOTE: slight tolerance is allowed in case of slow tests
+            redirect.max_age_seconds.abs_diff(stats.max_age_seconds) <= 1
+        );
+        assert!((3595..=3600).contains(&stats.max_age_seconds));
+    }
+
+    #[tokio::test]
+    async fn test_cache_max_age_overrides_default() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        service
+            .put_url(
+                "cached1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    cache_max_age_seconds: Some(300),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let redirect = service.get_url("cached1").await.unwrap();
+        assert_eq!(redirect.max_age_seconds, 300);
+        assert_eq!(redirect.cache_control(), "public, max-age=300");
+
+        let stats = service.get_url_stats("cached1").await.unwrap();
+        assert_eq!(stats.max_age_seconds, 300);
+        assert_eq!(stats.cache_max_age_seconds, Some(300));
+    }
+
+    #[tokio::test]
+    async fn test_cache_max_age_never_outlives_expiration() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl {
+            cache_max_age_seconds: Some(86400),
+            ..url_repo::ShortUrl::new_for_test(
+                "testurl",
+                "https://example.com/",
+                Duration::hours(1),
+            )
+        };
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+        mock_repo.expect_increment_hits().returning(|_| Ok(()));
+
+        let service = new_service(mock_repo);
+        let redirect = service.get_url("testurl").await.unwrap();
+        assert!((3595..=3600).contains(&redirect.max_age_seconds));
+    }
+
+    async fn single_use_service() -> Arc<UrlRestServiceImpl> {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        service
+            .put_url(
+                "once1234".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    single_use: true,
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        Arc::new(service)
+    }
+
+    #[tokio::test]
+    async fn test_single_use_url_is_consumed() {
+        let service = single_use_service().await;
+
+        let redirect = service.get_url("once1234").await.unwrap();
+        assert_eq!(redirect.url, "https://example.com/");
+        assert_eq!(redirect.cache_control(), "no-store");
+        assert!(matches!(
+            service.get_url("once1234").await,
+            Err(GetUrlError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_head_url_does_not_consume_single_use_url() {
+        let service = single_use_service().await;
+
+        let redirect = service.head_url("once1234").await.unwrap();
+        assert_eq!(redirect.url, "https://example.com/");
+        assert_eq!(redirect.cache_control(), "no-store");
+        assert!(service.get_url("once1234").await.is_ok());
+        assert!(matches!(
+            service.head_url("once1234").await,
+            Err(GetUrlError::NotFound)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_single_use_url_redirects_only_one_concurrent_request() {
+        let service = single_use_service().await;
+
+        let requests = (0..16)
+            .map(|_| {
+                let service = Arc::clone(&service);
+                tokio::spawn(async move { service.get_url("once1234").await })
+            })
+            .collect::<Vec<_>>();
+        let mut num_redirected = 0;
+        for request in requests {
+            match request.await.unwrap() {
+                Ok(_) => num_redirected += 1,
+                Err(err) => assert!(matches!(err, GetUrlError::NotFound)),
+            }
+        }
+        assert_eq!(num_redirected, 1);
+    }
+
+    async fn limited_service(max_hits: u64) -> Arc<UrlRestServiceImpl> {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        service
+            .put_url(
+                "limited1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    max_hits: Some(max_hits),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        Arc::new(service)
+    }
+
+    #[tokio::test]
+    async fn test_put_url_rejects_zero_max_hits() {
+        let service = new_service(MockUrlRepository::new());
+        let put_url_err = service
+            .put_url(
+                "limited1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    max_hits: Some(0),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(put_url_err, PutUrlError::ZeroMaxHits));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_rejects_single_use_with_max_hits() {
+        let service = new_service(MockUrlRepository::new());
+        let put_url_err = service
+            .put_url(
+                "limited1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    single_use: true,
+                    max_hits: Some(1),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(put_url_err, PutUrlError::SingleUseWithMaxHits));
+    }
+
+    async fn put_with_precondition(
+        service: &UrlRestServiceImpl,
+        precondition: PutPrecondition,
+    ) -> Result<UrlCreationStatus, PutUrlError> {
+        // NOTE: a fixed expiration, so that re-saves are identical
+        let timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .replace_time(Time::MIDNIGHT)
+            .format(&Rfc3339)
+            .unwrap();
+        service
+            .put_url(
+                "precond1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&timestamp),
+                    precondition,
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .map(|(_, creation_status)| creation_status)
+    }
+
+    #[tokio::test]
+    async fn test_create_only_put_url() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+
+        assert!(matches!(
+            put_with_precondition(&service, PutPrecondition::CreateOnly).await,
+            Ok(UrlCreationStatus::NewlyCreated)
+        ));
+        // NOTE: even an identical re-save fails, unlike without the precondition
+        assert!(matches!(
+            put_with_precondition(&service, PutPrecondition::CreateOnly).await,
+            Err(PutUrlError::ShortIdExists)
+        ));
+        assert!(matches!(
+            put_with_precondition(&service, PutPrecondition::None).await,
+            Ok(UrlCreationStatus::AlreadyExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_must_exist_put_url() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+
+        assert!(matches!(
+            put_with_precondition(&service, PutPrecondition::MustExist).await,
+            Err(PutUrlError::ShortIdMissing)
+        ));
+        assert!(matches!(
+            service.get_url("precond1").await,
+            Err(GetUrlError::NotFound)
+        ));
+        put_with_precondition(&service, PutPrecondition::None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            put_with_precondition(&service, PutPrecondition::MustExist).await,
+            Ok(UrlCreationStatus::Replaced)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_must_exist_put_url_changes_target() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        put_with_precondition(&service, PutPrecondition::None)
+            .await
+            .unwrap();
+        service.expire_url("precond1").await.unwrap();
+
+        let (shortened_url, creation_status) = service
+            .put_url(
+                "precond1".to_owned(),
+                "https://example.com/new",
+                PutUrlOptions {
+                    precondition: PutPrecondition::MustExist,
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(creation_status, UrlCreationStatus::Replaced);
+        assert_eq!(shortened_url.long_url, "https://example.com/new");
+        let redirect = service.get_url("precond1").await.unwrap();
+        assert_eq!(redirect.url, "https://example.com/new");
+    }
+
+    #[tokio::test]
+    async fn test_max_hits_boundary() {
+        let service = limited_service(2).await;
+
+        for _ in 0..2 {
+            let redirect = service.get_url("limited1").await.unwrap();
+            assert_eq!(redirect.cache_control(), "no-store");
+        }
+        assert!(matches!(
+            service.get_url("limited1").await,
+            Err(GetUrlError::Gone)
+        ));
+        let stats = service.url_repo.retrieve_url_stats("limited1").await;
+        assert_eq!(stats.unwrap().unwrap().hit_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_url_is_gone_for_every_reader() {
+        let service = limited_service(1).await;
+        service.get_url("limited1").await.unwrap();
+
+        assert!(matches!(
+            service.head_url("limited1").await,
+            Err(GetUrlError::Gone)
+        ));
+        assert!(matches!(
+            service.preview_url("limited1").await,
+            Err(GetUrlError::Gone)
+        ));
+        assert!(matches!(
+            service.get_url_stats("limited1").await,
+            Err(GetUrlStatsError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_head_url_does_not_count_hits() {
+        let service = limited_service(1).await;
+
+        for _ in 0..3 {
+            service.head_url("limited1").await.unwrap();
+        }
+        assert!(service.get_url("limited1").await.is_ok());
+        let stats = service.url_repo.retrieve_url_stats("limited1").await;
+        assert_eq!(stats.unwrap().unwrap().hit_count, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_max_hits_concurrent_burst() {
+        let service = limited_service(3).await;
+
+        let requests = (0..16)
+            .map(|_| {
+                let service = Arc::clone(&service);
+                tokio::spawn(async move { service.get_url("limited1").await })
+            })
+            .collect::<Vec<_>>();
+        let mut num_redirected = 0;
+        for request in requests {
+            match request.await.unwrap() {
+                Ok(_) => num_redirected += 1,
+                Err(err) => assert!(matches!(err, GetUrlError::Gone)),
+            }
+        }
+        assert_eq!(num_redirected, 3);
+        let stats = service.url_repo.retrieve_url_stats("limited1").await;
+        assert_eq!(stats.unwrap().unwrap().hit_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_not_before_delays_redirects() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        for (id, not_before_timestamp) in [("pending1", "1h"), ("active12", "0")] {
+            service
+                .put_url(
+                    id.to_owned(),
+                    "https://example.com/",
+                    PutUrlOptions {
+                        expiration_timestamp: Some("1d"),
+                        not_before_timestamp: Some(not_before_timestamp),
+                        ..PutUrlOptions::default()
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        assert!(matches!(
+            service.get_url("pending1").await,
+            Err(GetUrlError::NotFound)
+        ));
+        assert!(matches!(
+            service.preview_url("pending1").await,
+            Err(GetUrlError::NotFound)
+        ));
+        assert!(service.get_url("active12").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_put_url_rejects_not_before_after_expiration() {
+        let service = new_service(MockUrlRepository::new());
+
+        let result = service
+            .put_url(
+                "pending1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some("1h"),
+                    not_before_timestamp: Some("1d"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(PutUrlError::NotBeforeAfterExpiration)));
+    }
+
+    async fn put_url_with_offset(echo_expiration_offset: bool) -> ShortenedUrl {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        service.options.expiration.echo_expiration_offset = echo_expiration_offset;
+        let timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .replace_nanosecond(0)
+            .unwrap()
+            .to_offset(time::macros::offset!(+5))
+            .format(&Rfc3339)
+            .unwrap();
+        let (shortened_url, _) = service
+            .put_url(
+                "offset12".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            OffsetDateTime::parse(&shortened_url.expiration_timestamp, &Rfc3339).unwrap(),
+            OffsetDateTime::parse(&timestamp, &Rfc3339).unwrap(),
+        );
+        shortened_url
+    }
+
+    #[tokio::test]
+    async fn test_put_url_responds_in_utc_by_default() {
+        let shortened_url = put_url_with_offset(false).await;
+        assert!(shortened_url.expiration_timestamp.ends_with('Z'));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_echoes_requested_offset() {
+        let shortened_url = put_url_with_offset(true).await;
+        assert!(shortened_url.expiration_timestamp.ends_with("+05:00"));
+    }
+
+    #[tokio::test]
+    async fn test_preview_url_does_not_count_hit() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl::new_for_test(
+            "preview1",
+            "https://example.com/dest",
+            Duration::days(1),
+        );
+        let expiration_timestamp = short_url
+            .expiration_time
+            .clone()
+            .into_inner()
+            .format(&Rfc3339)
+            .unwrap();
+        mock_repo
+            .expect_retrieve_url()
+            .with(eq("preview1"))
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+        mock_repo.expect_increment_hits().never();
+
+        let service = new_service(mock_repo);
+        let preview = service.preview_url("preview1").await.unwrap();
+        assert_eq!(preview.long_url, "https://example.com/dest");
+        assert_eq!(preview.expiration_timestamp, expiration_timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_preview_url_expired() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(|_| Ok(RetrievedUrl::Expired));
+
+        let service = new_service(mock_repo);
+        assert!(matches!(
+            service.preview_url("expired1").await.unwrap_err(),
+            GetUrlError::Gone
+        ));
+    }
+
+    #[test]
+    fn test_redirect_max_age_seconds_saturates() {
+        let expiration_time = OffsetDateTime::now_utc() - Duration::hours(1);
+        assert_eq!(Redirect::max_age_seconds(expiration_time, None), 0);
+        assert_eq!(Redirect::max_age_seconds(expiration_time, Some(60)), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_url_stats_expired() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_retrieve_url_stats()
+            .once()
+            .return_once(|_| {
+                Ok(Some(url_repo::ShortUrlStats {
+                    url: Url::parse("https://example.com/").unwrap(),
+                    expiration_time: OffsetDateTime::now_utc() - Duration::seconds(1),
+                    hit_count: 7,
+                    cache_max_age_seconds: None,
+                    max_hits: None,
+                }))
+            });
+
+        let service = new_service(mock_repo);
+        let err = service.get_url_stats("testurl").await.unwrap_err();
+        assert!(matches!(err, GetUrlStatsError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_url_stats_not_found() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_retrieve_url_stats()
+            .once()
+            .return_once(|_| Ok(None));
+
+        let service = new_service(mock_repo);
+        let err = service.get_url_stats("testurl").await.unwrap_err();
+        assert!(matches!(err, GetUrlStatsError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_url_stats_db_error() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_retrieve_url_stats()
+            .once()
+            .return_once(|_| Err(anyhow::anyhow!("test error")));
+
+        let service = new_service(mock_repo);
+        let err = service.get_url_stats("testurl").await.unwrap_err();
+        assert!(matches!(err, GetUrlStatsError::Db(err) if err.to_string() == "test error"));
+    }
+
+    #[tokio::test]
+    async fn test_health_ok() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_ping().once().returning(|| Ok(()));
+
+        let health = new_service(mock_repo).health().await;
+        assert_eq!(
+            serde_json::to_value(health).unwrap(),
+            serde_json::json!({ "status": "ok" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_db_unreachable() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_ping()
+            .once()
+            .returning(|| Err(anyhow::anyhow!("test error")));
+
+        let health = new_service(mock_repo).health().await;
+        assert_eq!(
+            serde_json::to_value(health).unwrap(),
+            serde_json::json!({ "status": "db_unreachable" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_lists_discoverable_urls() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl {
+            discoverable: true,
+            ..url_repo::ShortUrl::new_for_test(
+                "public12",
+                "https://example.com/",
+                Duration::days(1),
+            )
+        };
+        mock_repo
+            .expect_find_discoverable()
+            .once()
+            .return_once(move |_| Ok(vec![short_url]));
+
+        let mut service = new_service(mock_repo);
+        service.options.sitemap_enabled = true;
+        service.options.base_url = Some(Url::parse("https://sto.pid/").unwrap());
+        let sitemap = service.sitemap().await.unwrap();
+        assert_eq!(
+            sitemap,
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                "\n",
+                r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+                "\n",
+                "  <url><loc>https://sto.pid/public12</loc></url>\n",
+                "</urlset>\n",
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_escapes_locations_for_xml() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url = url_repo::ShortUrl {
+            discoverable: true,
+            ..url_repo::ShortUrl::new_for_test(
+                "public12",
+                "https://example.com/",
+                Duration::days(1),
+            )
+        };
+        mock_repo
+            .expect_find_discoverable()
+            .once()
+            .return_once(move |_| Ok(vec![short_url]));
+
+        let mut service = new_service(mock_repo);
+        service.options.sitemap_enabled = true;
+        service.options.base_url = Some(Url::parse("https://sto.pid/r&d/").unwrap());
+        let sitemap = service.sitemap().await.unwrap();
+        assert!(sitemap.contains("  <url><loc>https://sto.pid/r&amp;d/public12</loc></url>\n"));
+    }
+
+    #[tokio::test]
+    async fn test_list_urls_pages_through_all_items() {
+        let repo = InMemoryUrlRepository::default();
+        for id in ["listed01", "listed02", "listed03", "listed04"] {
+            repo.save_url(url_repo::ShortUrl::new_for_test(
+                id,
+                "https://example.com/",
+                Duration::days(1),
+            ))
+            .await
+            .unwrap();
+        }
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(repo);
+
+        let first_page = service.list_urls(Some(2), None).await.unwrap();
+        let ids = |page: &UrlPage| {
+            page.items
+                .iter()
+                .map(|item| item.shortened_url.shortened_url_id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&first_page), ["listed01", "listed02"]);
+
+        let cursor = first_page.next_cursor.unwrap();
+        let last_page = service.list_urls(Some(2), Some(&cursor)).await.unwrap();
+        assert_eq!(ids(&last_page), ["listed03", "listed04"]);
+        assert_eq!(last_page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_list_urls_includes_original_url() {
+        let repo = InMemoryUrlRepository::default();
+        repo.save_url(url_repo::ShortUrl {
+            original_url: Some("https://EXAMPLE.com:443".to_owned()),
+            ..url_repo::ShortUrl::new_for_test(
+                "listed01",
+                "https://example.com/",
+                Duration::days(1),
+            )
+        })
+        .await
+        .unwrap();
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(repo);
+
+        let page = service.list_urls(None, None).await.unwrap();
+        let [item] = page.items.as_slice() else {
+            panic!("expected a single item, got {:?}", page.items);
+        };
+        assert_eq!(
+            item.original_url.as_deref(),
+            Some("https://EXAMPLE.com:443")
+        );
+        assert_eq!(
+            serde_json::to_value(item).unwrap()["original_url"],
+            "https://EXAMPLE.com:443"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_urls_caps_limit() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_list_urls()
+            .with(eq(4), eq(None))
+            .once()
+            .return_once(|_, _| Ok(vec![]));
+
+        // NOTE: new_service caps pages at 3 items, so 3 + 1 are requested
+        let service = new_service(mock_repo);
+        let page = service.list_urls(Some(1000), None).await.unwrap();
+        assert!(page.items.is_empty());
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_list_urls_invalid_cursor() {
+        let service = new_service(MockUrlRepository::new());
+        for cursor in ["not hex", "abc", "ff"] {
+            assert!(matches!(
+                service.list_urls(None, Some(cursor)).await,
+                Err(ListUrlsError::InvalidCursor)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debug_url_tells_generated_ids_from_chosen_ones() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        let (generated, _, _) = service
+            .post_url("https://example.com/generated", None, None)
+            .await
+            .unwrap();
+        service
+            .put_url(
+                "vanity12".to_owned(),
+                "https://example.com/vanity",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let generated_info = service
+            .debug_url(&generated.shortened_url_id)
+            .await
+            .unwrap();
+        assert!(generated_info.generated_id);
+        assert_eq!(generated_info.listed_url.shortened_url, generated);
+        assert!(!service.debug_url("vanity12").await.unwrap().generated_id);
+        assert!(matches!(
+            service.debug_url("missing1").await,
+            Err(GetUrlError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = encode_cursor("acme/promo1");
+        assert_eq!(decode_cursor(&cursor).as_deref(), Some("acme/promo1"));
+    }
+
+    #[tokio::test]
+    async fn test_qr_code_encodes_short_url() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_url =
+            url_repo::ShortUrl::new_for_test("qrcode12", "https://example.com/", Duration::days(1));
+        mock_repo
+            .expect_retrieve_url()
+            .with(eq("qrcode12"))
+            .once()
+            .return_once(move |_| Ok(RetrievedUrl::Found(Box::new(short_url))));
+
+        let mut service = new_service(mock_repo);
+        service.options.base_url = Some(Url::parse("https://sto.pid/").unwrap());
+        let svg = service.qr_code("qrcode12", Some(100_000)).await.unwrap();
+        let width = svg
+            .split_once(r#"width=""#)
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map(|(width, _)| width.parse::<u32>().unwrap())
+            .unwrap();
+        assert!((QR_CODE_MAX_SIZE - 64..=QR_CODE_MAX_SIZE).contains(&width));
+    }
+
+    #[tokio::test]
+    async fn test_qr_code_not_found_when_expired() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_retrieve_url()
+            .once()
+            .return_once(|_| Ok(RetrievedUrl::Expired));
+
+        let mut service = new_service(mock_repo);
+        service.options.base_url = Some(Url::parse("https://sto.pid/").unwrap());
+        assert!(matches!(
+            service.qr_code("expired1", None).await.unwrap_err(),
+            QrCodeError::NotFound
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_qr_code_requires_base_url() {
+        let service = new_service(MockUrlRepository::new());
+        assert!(matches!(
+            service.qr_code("qrcode12", None).await.unwrap_err(),
+            QrCodeError::Disabled
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_disabled() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.base_url = Some(Url::parse("https://sto.pid/").unwrap());
+        assert!(matches!(
+            service.sitemap().await.unwrap_err(),
+            SitemapError::Disabled
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sitemap_requires_base_url() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.sitemap_enabled = true;
+        assert!(matches!(
+            service.sitemap().await.unwrap_err(),
+            SitemapError::Disabled
+        ));
+    }
+
+    #[test]
+    fn test_url_policy_hides_shortener_blocklist() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.destinations.shortener_domains = vec!["bit.ly".to_owned()];
+        let policy = serde_json::to_value(service.url_policy()).unwrap();
+        assert_eq!(
+            policy,
+            serde_json::json!({
+                "allowed_schemes": ["http", "https"],
+                "shortener_blocklist_active": true,
+            })
+        );
+        assert!(!policy.to_string().contains("bit.ly"));
+    }
+
+    #[test]
+    fn test_url_policy_without_shortener_blocklist() {
+        let policy = new_service(MockUrlRepository::new()).url_policy();
+        assert!(!policy.shortener_blocklist_active);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_newly_created() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_id = "newurl123".to_owned();
+        let long_url = "https://example.com";
+        let expected_short_url = url_repo::ShortUrl {
+            original_url: Some(long_url.to_owned()),
+            ..url_repo::ShortUrl::new_for_test(&short_id, long_url, Duration::days(1))
+        };
+        let expiration_timestamp_str = expected_short_url
+            .expiration_time
+            .clone()
+            .into_inner()
+            .format(&Rfc3339)
+            .unwrap();
+
+        mock_repo
+            .expect_save_url()
+            .with(eq(expected_short_url.clone()))
+            .once()
+            .return_once({
+                let expected_short_url = expected_short_url.clone();
+                move |_| Ok(expected_short_url)
+            });
+
+        let service = new_service(mock_repo);
+        let (shortened_url, status) = service
+            .put_url(
+                short_id,
+                long_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp_str),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            shortened_url.shortened_url_id,
+            expected_short_url.short_id.into_inner()
+        );
+        assert_eq!(status, UrlCreationStatus::NewlyCreated);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_already_exists_same_content() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_id = "existurl123".to_owned();
+        let long_url = "https://example.com";
+        let existing_short_url = url_repo::ShortUrl {
+            original_url: Some(long_url.to_owned()),
+            ..url_repo::ShortUrl::new_for_test(&short_id, long_url, Duration::days(1))
+        };
+        let expiration_timestamp_str = existing_short_url
+            .expiration_time
+            .clone()
+            .into_inner()
+            .format(&Rfc3339)
+            .unwrap();
+
+        mock_repo
+            .expect_save_url()
+            .with(eq(existing_short_url.clone()))
+            .once()
+            .return_once({
+                let existing_short_url = existing_short_url.clone();
+                move |_| {
+                    Err(SaveUrlError::ItemAlreadyExists(Box::new(
+                        existing_short_url,
+                    )))
+                }
+            });
+
+        let service = new_service(mock_repo);
+        let (shortened_url, status) = service
+            .put_url(
+                short_id,
+                long_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp_str),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            shortened_url.shortened_url_id,
+            existing_short_url.short_id.into_inner()
+        );
+        assert_eq!(status, UrlCreationStatus::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_short_id_already_taken() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_id = "takenurl123".to_owned();
+        let long_url = "https://example.com";
+        let conflicting_short_url = url_repo::ShortUrl::new_for_test(
+            "anotherurl123",
+            "https://example.com",
+            Duration::days(1),
+        );
+        let expiration_timestamp_str = conflicting_short_url
+            .expiration_time
+            .clone()
+            .into_inner()
+            .format(&Rfc3339)
+            .unwrap();
+
+        let expected_short_url = ShortUrl {
+            expiration_time: conflicting_short_url.expiration_time.clone(),
+            original_url: Some(long_url.to_owned()),
+            ..url_repo::ShortUrl::new_for_test(&short_id, long_url, Duration::days(1))
+        };
+        mock_repo
+            .expect_save_url()
+            .with(eq(expected_short_url))
+            .once()
+            .return_once({
+                let conflicting_short_url = conflicting_short_url.clone();
+                move |_| {
+                    Err(SaveUrlError::ItemAlreadyExists(Box::new(
+                        conflicting_short_url,
+                    )))
+                }
+            });
+
+        let service = new_service(mock_repo);
+        let result = service
+            .put_url(
+                short_id,
+                long_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp_str),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            result,
+            PutUrlError::ShortIdAlreadyTaken { existing_url }
+                if existing_url == conflicting_short_url.url.as_str()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_strips_host_trailing_dot() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_save_url()
+            .withf(|short_url| short_url.url.as_str() == "https://example.com/path")
+            .once()
+            .return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.normalization.normalize_trailing_dot_hosts = true;
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com./path",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url, "https://example.com/path");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_trailing_dot_host_matches_shortener_domain() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.destinations.shortener_domains = vec!["bit.ly".to_owned()];
+        service.options.normalization.normalize_trailing_dot_hosts = true;
+        let err = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://bit.ly./abc",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PutUrlError::KnownShortener { host } if host == "bit.ly"));
+    }
+
+    #[tokio::test]
+    async fn test_post_url_trailing_dot_host_dedupes() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().times(2).returning(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.normalization.normalize_trailing_dot_hosts = true;
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let (qualified, ..) = service
+            .post_url("https://example.com./", Some(&expiration_timestamp), None)
+            .await
+            .unwrap();
+        let (unqualified, ..) = service
+            .post_url("https://example.com/", Some(&expiration_timestamp), None)
+            .await
+            .unwrap();
+        assert_eq!(qualified.shortened_url_id, unqualified.shortened_url_id);
+        assert_eq!(qualified.long_url, unqualified.long_url);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_normalizes_url() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_save_url()
+            .withf(|short_url| short_url.url.as_str() == "custom://example.com")
+            .once()
+            .return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.destinations.allowed_schemes = vec!["custom".to_owned()];
+        service.options.normalization.normalize_urls = true;
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "custom://EXAMPLE.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url, "custom://example.com");
+    }
+
+    #[test]
+    fn test_normalize_url_decodes_unreserved_characters() {
+        let url = Url::parse("https://example.com/%7Euser/a%2Db%2fc%20d?q=%7E").unwrap();
+        assert_eq!(
+            normalize_url(url).unwrap().as_str(),
+            "https://example.com/~user/a-b%2fc%20d?q=%7E"
+        );
+    }
+
+    #[test]
+    fn test_candidate_short_id_grows_with_hash_bytes() {
+        let lengths = [1, DEFAULT_HASH_BYTES, 10, MAX_HASH_BYTES].map(|hash_bytes| {
+            candidate_short_id(
+                &[0; blake3::KEY_LEN],
+                "https://example.com/",
+                "2030-01-01T00:00:00Z",
+                hash_bytes,
+                &IdAlphabet::default(),
+            )
+            .len()
+        });
+        assert!(lengths.is_sorted_by(|a, b| a < b), "{lengths:?}");
+    }
+
+    #[test]
+    fn test_default_id_alphabet_is_base62() {
+        let alphabet = IdAlphabet::default();
+        assert_eq!(alphabet.encode(0), "0");
+        assert_eq!(alphabet.encode(61), "z");
+        assert_eq!(alphabet.encode(62), "10");
+        assert_eq!(alphabet.encode(u128::MAX), "7n42DGM5Tflk9n8mt7Fhc7");
+    }
+
+    #[test]
+    fn test_id_alphabet_validation() {
+        assert_eq!(
+            "01".parse::<IdAlphabet>().unwrap().encode(5),
+            "101".to_owned()
+        );
+        for invalid_alphabet in ["", "a", "abca", "ab-c", "abcé"] {
+            assert!(invalid_alphabet.parse::<IdAlphabet>().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_post_url_custom_id_alphabet() {
+        const UNAMBIGUOUS: &str = "23456789abcdefghijkmnpqrstuvwxyz";
+
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().returning(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.short_ids.id_alphabet = UNAMBIGUOUS.parse().unwrap();
+        let (shortened_url, ..) = service
+            .post_url("https://example.com/", None, None)
+            .await
+            .unwrap();
+        let short_id = shortened_url.shortened_url_id;
+        assert!(
+            short_id.chars().all(|c| UNAMBIGUOUS.contains(c)),
+            "{short_id}"
+        );
+        let bounds = ShortIdLengthBounds::default();
+        assert!((bounds.min_len..=bounds.max_len).contains(&short_id.len()));
+    }
+
+    #[tokio::test]
+    async fn test_post_url_without_expiration_dedupes() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        service.options.destinations.target_cooldown = Some(std::time::Duration::from_mins(1));
+
+        let (first, first_status, _) = service
+            .post_url("https://example.com/", None, None)
+            .await
+            .unwrap();
+        let (second, second_status, _) = service
+            .post_url("https://example.com/", None, None)
+            .await
+            .unwrap();
+        assert_eq!(first_status, UrlCreationStatus::NewlyCreated);
+        assert_eq!(second_status, UrlCreationStatus::AlreadyExists);
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_post_url_equivalent_urls_dedupe() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().times(5).returning(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.normalization.normalize_urls = true;
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let mut short_ids = Vec::new();
+        for url in [
+            "https://example.com",
+            "https://example.com/",
+            "https://EXAMPLE.com:443",
+            "https://example.com/?",
+            "https://example.com/#",
+        ] {
+            let (shortened_url, ..) = service
+                .post_url(url, Some(&expiration_timestamp), None)
+                .await
+                .unwrap();
+            assert_eq!(shortened_url.long_url, "https://example.com/");
+            short_ids.push(shortened_url.shortened_url_id);
+        }
+        assert!(short_ids.iter().all(|short_id| *short_id == short_ids[0]));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_strips_tracking_params() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_save_url()
+            .withf(|short_url| short_url.url.as_str() == "https://example.com/?b=2&a=1&c=3")
+            .once()
+            .return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.normalization.strip_tracking_params = true;
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/?utm_source=x&b=2&fbclid=y&a=1&gclid=z&c=3&utm_medium=w",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url, "https://example.com/?b=2&a=1&c=3");
+    }
+
+    #[tokio::test]
+    async fn test_post_url_tracking_params_dedupe() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().times(2).returning(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.normalization.strip_tracking_params = true;
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let (tracked, ..) = service
+            .post_url(
+                "https://example.com/?utm_campaign=launch",
+                Some(&expiration_timestamp),
+                None,
+            )
+            .await
+            .unwrap();
+        let (untracked, ..) = service
+            .post_url("https://example.com/", Some(&expiration_timestamp), None)
+            .await
+            .unwrap();
+        assert_eq!(tracked.shortened_url_id, untracked.shortened_url_id);
+        assert_eq!(tracked.long_url, "https://example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_at_max_url_length() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let long_url = format!("https://example.com/{}", "a".repeat(2048 - 20));
+        let service = new_service(mock_repo);
+        let (shortened_url, _) = service
+            .put_url("valid123".to_owned(), &long_url, PutUrlOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url.len(), 2048);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_over_max_url_length() {
+        let long_url = format!("https://example.com/{}", "a".repeat(2048 - 19));
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .put_url("valid123".to_owned(), &long_url, PutUrlOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::UrlTooLong { max: 2048 }));
+    }
+
+    #[tokio::test]
+    async fn test_post_urls_reports_each_item() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().times(2).returning(Ok);
+
+        let service = new_service(mock_repo);
+        let items = [
+            "https://example.com/a",
+            "not a url",
+            "https://example.com/b",
+        ]
+        .map(|url| PostUrlPayload {
+            url: url.to_owned(),
+            expiration_timestamp: None,
+        })
+        .into();
+        let results = service.post_urls(items, None).await.unwrap();
+
+        let [
+            PostUrlsItem::Posted(Ok(first)),
+            PostUrlsItem::Posted(Err(PostUrlError::InvalidUrl(_))),
+            PostUrlsItem::Posted(Ok(third)),
+        ] = results.as_slice()
+        else {
+            panic!("unexpected results: {results:?}");
+        };
+        assert_eq!(first.long_url, "https://example.com/a");
+        assert_eq!(third.long_url, "https://example.com/b");
+    }
+
+    #[tokio::test]
+    async fn test_post_urls_collapses_duplicates_when_enabled() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().times(2).returning(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.collapse_bulk_duplicates = true;
+        let items = [
+            "https://example.com/a",
+            "https://example.com/b",
+            "https://example.com/a",
+        ]
+        .map(|url| PostUrlPayload {
+            url: url.to_owned(),
+            expiration_timestamp: None,
+        })
+        .into();
+        let results = service.post_urls(items, None).await.unwrap();
+
+        let [
+            PostUrlsItem::Posted(Ok(first)),
+            PostUrlsItem::Posted(Ok(second)),
+            PostUrlsItem::DuplicateOf(0),
+        ] = results.as_slice()
+        else {
+            panic!("unexpected results: {results:?}");
+        };
+        assert_eq!(first.long_url, "https://example.com/a");
+        assert_eq!(second.long_url, "https://example.com/b");
+    }
+
+    #[tokio::test]
+    async fn test_post_urls_too_many_items() {
+        let service = new_service(MockUrlRepository::new());
+        let items = (0..11)
+            .map(|i| PostUrlPayload {
+                url: format!("https://example.com/{i}"),
+                expiration_timestamp: None,
+            })
+            .collect();
+        let err = service.post_urls(items, None).await.unwrap_err();
+        assert!(matches!(err, PostUrlsError::TooManyItems { max: 10 }));
+    }
+
+    #[tokio::test]
+    async fn test_post_url_over_max_url_length_not_retried() {
+        let long_url = format!("https://example.com/{}", "a".repeat(2048 - 19));
+        let service = new_service(MockUrlRepository::new());
+        let result = service.post_url(&long_url, None, None).await.unwrap_err();
+        assert!(matches!(result, PostUrlError::UrlTooLong { max: 2048 }));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_allowed_scheme() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "http://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url, "http://example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_disallowed_scheme() {
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                "javascript:alert(1)",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::DisallowedScheme(scheme) if scheme == "javascript"));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_custom_scheme_allowlist() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.destinations.allowed_schemes = vec!["ftp".to_owned()];
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "ftp://example.com/file",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url, "ftp://example.com/file");
+
+        let result = service
+            .put_url(
+                "valid456".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::DisallowedScheme(scheme) if scheme == "https"));
+    }
+
+    #[tokio::test]
+    async fn test_post_url_disallowed_scheme_not_retried() {
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .post_url("ftp://example.com/file", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PostUrlError::DisallowedScheme(scheme) if scheme == "ftp"));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_private_url_rejected() {
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                "http://192.168.0.1/admin",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::DisallowedUrl(DisallowedUrlError::PrivateHost(host)) if host == "192.168.0.1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_private_url_allowed_when_enabled() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.destinations.allow_private_urls = true;
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "http://localhost:8080/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url, "http://localhost:8080/");
+    }
+
+    #[tokio::test]
+    async fn test_post_url_private_url_not_retried() {
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .post_url("http://[::1]/", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PostUrlError::DisallowedUrl(DisallowedUrlError::PrivateHost(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_custom_short_id_length_bounds() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.short_ids.short_id_length_bounds = ShortIdLengthBounds {
+            min_len: 4,
+            max_len: 4,
+        };
+        let (shortened_url, _) = service
+            .put_url(
+                "abcd".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.shortened_url_id, "abcd");
+
+        let result = service
+            .put_url(
+                "abcde".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::InvalidShortId(ShortIdValidationError::InvalidLength {
+                min_len: 4,
+                max_len: 4
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_post_url_respects_max_short_id_length() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.short_ids.short_id_length_bounds = ShortIdLengthBounds {
+            min_len: 4,
+            max_len: 4,
+        };
+        let (shortened_url, ..) = service
+            .post_url("https://example.com/", None, None)
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.shortened_url_id.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_just_under_configured_max_ttl() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.expiration.max_ttl = Duration::hours(24);
+        service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some("23h"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_put_url_just_over_configured_max_ttl() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.expiration.max_ttl = Duration::hours(24);
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some("25h"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::InvalidExpirationTime(ExpirationTimeValidationError::TooFarInFuture {
+                max_ttl,
+                ..
+            }) if max_ttl == Duration::hours(24)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_reserved_id_rejected() {
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .put_url(
+                "health".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::ReservedId));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_non_reserved_id_accepted() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (shortened_url, _) = service
+            .put_url(
+                "healthy".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.shortened_url_id, "healthy");
+    }
+
+    fn new_namespaced_service() -> UrlRestServiceImpl {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        service.options.short_ids.namespaces_enabled = true;
+        service
+    }
+
+    #[tokio::test]
+    async fn test_same_id_in_different_namespaces() {
+        let service = new_namespaced_service();
+        for (id, url) in [
+            ("acme/promo1", "https://acme.example/"),
+            ("globex/promo1", "https://globex.example/"),
+        ] {
+            let (shortened_url, status) = service
+                .put_url(id.to_owned(), url, PutUrlOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(shortened_url.shortened_url_id, id);
+            assert_eq!(status, UrlCreationStatus::NewlyCreated);
+        }
+
+        let acme = service.get_url("acme/promo1").await.unwrap();
+        assert_eq!(acme.url, "https://acme.example/");
+        let globex = service.get_url("globex/promo1").await.unwrap();
+        assert_eq!(globex.url, "https://globex.example/");
+        assert!(matches!(
+            service.get_url("promo1").await,
+            Err(GetUrlError::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_namespaced_id_rejected_when_disabled() {
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .put_url(
+                "acme/promo1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::InvalidShortId(ShortIdValidationError::InvalidCharacters { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_invalid_namespace() {
+        let service = new_namespaced_service();
+        let result = service
+            .put_url(
+                "ac-me/promo1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::InvalidNamespace(_)));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_namespaced_reserved_ids() {
+        let mut service = new_namespaced_service();
+        service.options.short_ids.short_id_length_bounds = ShortIdLengthBounds {
+            min_len: 2,
+            max_len: 16,
+        };
+        for id in ["acme/stats", "acme/qr"] {
+            let result = service
+                .put_url(
+                    id.to_owned(),
+                    "https://example.com/",
+                    PutUrlOptions::default(),
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(result, PutUrlError::ReservedId), "{id}");
+        }
+
+        let result = service
+            .put_url(
+                "admin/promo1".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::ReservedId));
+
+        // NOTE: top-level routes don't collide with IDs inside a namespace
+        let (shortened_url, _) = service
+            .put_url(
+                "acme/health".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.shortened_url_id, "acme/health");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_low_entropy_id_rejected() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.short_ids.min_distinct_id_chars = Some(3);
+        let result = service
+            .put_url(
+                "aaaaaa".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::LowEntropyShortId {
+                min_distinct_chars: 3
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_varied_id_accepted() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.short_ids.min_distinct_id_chars = Some(3);
+        let (shortened_url, _) = service
+            .put_url(
+                "abcabc".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.shortened_url_id, "abcabc");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_low_entropy_id_allowed_when_disabled() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (shortened_url, _) = service
+            .put_url(
+                "aaaaaa".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.shortened_url_id, "aaaaaa");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_preserves_original_url() {
+        let mut mock_repo = MockUrlRepository::new();
+        let original_url = "https://EXAMPLE.com:443/a/../b";
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+
+        mock_repo
+            .expect_save_url()
+            .withf(move |actual_short_url| {
+                actual_short_url.url.as_str() == "https://example.com/b"
+                    && actual_short_url.original_url.as_deref() == Some(original_url)
+            })
+            .once()
+            .return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                original_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.long_url, "https://example.com/b");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_stores_utm_template() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_save_url()
+            .withf(|actual_short_url| {
+                actual_short_url.utm_template.as_deref() == Some("utm_source=x&utm_medium=y")
+            })
+            .once()
+            .return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    utm_template: Some("?utm_source=x&utm_medium=y"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        // NOTE: the template is only applied on redirect
+        assert_eq!(shortened_url.long_url, "https://example.com/");
+    }
+
+    async fn put_owned_url(
+        service: &UrlRestServiceImpl,
+        id: &str,
+        url: &str,
+    ) -> Result<(ShortenedUrl, UrlCreationStatus), PutUrlError> {
+        service
+            .put_url(
+                id.to_owned(),
+                url,
+                PutUrlOptions {
+                    owner: Some("owner1"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_max_hosts_per_owner() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.url_repo = Arc::new(InMemoryUrlRepository::default());
+        service.options.destinations.max_hosts_per_owner = Some(2);
+        put_owned_url(&service, "hosta123", "https://a.example/")
+            .await
+            .unwrap();
+        put_owned_url(&service, "hostb123", "https://b.example/")
+            .await
+            .unwrap();
+
+        // NOTE: at the cap, hosts the owner already targets are still fine
+        put_owned_url(&service, "hosta456", "https://a.example/other")
+            .await
+            .unwrap();
+        let result = put_owned_url(&service, "hostc123", "https://c.example/").await;
+        assert!(matches!(result, Err(PutUrlError::TooManyHosts { max: 2 })));
+        let result = service
+            .post_url("https://c.example/", None, Some("owner1"))
+            .await;
+        assert!(matches!(result, Err(PostUrlError::TooManyHosts { max: 2 })));
+
+        // NOTE: other owners (and requests without an API key) have their own hosts
+        service
+            .post_url("https://c.example/", None, Some("owner2"))
+            .await
+            .unwrap();
+        service
+            .post_url("https://d.example/", None, None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_put_url_target_cooldown_rejects_repeat() {
+        let long_url = "https://example.com/";
+        let recent = url_repo::ShortUrl::new_for_test("recent123", long_url, Duration::days(1));
+
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_find_recently_created()
+            .withf(move |actual_long_url, _| actual_long_url == long_url)
+            .once()
+            .return_once(move |_, _| Ok(Some(recent)));
+
+        let mut service = new_service(mock_repo);
+        service.options.destinations.target_cooldown = Some(std::time::Duration::from_mins(1));
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                long_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::TargetCooldown {
+                cooldown_seconds: 60
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_target_cooldown_elapsed() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_find_recently_created()
+            .withf(|_, since| {
+                let expected_since = OffsetDateTime::now_utc() - Duration::seconds(60);
+                // NOTE: slight tolerance is allowed in case of slow tests
+                (expected_since - *since).abs() < Duration::seconds(5)
+            })
+            .once()
+            .return_once(|_, _| Ok(None));
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.destinations.target_cooldown = Some(std::time::Duration::from_mins(1));
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let (_, status) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, UrlCreationStatus::NewlyCreated);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_target_cooldown_allows_idempotent_retry() {
+        let long_url = "https://example.com/";
+        let existing = url_repo::ShortUrl::new_for_test("valid123", long_url, Duration::days(1));
+        let expiration_timestamp = existing
+            .expiration_time
+            .clone()
+            .into_inner()
+            .format(&Rfc3339)
+            .unwrap();
+
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_find_recently_created()
+            .once()
+            .return_once({
+                let existing = existing.clone();
+                move |_, _| Ok(Some(existing))
+            });
+        mock_repo
+            .expect_save_url()
+            .once()
+            .return_once(move |_| Err(SaveUrlError::ItemAlreadyExists(Box::new(existing))));
+
+        let mut service = new_service(mock_repo);
+        service.options.destinations.target_cooldown = Some(std::time::Duration::from_mins(1));
+        let (_, status) = service
+            .put_url(
+                "valid123".to_owned(),
+                long_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, UrlCreationStatus::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_default_ttl() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_save_url()
+            .withf(|actual_short_url| {
+                let expected_time = OffsetDateTime::now_utc() + Duration::hours(24);
+                let actual_time = actual_short_url.expiration_time.clone().into_inner();
+                // NOTE: slight tolerance is allowed in case of slow tests
+                (expected_time - actual_time).abs() < Duration::seconds(5)
+            })
+            .once()
+            .return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (_, status) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, UrlCreationStatus::NewlyCreated);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_invalid_short_id() {
+        let mock_repo = MockUrlRepository::new();
+        let service = new_service(mock_repo);
+        let result = service
+            .put_url(
+                "invalid_chars".to_owned(),
+                "https://example.com",
+                PutUrlOptions {
+                    expiration_timestamp: Some("2025-01-01T00:00:00Z"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::InvalidShortId(ShortIdValidationError::InvalidCharacters { invalid_chars })
+                if invalid_chars == "_"
+        ));
+    }
+
+    #[test]
+    fn test_host_matches_domain() {
+        assert!(host_matches_domain("bit.ly", "bit.ly"));
+        assert!(host_matches_domain("www.bit.ly", "bit.ly"));
+        assert!(host_matches_domain("BIT.ly", "bit.ly"));
+        assert!(!host_matches_domain("notbit.ly", "bit.ly"));
+        assert!(!host_matches_domain("bit.ly.example.com", "bit.ly"));
+        assert!(!host_matches_domain("ly", "bit.ly"));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_known_shortener_rejected() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.destinations.shortener_domains = vec!["bit.ly".to_owned()];
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://bit.ly/abc123",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            result,
+            PutUrlError::KnownShortener { host } if host == "bit.ly"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_non_shortener_host_allowed() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.destinations.shortener_domains = vec!["bit.ly".to_owned()];
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let (_, status) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(status, UrlCreationStatus::NewlyCreated);
+    }
+
+    #[tokio::test]
+    async fn test_put_url_invalid_long_url() {
+        let mock_repo = MockUrlRepository::new();
+        let service = new_service(mock_repo);
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                "not a url",
+                PutUrlOptions {
+                    expiration_timestamp: Some("1234-01-01T00:00:00Z"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::InvalidUrl(_)));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_invalid_timestamp_format() {
+        let mock_repo = MockUrlRepository::new();
+        let service = new_service(mock_repo);
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com",
+                PutUrlOptions {
+                    expiration_timestamp: Some("invalid-timestamp"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(result, PutUrlError::TimestampParse(_)));
+    }
+
+    #[tokio::test]
+    async fn test_put_url_timestamp_missing_offset() {
+        let service = new_service(MockUrlRepository::new());
+        let result = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com",
+                PutUrlOptions {
+                    expiration_timestamp: Some("2030-01-01T00:00:00.5"),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            result.to_string(),
+            "failed to parse timestamp: expiration_timestamp must include a timezone offset, e.g. Z"
+        );
+    }
+
+    /// Simulates server replicas racing to save the same deterministic POST id.
+    mod replica_race {
+        use rearch::Container;
+        use sea_orm::{DatabaseBackend, DbConn, DbErr, MockDatabase, RuntimeErr};
+
+        use super::*;
+        use crate::{config::db_conn_init_action, orm::short_url};
+
+        /// A replica with its own repository, sharing `db` with any other replicas.
+        fn replica(db: DbConn) -> UrlRestServiceImpl {
+            let container = Container::new();
+            container.read(db_conn_init_action)(db);
+            let mut service = new_service(MockUrlRepository::new());
+            service.url_repo = container.read(url_repository_capsule);
+            service
+        }
+
+        #[tokio::test]
+        async fn test_identical_posts_return_same_id() {
+            let long_url = "https://example.com/";
+            let expiration_time = (OffsetDateTime::now_utc() + Duration::days(1))
+                .replace_nanosecond(0)
+                .unwrap();
+            let expiration_timestamp = expiration_time.format(&Rfc3339).unwrap();
+            let id = candidate_short_id(
+                &[0; blake3::KEY_LEN],
+                long_url,
+                &expiration_timestamp,
+                DEFAULT_HASH_BYTES,
+                &IdAlphabet::default(),
+            );
+            let model = short_url::Model {
+                id: id.clone(),
+                long_url: long_url.to_owned(),
+                expiration_time_seconds: expiration_time.into(),
+                original_url: Some(long_url.to_owned()),
+                created_at_seconds: Some(OffsetDateTime::now_utc().into()),
+                hit_count: 0,
+                utm_template: None,
+                discoverable: false,
+                cache_max_age_seconds: None,
+                single_use: None,
+                max_hits: None,
+                not_before_seconds: None,
+                owner: None,
+            };
+
+            let db = MockDatabase::new(DatabaseBackend::Postgres)
+                // NOTE: replica A finds no existing row and inserts its own
+                .append_query_results([vec![], vec![model.clone()]])
+                // NOTE: replica B also found no row (before A committed), so its insert conflicts
+                .append_query_results::<short_url::Model, _, _>([vec![]])
+                .append_query_errors([DbErr::Query(RuntimeErr::Internal(
+                    r#"duplicate key value violates unique constraint "urls_pkey""#.to_owned(),
+                ))])
+                // NOTE: replica B's retry then finds replica A's row
+                .append_query_results([vec![model]])
+                .into_connection();
+            let (replica_a, replica_b) = (replica(db.clone()), replica(db));
+
+            let (shortened_a, ..) = replica_a
+                .post_url(long_url, Some(&expiration_timestamp), None)
+                .await
+                .unwrap();
+            let (shortened_b, ..) = replica_b
+                .post_url(long_url, Some(&expiration_timestamp), None)
+                .await
+                .unwrap();
+            assert_eq!(shortened_a.shortened_url_id, id);
+            assert_eq!(shortened_b.shortened_url_id, id);
+        }
+    }
+
+    mod parse_expiration_timestamp {
+        use time::macros::datetime;
+
+        use super::*;
+
+        #[test]
+        fn test_unix_timestamp() {
+            let parsed =
+                parse_expiration_timestamp("1893456000", AmbiguousTimePolicy::Reject).unwrap();
+            assert_eq!(parsed, datetime!(2030-01-01 00:00:00 UTC));
+        }
+
+        #[test]
+        fn test_rfc3339() {
+            let parsed = parse_expiration_timestamp(
+                "2030-01-01T05:00:00+05:00",
+                AmbiguousTimePolicy::Reject,
+            )
+            .unwrap();
+            assert_eq!(parsed, datetime!(2030-01-01 00:00:00 UTC));
+        }
+
+        #[test]
+        fn test_rfc3339_missing_offset() {
+            let err =
+                parse_expiration_timestamp("2030-01-01T00:00:00", AmbiguousTimePolicy::Reject)
+                    .unwrap_err();
+            assert!(matches!(err, TimestampParseError::MissingOffset));
+        }
+
+        #[test]
+        fn test_garbage() {
+            let err = parse_expiration_timestamp("not-a-timestamp", AmbiguousTimePolicy::Reject)
+                .unwrap_err();
+            assert!(matches!(err, TimestampParseError::Rfc3339(_)));
+        }
+
+        #[test]
+        fn test_unix_timestamp_out_of_range() {
+            let err = parse_expiration_timestamp("99999999999999999", AmbiguousTimePolicy::Reject)
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                TimestampParseError::UnixTimestampOutOfRange(_)
+            ));
+        }
+
+        #[test]
+        fn test_unix_timestamp_overflow() {
+            let err =
+                parse_expiration_timestamp("99999999999999999999", AmbiguousTimePolicy::Reject)
+                    .unwrap_err();
+            assert!(matches!(err, TimestampParseError::InvalidUnixTimestamp(_)));
+        }
+
+        #[test]
+        fn test_relative_minutes() {
+            let before = OffsetDateTime::now_utc();
+            let parsed = parse_expiration_timestamp("30m", AmbiguousTimePolicy::Reject).unwrap();
+            let after = OffsetDateTime::now_utc();
+            assert!(before + Duration::minutes(30) <= parsed);
+            assert!(parsed <= after + Duration::minutes(30));
+        }
+
+        #[test]
+        fn test_relative_weeks() {
+            let before = OffsetDateTime::now_utc();
+            let parsed = parse_expiration_timestamp("2w", AmbiguousTimePolicy::Reject).unwrap();
+            let after = OffsetDateTime::now_utc();
+            assert!(before + Duration::weeks(2) <= parsed);
+            assert!(parsed <= after + Duration::weeks(2));
+        }
+
+        #[test]
+        fn test_relative_invalid_unit() {
+            let err = parse_expiration_timestamp("5x", AmbiguousTimePolicy::Reject).unwrap_err();
+            assert!(matches!(err, TimestampParseError::UnknownDurationUnit('x')));
+        }
+
+        #[test]
+        fn test_relative_out_of_range() {
+            let err = parse_expiration_timestamp("99999999999999999w", AmbiguousTimePolicy::Reject)
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                TimestampParseError::RelativeDurationOutOfRange
+            ));
+        }
+
+        #[test]
+        fn test_leap_second_rejected() {
+            for leap_second in ["2016-12-31T23:59:60Z", "2030-06-15T23:59:60.5+01:00"] {
+                let err = parse_expiration_timestamp(leap_second, AmbiguousTimePolicy::Reject)
+                    .unwrap_err();
+                assert!(matches!(err, TimestampParseError::LeapSecond));
+            }
+        }
+
+        #[test]
+        fn test_leap_second_normalized() {
+            let parsed =
+                parse_expiration_timestamp("2016-12-31T23:59:60Z", AmbiguousTimePolicy::Normalize)
+                    .unwrap();
+            assert_eq!(parsed, datetime!(2017-01-01 00:00:00 UTC));
+            let parsed = parse_expiration_timestamp(
+                "2017-01-01T00:59:60+01:00",
+                AmbiguousTimePolicy::Normalize,
+            )
             .unwrap();
+            assert_eq!(parsed, datetime!(2017-01-01 00:00:00 UTC));
+        }
 
-        mock_repo
-            .expect_save_url()
-            .with(eq(existing_short_url.clone()))
-            .once()
-            .return_once({
-                let existing_short_url = existing_short_url.clone();
-                move |_| {
-                    Err(SaveUrlError::ItemAlreadyExists(Box::new(
-                        existing_short_url,
-                    )))
-                }
-            });
+        #[test]
+        fn test_end_of_day_rejected() {
+            let err =
+                parse_expiration_timestamp("2030-01-01T24:00:00Z", AmbiguousTimePolicy::Reject)
+                    .unwrap_err();
+            assert!(matches!(err, TimestampParseError::EndOfDay));
+        }
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
-        let (shortened_url, status) = service
-            .put_url(short_id, long_url, &expiration_timestamp_str)
-            .await
+        #[test]
+        fn test_end_of_day_normalized() {
+            let parsed = parse_expiration_timestamp(
+                "2030-12-31T24:00:00-05:00",
+                AmbiguousTimePolicy::Normalize,
+            )
             .unwrap();
+            assert_eq!(parsed, datetime!(2031-01-01 05:00:00 UTC));
+        }
 
-        assert_eq!(
-            shortened_url.shortened_url_id,
-            existing_short_url.short_id.into_inner()
-        );
-        assert_eq!(status, UrlCreationStatus::AlreadyExists);
+        #[test]
+        fn test_ordinary_seconds_unaffected() {
+            let parsed =
+                parse_expiration_timestamp("2030-01-01T23:59:59Z", AmbiguousTimePolicy::Normalize)
+                    .unwrap();
+            assert_eq!(parsed, datetime!(2030-01-01 23:59:59 UTC));
+        }
     }
 
     #[tokio::test]
-    async fn test_put_url_short_id_already_taken() {
-        let mut mock_repo = MockUrlRepository::new();
-        let short_id = "takenurl123".to_owned();
-        let long_url = "https://example.com";
-        let conflicting_short_url =
-            new_short_url("anotherurl123", "https://example.com", Duration::days(1));
-        let expiration_timestamp_str = conflicting_short_url
-            .expiration_time
-            .clone()
-            .into_inner()
-            .format(&Rfc3339)
+    async fn test_put_url_unix_timestamp() {
+        let expiration_time = (OffsetDateTime::now_utc() + Duration::days(1))
+            .replace_nanosecond(0)
             .unwrap();
 
-        let expected_short_url = ShortUrl {
-            short_id: ShortId::new(short_id.clone()).unwrap(),
-            url: Url::parse(long_url).unwrap(),
-            expiration_time: conflicting_short_url.expiration_time.clone(),
-        };
+        let mut mock_repo = MockUrlRepository::new();
         mock_repo
             .expect_save_url()
-            .with(eq(expected_short_url))
+            .withf(move |actual_short_url| {
+                actual_short_url.expiration_time.clone().into_inner() == expiration_time
+            })
             .once()
-            .return_once({
-                let conflicting_short_url = conflicting_short_url.clone();
-                move |_| {
-                    Err(SaveUrlError::ItemAlreadyExists(Box::new(
-                        conflicting_short_url,
-                    )))
-                }
-            });
+            .return_once(Ok);
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
-        let result = service
-            .put_url(short_id, long_url, &expiration_timestamp_str)
+        let service = new_service(mock_repo);
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_time.unix_timestamp().to_string()),
+                    ..PutUrlOptions::default()
+                },
+            )
             .await
-            .unwrap_err();
-
-        assert!(matches!(result, PutUrlError::ShortIdAlreadyTaken));
+            .unwrap();
+        assert_eq!(
+            shortened_url.expiration_timestamp,
+            expiration_time.format(&Rfc3339).unwrap()
+        );
     }
 
     #[tokio::test]
-    async fn test_put_url_invalid_short_id() {
+    async fn test_put_url_expiration_time_in_past() {
         let mock_repo = MockUrlRepository::new();
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
+        let past_timestamp = (OffsetDateTime::now_utc() - Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
         let result = service
             .put_url(
-                "invalid_chars".to_owned(),
+                "valid123".to_owned(),
                 "https://example.com",
-                "2025-01-01T00:00:00Z",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&past_timestamp),
+                    ..PutUrlOptions::default()
+                },
             )
             .await
             .unwrap_err();
         assert!(matches!(
             result,
-            PutUrlError::InvalidShortId(ShortIdValidationError::InvalidCharacters { invalid_chars })
-                if invalid_chars == "_"
+            PutUrlError::InvalidExpirationTime(ExpirationTimeValidationError::InPast)
         ));
     }
 
-    #[tokio::test]
-    async fn test_put_url_invalid_long_url() {
-        let mock_repo = MockUrlRepository::new();
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
-        let result = service
-            .put_url("valid123".to_owned(), "not a url", "1234-01-01T00:00:00Z")
-            .await
-            .unwrap_err();
-        assert!(matches!(result, PutUrlError::InvalidUrl(_)));
+    mod expiration_policy {
+        use time::macros::datetime;
+
+        use super::*;
+
+        #[test]
+        fn test_any_keeps_time() {
+            let time = datetime!(2030-01-01 12:34:56 UTC);
+            assert_eq!(ExpirationPolicy::Any.apply(time).unwrap(), time);
+        }
+
+        #[test]
+        fn test_snap_to_midnight_utc_rounds_up() {
+            let time = datetime!(2030-01-01 12:34:56 UTC);
+            let snapped = ExpirationPolicy::SnapToMidnightUtc.apply(time).unwrap();
+            assert_eq!(snapped, datetime!(2030-01-02 00:00:00 UTC));
+        }
+
+        #[test]
+        fn test_snap_to_midnight_utc_keeps_midnight() {
+            let time = datetime!(2030-01-01 00:00:00 UTC);
+            let snapped = ExpirationPolicy::SnapToMidnightUtc.apply(time).unwrap();
+            assert_eq!(snapped, time);
+        }
+
+        #[test]
+        fn test_snap_to_midnight_utc_rejects_last_day() {
+            let time = datetime!(9999-12-31 12:00:00 UTC);
+            let err = ExpirationPolicy::SnapToMidnightUtc.apply(time).unwrap_err();
+            assert!(matches!(err, ExpirationPolicyError::NoNextMidnightUtc));
+        }
+
+        #[test]
+        fn test_require_midnight_utc_rejects_non_midnight() {
+            let time = datetime!(2030-01-01 00:00:01 UTC);
+            let err = ExpirationPolicy::RequireMidnightUtc
+                .apply(time)
+                .unwrap_err();
+            assert!(matches!(err, ExpirationPolicyError::NotMidnightUtc));
+        }
+
+        #[test]
+        fn test_require_midnight_utc_accepts_midnight() {
+            let time = datetime!(2030-01-01 00:00:00 UTC);
+            let accepted = ExpirationPolicy::RequireMidnightUtc.apply(time).unwrap();
+            assert_eq!(accepted, time);
+        }
+
+        #[test]
+        fn test_from_str() {
+            assert_eq!("any".parse(), Ok(ExpirationPolicy::Any));
+            assert_eq!(
+                "midnight_utc".parse(),
+                Ok(ExpirationPolicy::SnapToMidnightUtc)
+            );
+            assert_eq!(
+                "midnight_utc_strict".parse(),
+                Ok(ExpirationPolicy::RequireMidnightUtc)
+            );
+            assert!("noon_utc".parse::<ExpirationPolicy>().is_err());
+        }
     }
 
     #[tokio::test]
-    async fn test_put_url_invalid_timestamp_format() {
-        let mock_repo = MockUrlRepository::new();
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
-        let result = service
+    async fn test_put_url_snaps_expiration_to_midnight_utc() {
+        let mut mock_repo = MockUrlRepository::new();
+        let short_id = "snapurl123".to_owned();
+        let long_url = "https://example.com";
+        let expiration_time = (OffsetDateTime::now_utc() + Duration::days(1))
+            .replace_time(Time::from_hms(12, 0, 0).unwrap());
+        let expected_expiration_time =
+            expiration_time.replace_time(Time::MIDNIGHT) + Duration::days(1);
+
+        mock_repo
+            .expect_save_url()
+            .withf(move |actual_short_url| {
+                actual_short_url.expiration_time.clone().into_inner() == expected_expiration_time
+            })
+            .once()
+            .return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.expiration.expiration_policy = ExpirationPolicy::SnapToMidnightUtc;
+        let (shortened_url, status) = service
             .put_url(
-                "valid123".to_owned(),
-                "https://example.com",
-                "invalid-timestamp",
+                short_id,
+                long_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_time.format(&Rfc3339).unwrap()),
+                    ..PutUrlOptions::default()
+                },
             )
             .await
-            .unwrap_err();
-        assert!(matches!(result, PutUrlError::TimestampParse(_)));
+            .unwrap();
+
+        assert_eq!(
+            shortened_url.expiration_timestamp,
+            expected_expiration_time.format(&Rfc3339).unwrap()
+        );
+        assert_eq!(status, UrlCreationStatus::NewlyCreated);
     }
 
     #[tokio::test]
-    async fn test_put_url_expiration_time_in_past() {
-        let mock_repo = MockUrlRepository::new();
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
-        let past_timestamp = (OffsetDateTime::now_utc() - Duration::days(1))
+    async fn test_put_url_rejects_non_midnight_utc_expiration() {
+        let mut service = new_service(MockUrlRepository::new());
+        service.options.expiration.expiration_policy = ExpirationPolicy::RequireMidnightUtc;
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .replace_time(Time::from_hms(12, 0, 0).unwrap())
             .format(&Rfc3339)
             .unwrap();
         let result = service
             .put_url(
                 "valid123".to_owned(),
                 "https://example.com",
-                &past_timestamp,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
             )
             .await
             .unwrap_err();
         assert!(matches!(
             result,
-            PutUrlError::InvalidExpirationTime(ExpirationTimeValidationError::InPast)
+            PutUrlError::DisallowedExpirationTime(ExpirationPolicyError::NotMidnightUtc)
         ));
     }
 
@@ -540,7 +4809,10 @@ mod tests {
         let mut mock_repo = MockUrlRepository::new();
         let short_id = "testurl123".to_owned();
         let long_url = "https://example.com";
-        let expected_short_url = new_short_url(&short_id, long_url, Duration::days(1));
+        let expected_short_url = url_repo::ShortUrl {
+            original_url: Some(long_url.to_owned()),
+            ..url_repo::ShortUrl::new_for_test(&short_id, long_url, Duration::days(1))
+        };
         let expiration_timestamp_str = expected_short_url
             .expiration_time
             .clone()
@@ -554,11 +4826,16 @@ mod tests {
             .once()
             .return_once(|_| Err(SaveUrlError::Internal(anyhow::anyhow!("test failure"))));
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let result = service
-            .put_url(short_id, long_url, &expiration_timestamp_str)
+            .put_url(
+                short_id,
+                long_url,
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp_str),
+                    ..PutUrlOptions::default()
+                },
+            )
             .await
             .unwrap_err();
         assert!(matches!(result, PutUrlError::Internal(_)));
@@ -580,15 +4857,15 @@ mod tests {
             .once()
             .return_once(Ok);
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
-        let result = service
-            .post_url(long_url, &expiration_timestamp)
+        let service = new_service(mock_repo);
+        let (result, creation_status, attempts) = service
+            .post_url(long_url, Some(&expiration_timestamp), None)
             .await
             .unwrap();
         assert_eq!(result.long_url, long_url);
         assert_eq!(result.expiration_timestamp, expiration_timestamp);
+        assert_eq!(creation_status, UrlCreationStatus::NewlyCreated);
+        assert_eq!(attempts, 1);
     }
 
     #[tokio::test]
@@ -607,25 +4884,110 @@ mod tests {
             .once()
             .return_once(|short_url| Err(SaveUrlError::ItemAlreadyExists(Box::new(short_url))));
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
-        let result = service
-            .post_url(long_url, &expiration_timestamp)
+        let service = new_service(mock_repo);
+        let (result, creation_status, attempts) = service
+            .post_url(long_url, Some(&expiration_timestamp), None)
             .await
             .unwrap();
         assert_eq!(result.long_url, long_url);
         assert_eq!(result.expiration_timestamp, expiration_timestamp);
+        assert_eq!(creation_status, UrlCreationStatus::AlreadyExists);
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_post_url_retries_on_collision() {
+        let long_url = "https://example.com/";
+        let expiration_time = OffsetDateTime::now_utc() + Duration::days(1);
+        let expiration_timestamp = expiration_time.format(&Rfc3339).unwrap();
+
+        let mut seq = mockall::Sequence::new();
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_save_url()
+            .once()
+            .in_sequence(&mut seq)
+            .return_once(|short_url| {
+                let conflicting_short_url = ShortUrl {
+                    url: Url::parse("https://gsconrad.com").unwrap(),
+                    ..short_url
+                };
+                Err(SaveUrlError::ItemAlreadyExists(Box::new(
+                    conflicting_short_url,
+                )))
+            });
+        mock_repo
+            .expect_save_url()
+            .once()
+            .in_sequence(&mut seq)
+            .return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (result, _, attempts) = service
+            .post_url(long_url, Some(&expiration_timestamp), None)
+            .await
+            .unwrap();
+        assert_eq!(result.long_url, long_url);
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_post_url_exhausts_configured_retry_attempts() {
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().times(5).returning(|short_url| {
+            let conflicting_short_url = ShortUrl {
+                url: Url::parse("https://gsconrad.com").unwrap(),
+                ..short_url
+            };
+            Err(SaveUrlError::ItemAlreadyExists(Box::new(
+                conflicting_short_url,
+            )))
+        });
+
+        let mut service = new_service(mock_repo);
+        service.options.short_ids.post_retry_attempts = 5;
+        let err = service
+            .post_url("https://example.com/", Some(&expiration_timestamp), None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, PostUrlError::Internal(err) if err.to_string() == "Exhausted retry attempts")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_url_default_ttl() {
+        let long_url = "https://example.com/";
+
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_save_url()
+            .withf(move |actual_short_url| {
+                let expected_time = OffsetDateTime::now_utc() + Duration::hours(24);
+                let actual_time = actual_short_url.expiration_time.clone().into_inner();
+                // NOTE: slight tolerance is allowed in case of slow tests
+                actual_short_url.url.as_str() == long_url
+                    && (expected_time - actual_time).abs() < Duration::seconds(5)
+            })
+            .once()
+            .return_once(Ok);
+
+        let service = new_service(mock_repo);
+        let (result, _, attempts) = service.post_url(long_url, None, None).await.unwrap();
+        assert_eq!(result.long_url, long_url);
+        assert_eq!(attempts, 1);
     }
 
     #[tokio::test]
     async fn test_post_url_invalid_long_url() {
         let mock_repo = MockUrlRepository::new();
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let result = service
-            .post_url("not a url", "1234-01-01T00:00:00Z")
+            .post_url("not a url", Some("1234-01-01T00:00:00Z"), None)
             .await
             .unwrap_err();
         assert!(matches!(result, PostUrlError::InvalidUrl(_)));
@@ -634,11 +4996,9 @@ mod tests {
     #[tokio::test]
     async fn test_post_url_invalid_timestamp_format() {
         let mock_repo = MockUrlRepository::new();
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let result = service
-            .post_url("https://example.com", "invalid-timestamp")
+            .post_url("https://example.com", Some("invalid-timestamp"), None)
             .await
             .unwrap_err();
         assert!(matches!(result, PostUrlError::TimestampParse(_)));
@@ -647,14 +5007,12 @@ mod tests {
     #[tokio::test]
     async fn test_post_url_expiration_time_in_past() {
         let mock_repo = MockUrlRepository::new();
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let past_timestamp = (OffsetDateTime::now_utc() - Duration::days(1))
             .format(&Rfc3339)
             .unwrap();
         let result = service
-            .post_url("https://example.com", &past_timestamp)
+            .post_url("https://example.com", Some(&past_timestamp), None)
             .await
             .unwrap_err();
         assert!(matches!(
@@ -679,35 +5037,128 @@ mod tests {
             .once()
             .return_once(|_| Err(SaveUrlError::Internal(anyhow::anyhow!("test failure"))));
 
-        let service = UrlRestServiceImpl {
-            url_repo: Arc::new(mock_repo),
-        };
+        let service = new_service(mock_repo);
         let result = service
-            .post_url(long_url, &expiration_timestamp)
+            .post_url(long_url, Some(&expiration_timestamp), None)
             .await
             .unwrap_err();
         assert!(matches!(result, PostUrlError::Internal(_)));
     }
 
+    #[tokio::test]
+    async fn test_delete_url_success() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_delete_url()
+            .with(eq("testurl123"))
+            .once()
+            .return_once(|_| Ok(true));
+
+        let service = new_service(mock_repo);
+        service.delete_url("testurl123").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_url_not_found() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_delete_url()
+            .with(eq("testurl123"))
+            .once()
+            .return_once(|_| Ok(false));
+
+        let service = new_service(mock_repo);
+        let delete_url_err = service.delete_url("testurl123").await.unwrap_err();
+        assert!(matches!(delete_url_err, DeleteUrlError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_url_db_error() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_delete_url()
+            .with(eq("testurl123"))
+            .once()
+            .return_once(|_| Err(anyhow::anyhow!("test error")));
+
+        let service = new_service(mock_repo);
+        let delete_url_err = service.delete_url("testurl123").await.unwrap_err();
+        assert!(
+            matches!(delete_url_err, DeleteUrlError::Db(err) if err.to_string() == "test error")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expire_url() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo
+            .expect_expire_url()
+            .with(eq("testurl123"))
+            .once()
+            .return_once(|_| Ok(true));
+        mock_repo
+            .expect_expire_url()
+            .with(eq("missing123"))
+            .once()
+            .return_once(|_| Ok(false));
+
+        let service = new_service(mock_repo);
+        service.expire_url("testurl123").await.unwrap();
+        let expire_url_err = service.expire_url("missing123").await.unwrap_err();
+        assert!(matches!(expire_url_err, ExpireUrlError::NotFound));
+    }
+
     #[test]
-    fn test_shortened_url_try_from_short_url() {
+    fn test_shortened_url_new() {
         let short_id = "abcDEF12";
         let long_url = "https://example.com/";
-        let expiration_time = OffsetDateTime::now_utc() + Duration::days(2);
+        let short_url = url_repo::ShortUrl::new_for_test(short_id, long_url, Duration::days(2));
+        let expiration_time = short_url.expiration_time.clone().into_inner();
 
-        let short_url = url_repo::ShortUrl {
-            short_id: ShortId::new(short_id.to_owned()).unwrap(),
-            url: Url::parse(long_url).unwrap(),
-            expiration_time: ExpirationTime::new(expiration_time).unwrap(),
-        };
-
-        let shortened_url: ShortenedUrl = short_url.try_into().unwrap();
+        let shortened_url = ShortenedUrl::new(short_url, None).unwrap();
 
         assert_eq!(shortened_url.shortened_url_id, short_id);
+        assert_eq!(shortened_url.short_url, short_id);
         assert_eq!(shortened_url.long_url, long_url);
         assert_eq!(
             shortened_url.expiration_timestamp,
             expiration_time.format(&Rfc3339).unwrap()
         );
     }
+
+    #[test]
+    fn test_shortened_url_new_with_base_url() {
+        let short_url =
+            url_repo::ShortUrl::new_for_test("abcDEF12", "https://example.com/", Duration::days(2));
+        let base_url = Url::parse("https://sto.pid/").unwrap();
+
+        let shortened_url = ShortenedUrl::new(short_url, Some(&base_url)).unwrap();
+
+        assert_eq!(shortened_url.shortened_url_id, "abcDEF12");
+        assert_eq!(shortened_url.short_url, "https://sto.pid/abcDEF12");
+    }
+
+    #[tokio::test]
+    async fn test_put_url_returns_full_short_url() {
+        let mut mock_repo = MockUrlRepository::new();
+        mock_repo.expect_save_url().once().return_once(Ok);
+
+        let mut service = new_service(mock_repo);
+        service.options.base_url = Some(Url::parse("https://sto.pid/links/").unwrap());
+        let expiration_timestamp = (OffsetDateTime::now_utc() + Duration::days(1))
+            .format(&Rfc3339)
+            .unwrap();
+        let (shortened_url, _) = service
+            .put_url(
+                "valid123".to_owned(),
+                "https://example.com/",
+                PutUrlOptions {
+                    expiration_timestamp: Some(&expiration_timestamp),
+                    ..PutUrlOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(shortened_url.short_url, "https://sto.pid/links/valid123");
+    }
 }
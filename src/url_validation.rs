@@ -0,0 +1,181 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use thiserror::Error;
+use url::{Host, Url};
+
+use crate::url_service::host_matches_domain;
+
+/// Schemes that never point at a remote resource, and so are never worth redirecting to.
+const DISALLOWED_SCHEMES: [&str; 2] = ["file", "data"];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DisallowedUrlError {
+    #[error("the {0} scheme is not allowed")]
+    Scheme(String),
+    #[error("{0} is a loopback, link-local, private, or otherwise non-global host")]
+    PrivateHost(String),
+}
+
+/// Rejects destination URLs that name an internal host outright: a literal IP address outside
+/// the globally routable ranges, or a `localhost` name.
+///
+/// NOTE: this is deliberately not a full SSRF guard. Hostnames are never resolved,
+/// so names that resolve to internal addresses (e.g. `127.0.0.1.nip.io`) pass;
+/// resolving here wouldn't settle it either, since the DNS answer at redirect time
+/// (in the client's network) could differ from the one seen here.
+pub(crate) fn check_public_destination(url: &Url) -> Result<(), DisallowedUrlError> {
+    if DISALLOWED_SCHEMES
+        .iter()
+        .any(|scheme| scheme.eq_ignore_ascii_case(url.scheme()))
+    {
+        return Err(DisallowedUrlError::Scheme(url.scheme().to_owned()));
+    }
+
+    let is_private = match url.host() {
+        None => false,
+        Some(Host::Domain(domain)) => {
+            host_matches_domain(domain.strip_suffix('.').unwrap_or(domain), "localhost")
+        }
+        Some(Host::Ipv4(ip)) => is_private_ipv4(ip),
+        Some(Host::Ipv6(ip)) => is_private_ipv6(ip),
+    };
+    if is_private {
+        return Err(DisallowedUrlError::PrivateHost(
+            url.host_str().unwrap_or_default().to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+const fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_private()
+        || ip.is_multicast()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        // NOTE: "this network" (0.0.0.0/8, including the unspecified address)
+        || a == 0
+        // NOTE: shared address space for carrier-grade NAT (100.64.0.0/10)
+        || (a == 100 && b & 0b1100_0000 == 64)
+        // NOTE: IETF protocol assignments (192.0.0.0/24)
+        || (a == 192 && b == 0 && c == 0)
+        // NOTE: benchmarking (198.18.0.0/15)
+        || (a == 198 && b & 0b1111_1110 == 18)
+        // NOTE: reserved for future use (240.0.0.0/4)
+        || a >= 240
+}
+
+fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    /// The well-known NAT64 prefix (`64:ff9b::/96`), which embeds an IPv4 address.
+    const NAT64_PREFIX: [u16; 6] = [0x64, 0xff9b, 0, 0, 0, 0];
+
+    let segments = ip.segments();
+    let [.., high, low] = segments;
+    let nat64_ipv4 = (segments[..6] == NAT64_PREFIX)
+        .then(|| Ipv4Addr::from_bits((u32::from(high) << 16) | u32::from(low)));
+    ip.to_ipv4_mapped().is_some_and(is_private_ipv4)
+        || nat64_ipv4.is_some_and(is_private_ipv4)
+        || ip.is_loopback()
+        || ip.is_unicast_link_local()
+        || ip.is_unique_local()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // NOTE: documentation (2001:db8::/32)
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn check(url: &str) -> Result<(), DisallowedUrlError> {
+        check_public_destination(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_public_urls() {
+        assert_eq!(check("https://example.com/"), Ok(()));
+        assert_eq!(check("http://93.184.215.14/"), Ok(()));
+        assert_eq!(check("http://[2606:4700::1111]/"), Ok(()));
+        assert_eq!(check("http://100.128.0.1/"), Ok(()));
+        assert_eq!(check("http://[64:ff9b::5db8:d70e]/"), Ok(()));
+        assert_eq!(check("https://localhost.example.com/"), Ok(()));
+    }
+
+    #[test]
+    fn test_disallowed_schemes() {
+        assert_eq!(
+            check("file:///etc/passwd"),
+            Err(DisallowedUrlError::Scheme("file".to_owned()))
+        );
+        assert_eq!(
+            check("data:text/html,hi"),
+            Err(DisallowedUrlError::Scheme("data".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_private_ipv4() {
+        for url in [
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://172.16.0.1/",
+            "http://192.168.1.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://0.0.0.0/",
+            "http://0.1.2.3/",
+            "http://100.64.0.1/",
+            "http://100.127.255.254/",
+            "http://192.0.0.8/",
+            "http://192.0.2.1/",
+            "http://198.18.0.1/",
+            "http://224.0.0.1/",
+            "http://240.0.0.1/",
+            "http://255.255.255.255/",
+        ] {
+            assert!(
+                matches!(check(url), Err(DisallowedUrlError::PrivateHost(_))),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_private_ipv6() {
+        for url in [
+            "http://[::1]/",
+            "http://[fe80::1]/",
+            "http://[fd00::1]/",
+            "http://[::]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[::ffff:100.64.0.1]/",
+            "http://[64:ff9b::a00:1]/",
+            "http://[ff02::1]/",
+            "http://[2001:db8::1]/",
+        ] {
+            assert!(
+                matches!(check(url), Err(DisallowedUrlError::PrivateHost(_))),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_localhost_names() {
+        for url in [
+            "http://localhost/",
+            "http://LOCALHOST:8080/",
+            "http://localhost./",
+            "http://api.localhost/",
+        ] {
+            assert!(
+                matches!(check(url), Err(DisallowedUrlError::PrivateHost(_))),
+                "{url}"
+            );
+        }
+    }
+}